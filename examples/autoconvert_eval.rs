@@ -0,0 +1,79 @@
+//! Evaluates the autoconvert decision engine on a labelled TSV corpus.
+//!
+//! Usage:
+//!
+//! ```text
+//! cargo run --example autoconvert_eval -- <corpus.tsv> [--baseline <baseline.txt>]
+//! ```
+//!
+//! Prints precision, recall, the confusion matrix and misclassified cases.
+//! With `--baseline`, exits with a non zero status when a metric regresses.
+
+use std::{fs, process::ExitCode};
+
+use rust_switcher::domain::text::{
+    autoconvert::language_detector,
+    autoconvert_eval::{evaluate, parse_baseline, parse_corpus, regressions, render_report},
+};
+
+const USAGE: &str = "usage: autoconvert_eval <corpus.tsv> [--baseline <baseline.txt>]";
+
+struct Args {
+    corpus: String,
+    baseline: Option<String>,
+}
+
+fn parse_args() -> Option<Args> {
+    let mut it = std::env::args().skip(1);
+    let mut corpus = None;
+    let mut baseline = None;
+
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--baseline" => baseline = Some(it.next()?),
+            _ if corpus.is_none() => corpus = Some(arg),
+            _ => return None,
+        }
+    }
+
+    Some(Args {
+        corpus: corpus?,
+        baseline,
+    })
+}
+
+fn run(args: &Args) -> Result<bool, String> {
+    let read = |path: &str| fs::read_to_string(path).map_err(|e| format!("{path}: {e}"));
+
+    let entries =
+        parse_corpus(&read(&args.corpus)?).map_err(|e| format!("{}: {e}", args.corpus))?;
+    let report = evaluate(language_detector(), &entries);
+    print!("{}", render_report(&report));
+
+    let Some(path) = args.baseline.as_deref() else {
+        return Ok(true);
+    };
+
+    let baseline = parse_baseline(&read(path)?).map_err(|e| format!("{path}: {e}"))?;
+    let failed = regressions(&report.metrics, &baseline);
+    for msg in &failed {
+        eprintln!("regression: {msg}");
+    }
+    Ok(failed.is_empty())
+}
+
+fn main() -> ExitCode {
+    let Some(args) = parse_args() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
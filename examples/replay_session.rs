@@ -0,0 +1,75 @@
+//! Replays a recorded typing session through the typing steps of the app.
+//!
+//! Usage:
+//!
+//! ```text
+//! cargo run --example replay_session -- <session.jsonl> [--check]
+//! ```
+//!
+//! Prints every decision with the line of the event that caused it.
+//! With `--check`, exits with a non zero status when the decisions differ from the recorded ones.
+
+use std::{fs, process::ExitCode};
+
+use rust_switcher::domain::text::session::{parse_session, replay};
+
+const USAGE: &str = "usage: replay_session <session.jsonl> [--check]";
+
+struct Args {
+    session: String,
+    check: bool,
+}
+
+fn parse_args() -> Option<Args> {
+    let mut session = None;
+    let mut check = false;
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ if session.is_none() => session = Some(arg),
+            _ => return None,
+        }
+    }
+
+    Some(Args {
+        session: session?,
+        check,
+    })
+}
+
+fn run(args: &Args) -> Result<bool, String> {
+    let path = &args.session;
+    let src = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let lines = parse_session(&src).map_err(|e| format!("{path}: {e}"))?;
+
+    let replay = replay(&lines);
+    for replayed in &replay.replayed {
+        println!("line {}: {}", replayed.line, replayed.decision);
+    }
+
+    if !args.check || replay.matches_recording() {
+        return Ok(true);
+    }
+    eprintln!("decisions differ from the recording:");
+    for recorded in &replay.recorded {
+        eprintln!("  recorded: {recorded}");
+    }
+    Ok(false)
+}
+
+fn main() -> ExitCode {
+    let Some(args) = parse_args() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -10,10 +10,14 @@ use std::collections::VecDeque;
 use windows::Win32::{
     Foundation::HWND,
     Graphics::Gdi::{HBRUSH, HFONT},
-    UI::WindowsAndMessaging::HMENU,
+    UI::WindowsAndMessaging::{HMENU, KBDLLHOOKSTRUCT},
 };
 
-use crate::config;
+use crate::{
+    config,
+    domain::text::{convert::SelectionRepeat, pipeline::TypingState},
+    input::sequence_matcher::SequenceMatcher,
+};
 
 #[derive(Debug, Clone)]
 pub struct UiError {
@@ -28,6 +32,8 @@ pub enum HotkeySlot {
     Pause,
     Selection,
     SwitchLayout,
+    UndoConversion,
+    LastPhrase,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -36,6 +42,8 @@ pub struct HotkeyValues {
     pub pause: Option<config::Hotkey>,
     pub selection: Option<config::Hotkey>,
     pub switch_layout: Option<config::Hotkey>,
+    pub undo_conversion: Option<config::Hotkey>,
+    pub last_phrase: Option<config::Hotkey>,
 }
 
 impl HotkeyValues {
@@ -45,6 +53,8 @@ impl HotkeyValues {
             pause: cfg.hotkey_pause,
             selection: cfg.hotkey_convert_selection,
             switch_layout: cfg.hotkey_switch_layout,
+            undo_conversion: cfg.hotkey_undo_conversion,
+            last_phrase: cfg.hotkey_convert_last_phrase,
         }
     }
 
@@ -55,6 +65,8 @@ impl HotkeyValues {
             HotkeySlot::Pause => self.pause,
             HotkeySlot::Selection => self.selection,
             HotkeySlot::SwitchLayout => self.switch_layout,
+            HotkeySlot::UndoConversion => self.undo_conversion,
+            HotkeySlot::LastPhrase => self.last_phrase,
         }
     }
 
@@ -64,6 +76,8 @@ impl HotkeyValues {
             HotkeySlot::Pause => self.pause = hk,
             HotkeySlot::Selection => self.selection = hk,
             HotkeySlot::SwitchLayout => self.switch_layout = hk,
+            HotkeySlot::UndoConversion => self.undo_conversion = hk,
+            HotkeySlot::LastPhrase => self.last_phrase = hk,
         }
     }
 }
@@ -74,24 +88,30 @@ pub struct HotkeySequenceValues {
     pub pause: Option<config::HotkeySequence>,
     pub selection: Option<config::HotkeySequence>,
     pub switch_layout: Option<config::HotkeySequence>,
+    pub undo_conversion: Option<config::HotkeySequence>,
+    pub last_phrase: Option<config::HotkeySequence>,
 }
 
 impl HotkeySequenceValues {
     pub fn from_config(cfg: &config::Config) -> Self {
         Self {
-            last_word: cfg.hotkey_convert_last_word_sequence,
-            pause: cfg.hotkey_pause_sequence,
-            selection: cfg.hotkey_convert_selection_sequence,
-            switch_layout: cfg.hotkey_switch_layout_sequence,
+            last_word: cfg.hotkey_convert_last_word_sequence.clone(),
+            pause: cfg.hotkey_pause_sequence.clone(),
+            selection: cfg.hotkey_convert_selection_sequence.clone(),
+            switch_layout: cfg.hotkey_switch_layout_sequence.clone(),
+            undo_conversion: cfg.hotkey_undo_conversion_sequence.clone(),
+            last_phrase: cfg.hotkey_convert_last_phrase_sequence.clone(),
         }
     }
 
-    pub fn get(&self, slot: HotkeySlot) -> Option<config::HotkeySequence> {
+    pub fn get(&self, slot: HotkeySlot) -> Option<&config::HotkeySequence> {
         match slot {
-            HotkeySlot::LastWord => self.last_word,
-            HotkeySlot::Pause => self.pause,
-            HotkeySlot::Selection => self.selection,
-            HotkeySlot::SwitchLayout => self.switch_layout,
+            HotkeySlot::LastWord => self.last_word.as_ref(),
+            HotkeySlot::Pause => self.pause.as_ref(),
+            HotkeySlot::Selection => self.selection.as_ref(),
+            HotkeySlot::SwitchLayout => self.switch_layout.as_ref(),
+            HotkeySlot::UndoConversion => self.undo_conversion.as_ref(),
+            HotkeySlot::LastPhrase => self.last_phrase.as_ref(),
         }
     }
 
@@ -101,6 +121,8 @@ impl HotkeySequenceValues {
             HotkeySlot::Pause => self.pause = seq,
             HotkeySlot::Selection => self.selection = seq,
             HotkeySlot::SwitchLayout => self.switch_layout = seq,
+            HotkeySlot::UndoConversion => self.undo_conversion = seq,
+            HotkeySlot::LastPhrase => self.last_phrase = seq,
         }
     }
 }
@@ -128,18 +150,20 @@ pub struct RuntimeChordCapture {
     pub saw_non_mod: bool,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
-pub struct SequenceProgress {
-    pub waiting_second: bool,
-    pub first_tick_ms: u64,
-}
-
-#[derive(Debug, Default)]
-pub struct HotkeySequenceProgress {
-    pub last_word: SequenceProgress,
-    pub pause: SequenceProgress,
-    pub selection: SequenceProgress,
-    pub switch_layout: SequenceProgress,
+/// Key with a tap or hold binding that is held down and not resolved yet.
+#[derive(Debug, Clone, Copy)]
+pub struct PendingKeyPress {
+    pub chord: config::HotkeyChord,
+    pub down_tick_ms: u64,
+    /// Hook data of the key-down, replayed when the press turns out to be ordinary typing.
+    pub kb: KBDLLHOOKSTRUCT,
+    /// Side-specific modifiers down at the key-down, as `MODVK_*` bits; the key is replayed with
+    /// them.
+    pub mods_vks: u32,
+    /// A key-down binding of the same chord fired, so the key is not replayed.
+    pub down_matched: bool,
+    /// A hold binding fired; the release only ends the press.
+    pub hold_fired: bool,
 }
 
 /// Per-window state used throughout the application.
@@ -155,9 +179,16 @@ pub struct AppState {
     pub hotkeys: HotkeyEdits,
     pub buttons: Buttons,
 
-    pub autoconvert_enabled: bool,
     pub errors: VecDeque<UiError>,
 
+    /// Autoconvert settings and state, undo history and the span of the last Convert press.
+    ///
+    /// Its steps run on the input journal shared with the keyboard hook.
+    pub typing: TypingState,
+
+    /// Selection replaced by the last Convert press, used to cycle alternatives on repeat.
+    pub selection_repeat: Option<SelectionRepeat>,
+
     /// Temporary hotkeys currently shown in UI. Committed on Apply.
     pub hotkey_values: HotkeyValues,
     pub hotkey_sequence_values: HotkeySequenceValues,
@@ -165,19 +196,17 @@ pub struct AppState {
     /// Which hotkey edit is currently capturing input.
     pub hotkey_capture: HotkeyCaptureUi,
 
-    /// Active (already applied) sequences used by the runtime hotkey recognizer.
+    /// Runtime hotkey recognizer over the active (already applied) sequences.
     /// This must NOT be tied to temporary edits in the UI.
-    pub active_hotkey_sequences: HotkeySequenceValues,
+    pub hotkey_matcher: SequenceMatcher<HotkeySlot>,
 
     /// Runtime state for modifier-only chord detection.
     pub runtime_chord_capture: RuntimeChordCapture,
 
-    /// Runtime state for chord sequence progress.
-    pub hotkey_sequence_progress: HotkeySequenceProgress,
+    /// Key held down while its tap or hold binding is undecided.
+    pub pending_key_press: Option<PendingKeyPress>,
 
     pub active_switch_layout_sequence: Option<config::HotkeySequence>,
-    pub switch_layout_waiting_second: bool,
-    pub switch_layout_first_tick_ms: u64,
 
     pub current_theme_dark: bool,
     // Cached theme brushes (must be deleted on window destroy)
@@ -204,6 +233,8 @@ pub struct HotkeyEdits {
     pub pause: HWND,
     pub selection: HWND,
     pub switch_layout: HWND,
+    pub undo_conversion: HWND,
+    pub last_phrase: HWND,
 }
 
 #[derive(Debug, Default)]
@@ -227,6 +258,8 @@ pub enum ControlId {
     HotkeyPause = 1202,
     HotkeySelection = 1203,
     HotkeySwitchLayout = 1204,
+    HotkeyUndoConversion = 1205,
+    HotkeyLastPhrase = 1206,
 
     Apply = 1101,
     Cancel = 1102,
@@ -247,6 +280,8 @@ impl ControlId {
             1202 => Some(Self::HotkeyPause),
             1203 => Some(Self::HotkeySelection),
             1204 => Some(Self::HotkeySwitchLayout),
+            1205 => Some(Self::HotkeyUndoConversion),
+            1206 => Some(Self::HotkeyLastPhrase),
 
             1101 => Some(Self::Apply),
             1102 => Some(Self::Cancel),
@@ -8,41 +8,42 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    domain::text::autoconvert::{
+        AutoconvertExceptions, DEFAULT_CONTEXT_WEIGHT, DEFAULT_MAX_PAUSE_IN_WORD_MS,
+        DEFAULT_PAUSE_AFTER_REVERTS,
+    },
+    input::key_code,
+    input_journal::DEFAULT_AUTOCONVERT_TRIGGER_CHARS,
+};
+
 const APP_DIR: &str = "RustSwitcher";
 const CONFIG_FILE: &str = "config.json";
+const SESSION_FILE: &str = "session.jsonl";
+
+pub use crate::{
+    domain::text::pipeline::{ConvertRepeatMode, DEFAULT_CONVERT_REPEAT_WINDOW_MS},
+    input::{
+        hotkey_sequence::{
+            DEFAULT_MAX_GAP_MS, DEFAULT_TAP_MAX_MS, HotkeyChord, HotkeyPress, HotkeySequence,
+            HotkeyStep, MODVK_LCTRL, MODVK_RALT,
+        },
+        key_code::KeyCode,
+    },
+};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Hotkey {
-    pub vk: u32,
+    /// Older configs call it `vk` and store a Win32 virtual-key number, 0 for none.
+    #[serde(
+        default,
+        alias = "vk",
+        deserialize_with = "key_code::deserialize_optional"
+    )]
+    pub key: Option<KeyCode>,
     pub mods: u32,
 }
 
-pub const MODVK_LCTRL: u32 = 1 << 0;
-pub const MODVK_RCTRL: u32 = 1 << 1;
-pub const MODVK_LSHIFT: u32 = 1 << 2;
-pub const MODVK_RSHIFT: u32 = 1 << 3;
-pub const MODVK_LALT: u32 = 1 << 4;
-pub const MODVK_RALT: u32 = 1 << 5;
-pub const MODVK_LWIN: u32 = 1 << 6;
-pub const MODVK_RWIN: u32 = 1 << 7;
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-pub struct HotkeyChord {
-    pub mods: u32,
-
-    #[serde(default)]
-    pub mods_vks: u32,
-
-    pub vk: Option<u32>,
-}
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-pub struct HotkeySequence {
-    pub first: HotkeyChord,
-    pub second: Option<HotkeyChord>,
-    pub max_gap_ms: u32,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub delay_ms: u32,
@@ -55,6 +56,10 @@ pub struct Config {
     pub hotkey_convert_selection: Option<Hotkey>,
     pub hotkey_switch_layout: Option<Hotkey>,
     pub hotkey_pause: Option<Hotkey>,
+    #[serde(default)]
+    pub hotkey_undo_conversion: Option<Hotkey>,
+    #[serde(default)]
+    pub hotkey_convert_last_phrase: Option<Hotkey>,
 
     #[serde(default)]
     pub hotkey_convert_last_word_sequence: Option<HotkeySequence>,
@@ -64,9 +69,92 @@ pub struct Config {
     pub hotkey_convert_selection_sequence: Option<HotkeySequence>,
     #[serde(default)]
     pub hotkey_switch_layout_sequence: Option<HotkeySequence>,
+    #[serde(default)]
+    pub hotkey_undo_conversion_sequence: Option<HotkeySequence>,
+    #[serde(default)]
+    pub hotkey_convert_last_phrase_sequence: Option<HotkeySequence>,
+
+    #[serde(default)]
+    pub autoconvert_exceptions: AutoconvertExceptions,
+
+    /// Consecutive Backspace reverts after which autoconvert pauses. `0` disables the pause.
+    #[serde(default = "default_autoconvert_pause_after_reverts")]
+    pub autoconvert_pause_after_reverts: u32,
+
+    /// Weight of the preceding words in autoconvert decisions, `0.0..=1.0`. `0` judges words alone.
+    #[serde(default = "default_autoconvert_context_weight")]
+    pub autoconvert_context_weight: f64,
+
+    /// Punctuation and symbols that make autoconvert judge the word typed before them.
+    /// Whitespace always does.
+    #[serde(default = "default_autoconvert_trigger_chars")]
+    pub autoconvert_trigger_chars: String,
+
+    /// Longest pause inside a word, in milliseconds, for autoconvert to still judge it.
+    /// `0` disables the check.
+    #[serde(default = "default_autoconvert_max_pause_in_word_ms")]
+    pub autoconvert_max_pause_in_word_ms: u32,
+
+    /// Inactivity after which the typed text is forgotten, in milliseconds. `0` keeps it.
+    #[serde(default)]
+    pub journal_idle_expiry_ms: u32,
+
+    /// Records typed text, focus changes and autoconvert decisions to `session.jsonl` next to
+    /// the config, for reproducing misfires. Off by default, since the file holds what was typed.
+    #[serde(default)]
+    pub record_session: bool,
+
+    /// Executables, such as `keepass.exe`, whose windows are never journalled. Password fields
+    /// are never journalled in any program.
+    #[serde(default = "default_privacy_blocked_processes")]
+    pub privacy_blocked_processes: Vec<String>,
+
+    #[serde(default)]
+    pub convert_repeat: ConvertRepeatMode,
+
+    /// Time after a Convert press during which the next press extends the span (`extend` mode).
+    #[serde(default = "default_convert_repeat_window_ms")]
+    pub convert_repeat_window_ms: u32,
+}
+
+fn default_autoconvert_pause_after_reverts() -> u32 {
+    DEFAULT_PAUSE_AFTER_REVERTS
+}
+
+fn default_autoconvert_context_weight() -> f64 {
+    DEFAULT_CONTEXT_WEIGHT
+}
+
+fn default_autoconvert_trigger_chars() -> String {
+    DEFAULT_AUTOCONVERT_TRIGGER_CHARS.to_string()
+}
+
+fn default_privacy_blocked_processes() -> Vec<String> {
+    [
+        "keepass.exe",
+        "keepassxc.exe",
+        "1password.exe",
+        "bitwarden.exe",
+    ]
+    .map(str::to_string)
+    .to_vec()
+}
+
+fn default_autoconvert_max_pause_in_word_ms() -> u32 {
+    DEFAULT_MAX_PAUSE_IN_WORD_MS
+}
+
+fn default_convert_repeat_window_ms() -> u32 {
+    DEFAULT_CONVERT_REPEAT_WINDOW_MS
 }
 impl Default for Config {
     fn default() -> Self {
+        const LSHIFT: HotkeyChord = HotkeyChord {
+            mods: 4,
+            mods_vks: 4,
+            key: None,
+        };
+
         Self {
             delay_ms: 100,
             start_minimized: false,
@@ -76,54 +164,51 @@ impl Default for Config {
             hotkey_pause: None,
             hotkey_convert_last_word: None,
             hotkey_convert_selection: None,
+            hotkey_undo_conversion: None,
+            hotkey_convert_last_phrase: None,
 
-            hotkey_convert_last_word_sequence: Some(HotkeySequence {
-                first: HotkeyChord {
-                    mods: 4,
-                    mods_vks: 4,
-                    vk: None,
-                },
-                second: Some(HotkeyChord {
-                    mods: 4,
-                    mods_vks: 4,
-                    vk: None,
-                }),
-                max_gap_ms: 1000,
-            }),
-
-            hotkey_pause_sequence: Some(HotkeySequence {
-                first: HotkeyChord {
+            hotkey_convert_last_word_sequence: Some(HotkeySequence::of(
+                [LSHIFT; 2],
+                DEFAULT_MAX_GAP_MS,
+            )),
+
+            hotkey_pause_sequence: Some(HotkeySequence::of(
+                [HotkeyChord {
                     mods: 4,
                     mods_vks: 12,
-                    vk: None,
-                },
-                second: None,
-                max_gap_ms: 1000,
-            }),
-
-            hotkey_convert_selection_sequence: Some(HotkeySequence {
-                first: HotkeyChord {
-                    mods: 4,
-                    mods_vks: 4,
-                    vk: None,
-                },
-                second: Some(HotkeyChord {
-                    mods: 4,
-                    mods_vks: 4,
-                    vk: None,
-                }),
-                max_gap_ms: 1000,
-            }),
-
-            hotkey_switch_layout_sequence: Some(HotkeySequence {
-                first: HotkeyChord {
+                    key: None,
+                }],
+                DEFAULT_MAX_GAP_MS,
+            )),
+
+            hotkey_convert_selection_sequence: Some(HotkeySequence::of(
+                [LSHIFT; 2],
+                DEFAULT_MAX_GAP_MS,
+            )),
+
+            hotkey_switch_layout_sequence: Some(HotkeySequence::of(
+                [HotkeyChord {
                     mods: 0,
                     mods_vks: 0,
-                    vk: Some(20),
-                },
-                second: None,
-                max_gap_ms: 1000,
-            }),
+                    key: Some(KeyCode::CapsLock),
+                }],
+                DEFAULT_MAX_GAP_MS,
+            )),
+
+            hotkey_undo_conversion_sequence: None,
+            hotkey_convert_last_phrase_sequence: None,
+
+            autoconvert_exceptions: AutoconvertExceptions::default(),
+            autoconvert_pause_after_reverts: DEFAULT_PAUSE_AFTER_REVERTS,
+            autoconvert_context_weight: DEFAULT_CONTEXT_WEIGHT,
+            autoconvert_trigger_chars: default_autoconvert_trigger_chars(),
+            autoconvert_max_pause_in_word_ms: DEFAULT_MAX_PAUSE_IN_WORD_MS,
+            journal_idle_expiry_ms: 0,
+            record_session: false,
+            privacy_blocked_processes: default_privacy_blocked_processes(),
+
+            convert_repeat: ConvertRepeatMode::Cycle,
+            convert_repeat_window_ms: DEFAULT_CONVERT_REPEAT_WINDOW_MS,
         }
     }
 }
@@ -135,6 +220,11 @@ pub fn config_path() -> io::Result<PathBuf> {
     Ok(PathBuf::from(appdata).join(APP_DIR).join(CONFIG_FILE))
 }
 
+/// Where the session recording is written, see [`Config::record_session`].
+pub fn session_path() -> io::Result<PathBuf> {
+    config_path().map(|path| path.with_file_name(SESSION_FILE))
+}
+
 fn ensure_parent_dir(path: &Path) -> io::Result<()> {
     let Some(dir) = path.parent() else {
         return Ok(());
@@ -157,5 +247,19 @@ pub fn save(cfg: &Config) -> io::Result<()> {
     ensure_parent_dir(&path)?;
     cfg.validate_hotkey_sequences()
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    cfg.validate_autoconvert_exceptions()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
     confy::store_path(path, cfg).map_err(confy_err)
 }
+
+/// Adds `word` to the persisted never-convert list and returns the saved config.
+///
+/// Only this setting is changed; other values are taken from the config on disk. A config that
+/// fails to load is left untouched and the error is returned.
+pub fn add_never_convert_word(word: &str) -> io::Result<Config> {
+    let mut cfg = load()?;
+    if cfg.autoconvert_exceptions.add_never_convert(word) {
+        save(&cfg)?;
+    }
+    Ok(cfg)
+}
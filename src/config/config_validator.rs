@@ -1,17 +1,33 @@
 use std::fmt::Write as _;
 
-use crate::config::{
-    Config,
-    constants::{CONVERT_LAST_WORD, CONVERT_SELECTION, PAUSE, SWITCH_LAYOUT},
+use crate::{
+    config::{
+        Config, HotkeySequence,
+        constants::{
+            CONVERT_LAST_PHRASE, CONVERT_LAST_WORD, CONVERT_SELECTION, PAUSE, SWITCH_LAYOUT,
+            UNDO_CONVERSION,
+        },
+    },
+    domain::text::autoconvert::ExceptionRules,
 };
 
-pub fn find_duplicate_hotkey_sequences(config: &Config) -> Option<String> {
-    let sequences = [
+/// Hotkey sequences of the config, with the names of their actions.
+fn named_sequences(config: &Config) -> [(&'static str, &Option<HotkeySequence>); 6] {
+    [
         (CONVERT_LAST_WORD, &config.hotkey_convert_last_word_sequence),
         (PAUSE, &config.hotkey_pause_sequence),
         (CONVERT_SELECTION, &config.hotkey_convert_selection_sequence),
         (SWITCH_LAYOUT, &config.hotkey_switch_layout_sequence),
-    ];
+        (UNDO_CONVERSION, &config.hotkey_undo_conversion_sequence),
+        (
+            CONVERT_LAST_PHRASE,
+            &config.hotkey_convert_last_phrase_sequence,
+        ),
+    ]
+}
+
+pub fn find_duplicate_hotkey_sequences(config: &Config) -> Option<String> {
+    let sequences = named_sequences(config);
 
     // Allowed duplicates (bidirectional check)
     let is_allowed_duplicate = |a: &str, b: &str| {
@@ -52,12 +68,44 @@ pub fn find_duplicate_hotkey_sequences(config: &Config) -> Option<String> {
     }
 }
 
+/// Finds tap and hold steps without a key. Modifier keys alone are never held back, so such a
+/// step could not fire.
+pub fn find_modifier_only_tap_or_hold(config: &Config) -> Option<String> {
+    let names: Vec<_> = named_sequences(config)
+        .into_iter()
+        .filter(|(_, seq)| {
+            seq.as_ref().is_some_and(|seq| {
+                seq.steps
+                    .iter()
+                    .any(|step| step.chord.key.is_none() && !step.press.is_down())
+            })
+        })
+        .map(|(name, _)| name)
+        .collect();
+
+    if names.is_empty() {
+        return None;
+    }
+    let mut error = String::from("Tap and hold steps need a key:\n\n");
+    for name in &names {
+        let _ = writeln!(error, "• '{name}'");
+    }
+    error.push_str("\nModifier keys alone can only be used as plain presses.");
+    Some(error)
+}
+
 impl Config {
     pub fn validate_hotkey_sequences(&self) -> Result<(), String> {
-        if let Some(error) = find_duplicate_hotkey_sequences(self) {
+        if let Some(error) = find_modifier_only_tap_or_hold(self) {
+            Err(error)
+        } else if let Some(error) = find_duplicate_hotkey_sequences(self) {
             Err(error)
         } else {
             Ok(())
         }
     }
+
+    pub fn validate_autoconvert_exceptions(&self) -> Result<(), String> {
+        ExceptionRules::compile(&self.autoconvert_exceptions).map(|_| ())
+    }
 }
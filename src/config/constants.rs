@@ -2,3 +2,5 @@ pub const CONVERT_LAST_WORD: &str = "Convert last sequence";
 pub const CONVERT_SELECTION: &str = "Convert selection";
 pub const PAUSE: &str = "Autoconvert pause";
 pub const SWITCH_LAYOUT: &str = "Switch keyboard layout";
+pub const UNDO_CONVERSION: &str = "Undo conversion";
+pub const CONVERT_LAST_PHRASE: &str = "Convert last phrase";
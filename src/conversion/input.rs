@@ -1,6 +1,9 @@
-use windows::Win32::UI::Input::KeyboardAndMouse::{
-    INPUT, INPUT_0, INPUT_KEYBOARD, KEYBD_EVENT_FLAGS, KEYBDINPUT, KEYEVENTF_KEYUP, SendInput,
-    VIRTUAL_KEY,
+use windows::Win32::UI::{
+    Input::KeyboardAndMouse::{
+        INPUT, INPUT_0, INPUT_KEYBOARD, KEYBD_EVENT_FLAGS, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY,
+        KEYEVENTF_KEYUP, SendInput, VIRTUAL_KEY,
+    },
+    WindowsAndMessaging::{KBDLLHOOKSTRUCT, LLKHF_EXTENDED, LLKHF_UP},
 };
 
 /// Virtual key code for the Left Arrow key.
@@ -85,7 +88,7 @@ pub fn send_text_unicode(text: &str) -> bool {
                         KEYEVENTF_UNICODE
                     },
                     time: 0,
-                    dwExtraInfo: 0,
+                    dwExtraInfo: crate::input::ring_buffer::OWN_INPUT_TAG,
                 },
             },
         }
@@ -135,6 +138,45 @@ pub fn reselect_last_inserted_text_utf16_units(units: usize) -> bool {
         && (0..units).all(|_| KeySequence::tap(VK_RIGHT_KEY))
 }
 
+/// Sends key events seen by the keyboard hook again, in order, as this program's input.
+///
+/// Virtual key, scan code and the extended and key-up flags are kept.
+///
+/// Returns `true` if `SendInput` inserted every event.
+pub fn replay_hook_keys(keys: &[KBDLLHOOKSTRUCT]) -> bool {
+    let inputs: Vec<INPUT> = keys
+        .iter()
+        .filter_map(|kb| {
+            let mut flags = KEYBD_EVENT_FLAGS::default();
+            if kb.flags.contains(LLKHF_EXTENDED) {
+                flags |= KEYEVENTF_EXTENDEDKEY;
+            }
+            if kb.flags.contains(LLKHF_UP) {
+                flags |= KEYEVENTF_KEYUP;
+            }
+            Some(INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: VIRTUAL_KEY(u16::try_from(kb.vkCode).ok()?),
+                        wScan: u16::try_from(kb.scanCode).ok()?,
+                        dwFlags: flags,
+                        time: 0,
+                        dwExtraInfo: crate::input::ring_buffer::OWN_INPUT_TAG,
+                    },
+                },
+            })
+        })
+        .collect();
+
+    let Some(input_size) = input_struct_size_i32() else {
+        return false;
+    };
+
+    let sent = unsafe { SendInput(&inputs, input_size) };
+    usize::try_from(sent).is_ok_and(|n| n == keys.len())
+}
+
 fn input_struct_size_i32() -> Option<i32> {
     i32::try_from(std::mem::size_of::<INPUT>()).ok()
 }
@@ -157,7 +199,7 @@ fn send_key(vk: VIRTUAL_KEY, key_up: bool) -> bool {
                     KEYBD_EVENT_FLAGS::default()
                 },
                 time: 0,
-                dwExtraInfo: 0,
+                dwExtraInfo: crate::input::ring_buffer::OWN_INPUT_TAG,
             },
         },
     };
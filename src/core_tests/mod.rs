@@ -3,3 +3,48 @@ mod mapping_invariants_tests;
 
 #[path = "../tests/ring_buffer_tests.rs"]
 mod ring_buffer_tests;
+
+#[path = "../tests/input_journal_tests.rs"]
+mod input_journal_tests;
+
+#[path = "../tests/autoconvert_eval_tests.rs"]
+mod autoconvert_eval_tests;
+
+#[path = "../tests/autoconvert_exceptions_tests.rs"]
+mod autoconvert_exceptions_tests;
+
+#[path = "../tests/autoconvert_learning_tests.rs"]
+mod autoconvert_learning_tests;
+
+#[path = "../tests/conversion_history_tests.rs"]
+mod conversion_history_tests;
+
+#[path = "../tests/alternatives_tests.rs"]
+mod alternatives_tests;
+
+#[path = "../tests/phrase_tests.rs"]
+mod phrase_tests;
+
+#[path = "../tests/autoconvert_context_tests.rs"]
+mod autoconvert_context_tests;
+
+#[path = "../tests/mixed_word_tests.rs"]
+mod mixed_word_tests;
+
+#[path = "../tests/autoconvert_timing_tests.rs"]
+mod autoconvert_timing_tests;
+
+#[path = "../tests/typing_pipeline_tests.rs"]
+mod typing_pipeline_tests;
+
+#[path = "../tests/session_replay_tests.rs"]
+mod session_replay_tests;
+
+#[path = "../tests/hotkey_syntax_tests.rs"]
+mod hotkey_syntax_tests;
+
+#[path = "../tests/key_code_tests.rs"]
+mod key_code_tests;
+
+#[path = "../tests/sequence_matcher_tests.rs"]
+mod sequence_matcher_tests;
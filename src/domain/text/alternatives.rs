@@ -0,0 +1,255 @@
+//! Alternatives offered by repeated Convert presses.
+//!
+//! The first press shows the layout conversion. Each further press on the same span moves to
+//! the next alternative: the original text, its transliteration and its case-fixed variant,
+//! then back to the layout conversion. Alternatives equal to an earlier one are skipped.
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Alternative {
+    LayoutConversion,
+    Original,
+    Transliteration,
+    CaseFixed,
+}
+
+fn is_cyrillic_letter(ch: char) -> bool {
+    matches!(ch, 'А'..='я' | 'Ё' | 'ё')
+}
+
+fn ru_to_latin(ch: char) -> Option<&'static str> {
+    let s = match ch {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "yo",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ы' => "y",
+        'э' => "e",
+        'ю' => "yu",
+        'я' => "ya",
+        _ => return None,
+    };
+    Some(s)
+}
+
+/// Latin sequences mapped to Cyrillic, longest first so that greedy matching works.
+const LATIN_TO_RU: &[(&str, char)] = &[
+    ("shch", 'щ'),
+    ("zh", 'ж'),
+    ("kh", 'х'),
+    ("ts", 'ц'),
+    ("ch", 'ч'),
+    ("sh", 'ш'),
+    ("yo", 'ё'),
+    ("yu", 'ю'),
+    ("ya", 'я'),
+    ("a", 'а'),
+    ("b", 'б'),
+    ("c", 'к'),
+    ("d", 'д'),
+    ("e", 'е'),
+    ("f", 'ф'),
+    ("g", 'г'),
+    ("h", 'х'),
+    ("i", 'и'),
+    ("j", 'й'),
+    ("k", 'к'),
+    ("l", 'л'),
+    ("m", 'м'),
+    ("n", 'н'),
+    ("o", 'о'),
+    ("p", 'п'),
+    ("q", 'к'),
+    ("r", 'р'),
+    ("s", 'с'),
+    ("t", 'т'),
+    ("u", 'у'),
+    ("v", 'в'),
+    ("w", 'в'),
+    ("x", 'х'),
+    ("y", 'ы'),
+    ("z", 'з'),
+];
+
+fn push_with_case(out: &mut String, s: &str, upper: bool) {
+    if !upper {
+        out.push_str(s);
+        return;
+    }
+    let mut chars = s.chars();
+    if let Some(first) = chars.next() {
+        out.extend(first.to_uppercase());
+        out.push_str(chars.as_str());
+    }
+}
+
+fn cyrillic_to_latin(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        let lower = ch.to_lowercase().next().unwrap_or(ch);
+        match ru_to_latin(lower) {
+            Some(s) => push_with_case(&mut out, s, ch.is_uppercase()),
+            None => out.push(ch),
+        }
+    }
+    out
+}
+
+fn latin_to_cyrillic(text: &str) -> String {
+    let lower = text.to_ascii_lowercase();
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut i = 0usize;
+
+    while i < text.len() {
+        let rest = &lower[i..];
+        let Some((src, ru)) = LATIN_TO_RU.iter().find(|(src, _)| rest.starts_with(src)) else {
+            // ASCII lowercasing keeps byte offsets, so `i` is a char boundary in both strings.
+            let ch = text[i..].chars().next().unwrap_or_default();
+            out.push(ch);
+            i += ch.len_utf8();
+            continue;
+        };
+
+        let upper = text[i..].starts_with(|c: char| c.is_ascii_uppercase());
+        if upper {
+            out.extend(ru.to_uppercase());
+        } else {
+            out.push(*ru);
+        }
+        i += src.len();
+    }
+
+    out
+}
+
+/// Transliterates Cyrillic text to Latin, or Latin text to Cyrillic.
+#[must_use]
+pub fn transliterate(text: &str) -> String {
+    if text.chars().any(is_cyrillic_letter) {
+        cyrillic_to_latin(text)
+    } else {
+        latin_to_cyrillic(text)
+    }
+}
+
+fn invert_case(word: &str) -> String {
+    word.chars()
+        .flat_map(|ch| {
+            let inverted: Vec<char> = if ch.is_uppercase() {
+                ch.to_lowercase().collect()
+            } else {
+                ch.to_uppercase().collect()
+            };
+            inverted
+        })
+        .collect()
+}
+
+/// Repairs text typed with CapsLock on.
+///
+/// Words with an uppercase letter after the first one (`hELLO`, `HELLO`) get their case inverted.
+/// Other words are kept.
+#[must_use]
+pub fn fix_case(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut word = String::new();
+
+    let flush = |word: &mut String, out: &mut String| {
+        if word.chars().skip(1).any(char::is_uppercase) {
+            out.push_str(&invert_case(word));
+        } else {
+            out.push_str(word);
+        }
+        word.clear();
+    };
+
+    for ch in text.chars() {
+        if ch.is_alphabetic() {
+            word.push(ch);
+        } else {
+            flush(&mut word, &mut out);
+            out.push(ch);
+        }
+    }
+    flush(&mut word, &mut out);
+
+    out
+}
+
+/// Cycle of alternatives for one replaced span.
+#[derive(Clone, Debug)]
+pub struct ConversionCycle {
+    candidates: Vec<(Alternative, String)>,
+    index: usize,
+}
+
+impl ConversionCycle {
+    /// Starts a cycle for `original` that is currently shown as its layout conversion `converted`.
+    #[must_use]
+    pub fn new(original: &str, converted: &str) -> Self {
+        let all = [
+            (Alternative::LayoutConversion, converted.to_string()),
+            (Alternative::Original, original.to_string()),
+            (Alternative::Transliteration, transliterate(original)),
+            (Alternative::CaseFixed, fix_case(original)),
+        ];
+
+        let mut candidates: Vec<(Alternative, String)> = Vec::with_capacity(all.len());
+        for (alt, text) in all {
+            if !text.is_empty() && candidates.iter().all(|(_, t)| *t != text) {
+                candidates.push((alt, text));
+            }
+        }
+
+        Self {
+            candidates,
+            index: 0,
+        }
+    }
+
+    /// Text currently shown in place of the original.
+    #[must_use]
+    pub fn current(&self) -> &str {
+        self.candidates
+            .get(self.index)
+            .map_or("", |(_, text)| text.as_str())
+    }
+
+    #[must_use]
+    pub fn current_alternative(&self) -> Alternative {
+        self.candidates
+            .get(self.index)
+            .map_or(Alternative::LayoutConversion, |(alt, _)| *alt)
+    }
+
+    /// Moves to the next alternative and returns its text.
+    pub fn advance(&mut self) -> &str {
+        if !self.candidates.is_empty() {
+            self.index = (self.index + 1) % self.candidates.len();
+        }
+        self.current()
+    }
+}
@@ -0,0 +1,374 @@
+//! Autoconvert decision engine.
+//!
+//! Decides whether a word typed in one layout should be replaced by its layout conversion.
+//! The module is platform independent so that the same decision logic can be exercised by
+//! the Windows runtime, unit tests and the corpus evaluation harness.
+
+pub mod context;
+pub mod exceptions;
+pub mod learning;
+pub mod timing;
+
+use std::sync::OnceLock;
+
+pub use self::{
+    context::{CONTEXT_WORDS, ContextPrior, DEFAULT_CONTEXT_WEIGHT, SentenceContext},
+    exceptions::{AutoconvertExceptions, ExceptionRules, ExceptionVerdict},
+    learning::{AutoconvertLearning, DEFAULT_PAUSE_AFTER_REVERTS},
+    timing::{DEFAULT_MAX_PAUSE_IN_WORD_MS, TypingTiming, check_typing_rhythm},
+};
+
+pub const MIN_WORD_LEN: usize = 4;
+pub const MIN_CONVERTED_CONFIDENCE: f64 = 0.70;
+pub const MIN_CONFIDENCE_GAIN: f64 = 0.25;
+
+/// Context bias from which short words are judged.
+const STRONG_PRIOR: f64 = 0.4;
+const MIN_WORD_LEN_WITH_STRONG_PRIOR: usize = 2;
+/// Largest shift of the confidence thresholds, reached at a bias of `1.0`.
+const PRIOR_THRESHOLD_SHIFT: f64 = 0.2;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SkipReason {
+    Reentry,
+    SuffixHasNewline,
+    NotAWord,
+    NoChangeAfterConvert,
+    TooShort,
+    ScriptCheckFailed,
+    AlreadyCorrect,
+    ConvertedConfidenceLow,
+    NotBetterEnough,
+    UserNeverConvert,
+    PausedMidWord,
+}
+
+impl SkipReason {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SkipReason::Reentry => "reentry",
+            SkipReason::SuffixHasNewline => "suffix_has_newline",
+            SkipReason::NotAWord => "not_a_word",
+            SkipReason::NoChangeAfterConvert => "no_change_after_convert",
+            SkipReason::TooShort => "too_short",
+            SkipReason::ScriptCheckFailed => "script_check_failed",
+            SkipReason::AlreadyCorrect => "already_correct",
+            SkipReason::ConvertedConfidenceLow => "converted_confidence_low",
+            SkipReason::NotBetterEnough => "not_better_enough",
+            SkipReason::UserNeverConvert => "user_never_convert",
+            SkipReason::PausedMidWord => "paused_mid_word",
+        }
+    }
+}
+
+fn has_ascii_vowel(s: &str) -> bool {
+    s.chars().any(|ch| {
+        let c = ch.to_ascii_lowercase();
+        matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+    })
+}
+
+fn has_cyrillic_vowel(s: &str) -> bool {
+    s.chars().any(|ch| {
+        let c = ch.to_lowercase().next().unwrap_or(ch);
+        matches!(c, 'а' | 'е' | 'ё' | 'и' | 'о' | 'у' | 'ы' | 'э' | 'ю' | 'я')
+    })
+}
+
+fn is_plausible_english_like_token(s: &str) -> bool {
+    if !looks_like_ascii_word(s) {
+        return false;
+    }
+    let has_vowel = has_ascii_vowel(s);
+    // 'y' intentionally treated as consonant here to reduce false positives.
+    let mut consonant_run = 0usize;
+    let mut max_consonant_run = 0usize;
+    let mut rare = 0usize;
+    for ch in s.chars() {
+        if ch == '\'' {
+            continue;
+        }
+        let c = ch.to_ascii_lowercase();
+        let is_vowel = matches!(c, 'a' | 'e' | 'i' | 'o' | 'u');
+        if is_vowel {
+            consonant_run = 0;
+        } else {
+            consonant_run += 1;
+            max_consonant_run = max_consonant_run.max(consonant_run);
+            if matches!(c, 'j' | 'q' | 'x' | 'z') {
+                rare += 1;
+            }
+        }
+    }
+    has_vowel && max_consonant_run <= 4 && rare <= 1
+}
+
+fn is_plausible_russian_like_token(s: &str) -> bool {
+    if !looks_like_cyrillic_word(s) {
+        return false;
+    }
+    if !has_cyrillic_vowel(s) {
+        return false;
+    }
+    let mut consonant_run = 0usize;
+    let mut max_consonant_run = 0usize;
+    for ch in s.chars() {
+        if ch == '\'' || ch == '-' {
+            continue;
+        }
+        if !ch.is_alphabetic() {
+            continue;
+        }
+        let c = ch.to_lowercase().next().unwrap_or(ch);
+        let is_vowel = matches!(c, 'а' | 'е' | 'ё' | 'и' | 'о' | 'у' | 'ы' | 'э' | 'ю' | 'я');
+        if is_vowel {
+            consonant_run = 0;
+        } else {
+            consonant_run += 1;
+            max_consonant_run = max_consonant_run.max(consonant_run);
+        }
+    }
+    max_consonant_run <= 4
+}
+
+/// Returns the shared RU/EN language detector.
+///
+/// Building the detector loads language models, so it is created once per process.
+pub fn language_detector() -> &'static lingua::LanguageDetector {
+    use lingua::{Language, LanguageDetector, LanguageDetectorBuilder};
+    static DETECTOR: OnceLock<LanguageDetector> = OnceLock::new();
+    DETECTOR.get_or_init(|| {
+        LanguageDetectorBuilder::from_languages(&[Language::English, Language::Russian])
+            .with_minimum_relative_distance(0.20)
+            .build()
+    })
+}
+
+/// Builds the detector and loads its models on a background thread, so that the first word
+/// typed does not wait for them.
+#[cfg(windows)]
+pub fn warm_up_language_detector() {
+    let spawned = std::thread::Builder::new()
+        .name("language-detector".to_string())
+        .spawn(|| {
+            // Words in both scripts leave both languages in play, which loads both models.
+            let _ = language_detector().compute_language_confidence_values("hello привет");
+        });
+    if let Err(e) = spawned {
+        tracing::warn!(error = %e, "language detector warm-up not started");
+    }
+}
+
+fn looks_like_ascii_word(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    if bytes.is_empty() {
+        return false;
+    }
+
+    let is_ascii_letter = |b: u8| b.is_ascii_alphabetic();
+    let has_letter = bytes.iter().copied().any(is_ascii_letter);
+    if !has_letter {
+        return false;
+    }
+
+    bytes.iter().copied().enumerate().all(|(i, b)| {
+        if is_ascii_letter(b) || b == b'\'' {
+            return true;
+        }
+
+        // Allow dot or comma only when it is between ASCII letters.
+        (b == b'.' || b == b',')
+            && i > 0
+            && i + 1 < bytes.len()
+            && is_ascii_letter(bytes[i - 1])
+            && is_ascii_letter(bytes[i + 1])
+    })
+}
+
+fn trailing_convertible_punct_count(s: &str) -> usize {
+    s.chars()
+        .rev()
+        .take_while(|ch| matches!(ch, '?' | '/' | ',' | '.'))
+        .count()
+}
+
+fn trim_tail_chars(s: &str, n: usize) -> &str {
+    if n == 0 {
+        return s;
+    }
+
+    // `n` is usually tiny (trailing punctuation), so scan from the end for the cut boundary.
+    let Some((cut, _)) = s.char_indices().rev().nth(n.saturating_sub(1)) else {
+        return "";
+    };
+    &s[..cut]
+}
+
+/// Returns the part of `word` the decision is made on: trailing convertible punctuation is dropped.
+///
+/// Exception lists are matched against this form.
+#[must_use]
+pub fn decision_word(word: &str) -> &str {
+    trim_tail_chars(word, trailing_convertible_punct_count(word))
+}
+
+/// Decides whether `word` should be replaced by `converted`.
+///
+/// `converted` is expected to be the layout conversion of `word`.
+/// User exception lists are consulted first and short circuit the heuristics.
+#[cfg_attr(
+    windows,
+    allow(dead_code, reason = "the app always judges with a context prior")
+)]
+pub fn should_autoconvert_word(
+    detector: &lingua::LanguageDetector,
+    exceptions: &ExceptionRules,
+    word: &str,
+    converted: &str,
+) -> Result<(), SkipReason> {
+    should_autoconvert_word_with_prior(
+        detector,
+        exceptions,
+        word,
+        converted,
+        &ContextPrior::NEUTRAL,
+    )
+}
+
+/// Same as [`should_autoconvert_word`], with the preceding words as a prior.
+///
+/// A context in the language of `converted` lowers the confidence thresholds, and when it is
+/// strong, words down to two letters are judged. A context in the language of `word` raises the
+/// thresholds. The "already looks correct" guards hold whatever the context: English words such
+/// as `merge` or `docker` are common in Russian text.
+pub fn should_autoconvert_word_with_prior(
+    detector: &lingua::LanguageDetector,
+    exceptions: &ExceptionRules,
+    word: &str,
+    converted: &str,
+    prior: &ContextPrior,
+) -> Result<(), SkipReason> {
+    use lingua::Language;
+    const MIN_CONVERTED_EN_CONF_FOR_OVERRIDE: f64 = 0.80;
+    let trailing_punct = trailing_convertible_punct_count(word);
+    let word_analysis = trim_tail_chars(word, trailing_punct);
+    let conv_analysis = trim_tail_chars(converted, trailing_punct);
+    if word_analysis.is_empty() || conv_analysis.is_empty() {
+        return Err(SkipReason::ScriptCheckFailed);
+    }
+    match exceptions.verdict(word_analysis) {
+        Some(ExceptionVerdict::NeverConvert) => return Err(SkipReason::UserNeverConvert),
+        Some(ExceptionVerdict::AlwaysConvert) => return Ok(()),
+        None => {}
+    }
+    let bias = prior.bias(if looks_like_ascii_word(word_analysis) {
+        Language::Russian
+    } else {
+        Language::English
+    });
+    let strong_prior = bias >= STRONG_PRIOR;
+    let shift = bias * PRIOR_THRESHOLD_SHIFT;
+    let min_len = if strong_prior {
+        MIN_WORD_LEN_WITH_STRONG_PRIOR
+    } else {
+        MIN_WORD_LEN
+    };
+    if word_analysis.chars().count() < min_len {
+        return Err(SkipReason::TooShort);
+    }
+    let w_is_ascii = looks_like_ascii_word(word_analysis);
+    let w_is_cyr = looks_like_cyrillic_word(word_analysis);
+    let c_is_ascii = looks_like_ascii_word(conv_analysis);
+    let c_is_cyr = looks_like_cyrillic_word(conv_analysis);
+    if !(w_is_ascii || w_is_cyr) || !(c_is_ascii || c_is_cyr) {
+        return Err(SkipReason::ScriptCheckFailed);
+    }
+    let w_ru = confidence(detector, word_analysis, Language::Russian);
+    let w_en = confidence(detector, word_analysis, Language::English);
+    let c_ru = confidence(detector, conv_analysis, Language::Russian);
+    let c_en = confidence(detector, conv_analysis, Language::English);
+    // Keep the English guard: do not convert real English words to Russian.
+    if w_is_ascii && is_plausible_english_like_token(word_analysis) {
+        return Err(SkipReason::AlreadyCorrect);
+    }
+    // Russian guard is conditional: if conversion yields a strong English candidate, do not short circuit.
+    if w_is_cyr && is_plausible_russian_like_token(word_analysis) {
+        let converted_looks_english = is_plausible_english_like_token(conv_analysis)
+            && c_en >= MIN_CONVERTED_EN_CONF_FOR_OVERRIDE;
+        if !converted_looks_english {
+            return Err(SkipReason::AlreadyCorrect);
+        }
+    }
+    let w_best = w_ru.max(w_en);
+    let c_best = c_ru.max(c_en);
+    let target = if w_is_ascii {
+        Language::Russian
+    } else {
+        Language::English
+    };
+    let (w_in_target, c_in_target) = if matches!(target, Language::Russian) {
+        (w_ru, c_ru)
+    } else {
+        (w_en, c_en)
+    };
+    if c_best < MIN_CONVERTED_CONFIDENCE - shift {
+        return Err(SkipReason::ConvertedConfidenceLow);
+    }
+    let min_abs = if w_best < 0.30 {
+        0.55
+    } else {
+        MIN_CONVERTED_CONFIDENCE
+    };
+    if c_in_target < min_abs - shift {
+        return Err(SkipReason::ConvertedConfidenceLow);
+    }
+    if c_in_target - w_in_target < MIN_CONFIDENCE_GAIN - shift {
+        return Err(SkipReason::NotBetterEnough);
+    }
+    Ok(())
+}
+
+fn confidence(detector: &lingua::LanguageDetector, text: &str, lang: lingua::Language) -> f64 {
+    detector
+        .compute_language_confidence_values(text)
+        .iter()
+        .find(|(l, _)| *l == lang)
+        .map_or(0.0, |(_, v)| *v)
+}
+
+pub fn ensure_has_letters(word: &str) -> Result<(), SkipReason> {
+    if word.chars().any(char::is_alphabetic) {
+        return Ok(());
+    }
+    Err(SkipReason::NotAWord)
+}
+
+pub fn ensure_changed(word: &str, converted: &str) -> Result<(), SkipReason> {
+    if word != converted {
+        return Ok(());
+    }
+    Err(SkipReason::NoChangeAfterConvert)
+}
+
+fn looks_like_cyrillic_word(s: &str) -> bool {
+    let mut has_alpha = false;
+    for ch in s.chars() {
+        if ch.is_alphabetic() {
+            if !is_cyrillic(ch) {
+                return false;
+            }
+            has_alpha = true;
+            continue;
+        }
+        if ch == '\'' || ch == '-' {
+            continue;
+        }
+        return false;
+    }
+    has_alpha
+}
+
+fn is_cyrillic(ch: char) -> bool {
+    ('\u{0400}'..='\u{04FF}').contains(&ch) || ('\u{0500}'..='\u{052F}').contains(&ch)
+}
@@ -0,0 +1,117 @@
+//! Sentence context used as a prior by the decision engine.
+//!
+//! A word is judged alone by the language detector, which is unreliable for short words. The
+//! words typed right before it tell which language the user is writing in: after three Russian
+//! words, `lf` is much more likely to be `да` than English. The context leans the thresholds
+//! towards or away from converting, scaled by a user configurable weight.
+
+use lingua::Language;
+
+/// Number of preceding words collected into the context.
+pub const CONTEXT_WORDS: usize = 4;
+
+/// Default weight of the context prior.
+pub const DEFAULT_CONTEXT_WEIGHT: f64 = 0.5;
+
+/// Languages of the words typed before the word being judged.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SentenceContext {
+    pub russian_words: u32,
+    pub english_words: u32,
+    /// Words that are in their language because a conversion put them there.
+    pub converted_russian_words: u32,
+    pub converted_english_words: u32,
+}
+
+fn script_language(word: &str) -> Option<Language> {
+    let mut cyrillic = 0usize;
+    let mut latin = 0usize;
+    for ch in word.chars() {
+        if matches!(ch, 'А'..='я' | 'Ё' | 'ё') {
+            cyrillic += 1;
+        } else if ch.is_ascii_alphabetic() {
+            latin += 1;
+        }
+    }
+    match cyrillic.cmp(&latin) {
+        std::cmp::Ordering::Greater => Some(Language::Russian),
+        std::cmp::Ordering::Less => Some(Language::English),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+impl SentenceContext {
+    /// Adds a preceding word.
+    ///
+    /// The language is taken from the script of `word`. `layout` is used when the word has no
+    /// letters to decide on, for example `,` typed on the Russian layout.
+    pub fn observe(&mut self, word: &str, layout: Option<Language>, converted: bool) {
+        let Some(language) = script_language(word).or(layout) else {
+            return;
+        };
+        match (language, converted) {
+            (Language::Russian, false) => self.russian_words += 1,
+            (Language::Russian, true) => self.converted_russian_words += 1,
+            (Language::English, false) => self.english_words += 1,
+            (Language::English, true) => self.converted_english_words += 1,
+        }
+    }
+
+    /// How strongly the context points at `target`, from `-1.0` (only the other language)
+    /// to `1.0` (only `target`). Converted words count twice: they were typed in the wrong
+    /// layout, which is what the current word is suspected of.
+    #[must_use]
+    pub fn lean(&self, target: Language) -> f64 {
+        let russian = f64::from(self.russian_words + 2 * self.converted_russian_words);
+        let english = f64::from(self.english_words + 2 * self.converted_english_words);
+        let total = russian + english;
+        if total == 0.0 {
+            return 0.0;
+        }
+        match target {
+            Language::Russian => (russian - english) / total,
+            Language::English => (english - russian) / total,
+        }
+    }
+}
+
+/// Context together with the weight the decision engine gives it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ContextPrior {
+    context: SentenceContext,
+    weight: f64,
+}
+
+impl ContextPrior {
+    /// Prior that does not change any decision.
+    #[cfg_attr(
+        windows,
+        allow(dead_code, reason = "the app always judges with a context prior")
+    )]
+    pub const NEUTRAL: Self = Self {
+        context: SentenceContext {
+            russian_words: 0,
+            english_words: 0,
+            converted_russian_words: 0,
+            converted_english_words: 0,
+        },
+        weight: 0.0,
+    };
+
+    /// `weight` is clamped to `0.0..=1.0`; `0.0` disables the prior.
+    #[must_use]
+    pub fn new(context: SentenceContext, weight: f64) -> Self {
+        let weight = if weight.is_finite() {
+            weight.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        Self { context, weight }
+    }
+
+    /// Signed bias towards converting into `target`, within `-weight..=weight`.
+    #[must_use]
+    pub fn bias(&self, target: Language) -> f64 {
+        self.weight * self.context.lean(target)
+    }
+}
@@ -0,0 +1,140 @@
+//! User controlled exception lists for autoconvert.
+//!
+//! Lists hold words exactly as they are typed (before conversion). Words are compared
+//! case-insensitively. Patterns use an explicit tag:
+//! - `prefix:<text>` matches words starting with `<text>` (case-insensitive)
+//! - `regex:<expr>` matches words where `<expr>` finds a match
+//!
+//! Never-convert rules win over always-convert rules.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+const PREFIX_TAG: &str = "prefix:";
+const REGEX_TAG: &str = "regex:";
+
+/// Persisted form of the exception lists, stored in `Config`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AutoconvertExceptions {
+    #[serde(default)]
+    pub never_convert: Vec<String>,
+    #[serde(default)]
+    pub always_convert: Vec<String>,
+    #[serde(default)]
+    pub never_convert_patterns: Vec<String>,
+    #[serde(default)]
+    pub always_convert_patterns: Vec<String>,
+}
+
+impl AutoconvertExceptions {
+    /// Adds `word` to the never-convert list.
+    ///
+    /// Returns `false` when the word is empty or already listed.
+    pub fn add_never_convert(&mut self, word: &str) -> bool {
+        let word = word.trim();
+        if word.is_empty() {
+            return false;
+        }
+
+        let key = word.to_lowercase();
+        if self.never_convert.iter().any(|w| w.to_lowercase() == key) {
+            return false;
+        }
+
+        // A word cannot be in both lists; the latest user decision wins.
+        self.always_convert.retain(|w| w.to_lowercase() != key);
+        self.never_convert.push(word.to_string());
+        true
+    }
+}
+
+/// Outcome of consulting the exception lists for a word.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExceptionVerdict {
+    NeverConvert,
+    AlwaysConvert,
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Prefix(String),
+    Regex(regex::Regex),
+}
+
+impl Pattern {
+    fn parse(raw: &str) -> Result<Self, String> {
+        if let Some(prefix) = raw.strip_prefix(PREFIX_TAG) {
+            if prefix.is_empty() {
+                return Err(format!("Empty prefix pattern: {raw:?}"));
+            }
+            return Ok(Self::Prefix(prefix.to_lowercase()));
+        }
+
+        if let Some(expr) = raw.strip_prefix(REGEX_TAG) {
+            return regex::Regex::new(expr)
+                .map(Self::Regex)
+                .map_err(|e| format!("Invalid regex pattern {raw:?}: {e}"));
+        }
+
+        Err(format!(
+            "Unknown pattern {raw:?}: expected \"{PREFIX_TAG}...\" or \"{REGEX_TAG}...\""
+        ))
+    }
+
+    fn matches(&self, word: &str, word_lower: &str) -> bool {
+        match self {
+            Self::Prefix(prefix) => word_lower.starts_with(prefix.as_str()),
+            Self::Regex(re) => re.is_match(word),
+        }
+    }
+}
+
+/// Compiled exception lists used at runtime.
+#[derive(Debug, Clone, Default)]
+pub struct ExceptionRules {
+    never_words: HashSet<String>,
+    always_words: HashSet<String>,
+    never_patterns: Vec<Pattern>,
+    always_patterns: Vec<Pattern>,
+}
+
+impl ExceptionRules {
+    /// Compiles persisted lists. Fails on the first malformed pattern.
+    pub fn compile(src: &AutoconvertExceptions) -> Result<Self, String> {
+        let words = |list: &[String]| -> HashSet<String> {
+            list.iter()
+                .map(|w| w.trim().to_lowercase())
+                .filter(|w| !w.is_empty())
+                .collect()
+        };
+        let patterns = |list: &[String]| -> Result<Vec<Pattern>, String> {
+            list.iter().map(|p| Pattern::parse(p.trim())).collect()
+        };
+
+        Ok(Self {
+            never_words: words(&src.never_convert),
+            always_words: words(&src.always_convert),
+            never_patterns: patterns(&src.never_convert_patterns)?,
+            always_patterns: patterns(&src.always_convert_patterns)?,
+        })
+    }
+
+    /// Looks up `word` (as typed) in the lists.
+    #[must_use]
+    pub fn verdict(&self, word: &str) -> Option<ExceptionVerdict> {
+        let lower = word.to_lowercase();
+
+        let hit = |words: &HashSet<String>, patterns: &[Pattern]| {
+            words.contains(&lower) || patterns.iter().any(|p| p.matches(word, &lower))
+        };
+
+        if hit(&self.never_words, &self.never_patterns) {
+            return Some(ExceptionVerdict::NeverConvert);
+        }
+        if hit(&self.always_words, &self.always_patterns) {
+            return Some(ExceptionVerdict::AlwaysConvert);
+        }
+        None
+    }
+}
@@ -0,0 +1,82 @@
+//! Learning from autoconversions the user reverted.
+//!
+//! A revert means the decision was wrong for that word, so the word is reported back for the
+//! never-convert list. Several reverts in a row mean autoconvert does not fit what the user is
+//! typing right now, so the store asks to pause it.
+
+use super::decision_word;
+
+/// Consecutive reverts after which autoconvert is paused.
+pub const DEFAULT_PAUSE_AFTER_REVERTS: u32 = 3;
+
+/// What the caller should do after a revert.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RevertOutcome {
+    /// Word to add to the never-convert list, as it was typed.
+    pub word: String,
+    pub pause_autoconvert: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct AutoconvertLearning {
+    /// `0` disables the auto-pause.
+    pause_after_reverts: u32,
+    consecutive_reverts: u32,
+    /// An autoconvert happened and was not reverted yet.
+    awaiting_feedback: bool,
+}
+
+impl Default for AutoconvertLearning {
+    fn default() -> Self {
+        Self::new(DEFAULT_PAUSE_AFTER_REVERTS)
+    }
+}
+
+impl AutoconvertLearning {
+    #[must_use]
+    pub const fn new(pause_after_reverts: u32) -> Self {
+        Self {
+            pause_after_reverts,
+            consecutive_reverts: 0,
+            awaiting_feedback: false,
+        }
+    }
+
+    pub fn set_pause_after_reverts(&mut self, pause_after_reverts: u32) {
+        self.pause_after_reverts = pause_after_reverts;
+    }
+
+    /// Records a performed autoconvert.
+    ///
+    /// A new conversion while the previous one is still unreverted means the previous one was
+    /// accepted, which breaks the run of reverts.
+    pub fn record_autoconvert(&mut self) {
+        if self.awaiting_feedback {
+            self.consecutive_reverts = 0;
+        }
+        self.awaiting_feedback = true;
+    }
+
+    /// Records a revert of the last autoconvert of `original` (the text as typed).
+    pub fn record_revert(&mut self, original: &str) -> RevertOutcome {
+        self.awaiting_feedback = false;
+        self.consecutive_reverts = self.consecutive_reverts.saturating_add(1);
+
+        let pause_autoconvert =
+            self.pause_after_reverts != 0 && self.consecutive_reverts >= self.pause_after_reverts;
+        if pause_autoconvert {
+            self.consecutive_reverts = 0;
+        }
+
+        RevertOutcome {
+            word: decision_word(original).to_string(),
+            pause_autoconvert,
+        }
+    }
+
+    /// Forgets the current run, for example when autoconvert is toggled by the user.
+    pub fn reset(&mut self) {
+        self.consecutive_reverts = 0;
+        self.awaiting_feedback = false;
+    }
+}
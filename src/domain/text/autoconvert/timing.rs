@@ -0,0 +1,46 @@
+//! Typing rhythm used by the decision engine.
+//!
+//! A word typed in one burst is a candidate for autoconvert. A long pause inside it means the
+//! user stopped to look at the screen and most likely saw the layout, so such a word is left alone.
+
+use std::time::{Duration, Instant};
+
+use super::SkipReason;
+
+/// Default longest pause inside a word that still counts as one burst.
+pub const DEFAULT_MAX_PAUSE_IN_WORD_MS: u32 = 3000;
+
+/// Timing of the chars of a word.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TypingTiming {
+    /// Longest pause between two consecutive chars.
+    pub longest_pause: Duration,
+}
+
+impl TypingTiming {
+    /// Timing of chars typed at `times`, oldest first.
+    #[must_use]
+    pub fn of(times: impl IntoIterator<Item = Instant>) -> Self {
+        let mut times = times.into_iter();
+        let Some(first) = times.next() else {
+            return Self::default();
+        };
+        let mut timing = Self::default();
+        let mut prev = first;
+        for at in times {
+            timing.longest_pause = timing.longest_pause.max(at.saturating_duration_since(prev));
+            prev = at;
+        }
+        timing
+    }
+}
+
+/// Rejects a word with a pause longer than `max_pause` inside it.
+///
+/// `Duration::ZERO` disables the check.
+pub fn check_typing_rhythm(timing: &TypingTiming, max_pause: Duration) -> Result<(), SkipReason> {
+    if !max_pause.is_zero() && timing.longest_pause > max_pause {
+        return Err(SkipReason::PausedMidWord);
+    }
+    Ok(())
+}
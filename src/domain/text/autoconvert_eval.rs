@@ -0,0 +1,395 @@
+//! Labelled-corpus evaluation of the autoconvert decision engine.
+//!
+//! The corpus is a TSV file with one case per line:
+//! `typed_text<TAB>expected_action[<TAB>layout[<TAB>context]]`.
+//!
+//! - `expected_action` is `convert` or `keep`.
+//! - `layout` is optional (`en` or `ru`) and names the layout the text was typed in.
+//!   When it is absent or empty, the conversion direction is inferred from the letters.
+//! - `context` is optional: the words typed before the case, separated by spaces. They are
+//!   judged as a prior with the default weight, as autoconvert does; without them the prior is
+//!   neutral.
+//!
+//! Blank lines and lines starting with `#` are ignored.
+//!
+//! A baseline file holds minimal acceptable metrics as `name = value` lines
+//! (`min_precision`, `min_recall`, `max_false_converts`). It is used to fail CI when tuning
+//! the thresholds makes the engine worse on the checked-in corpus.
+
+use std::fmt::{self, Write as _};
+
+use crate::domain::text::{
+    autoconvert::{
+        ContextPrior, DEFAULT_CONTEXT_WEIGHT, ExceptionRules, SentenceContext, SkipReason,
+        ensure_changed, ensure_has_letters, should_autoconvert_word_with_prior,
+    },
+    mapping::{ConversionDirection, conversion_direction_for_text, convert_ru_en_with_direction},
+};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExpectedAction {
+    Convert,
+    Keep,
+}
+
+impl ExpectedAction {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "convert" => Some(Self::Convert),
+            "keep" => Some(Self::Keep),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Convert => "convert",
+            Self::Keep => "keep",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CorpusEntry {
+    pub line: usize,
+    pub text: String,
+    pub expected: ExpectedAction,
+    pub direction: Option<ConversionDirection>,
+    /// Words typed before the case, empty for none.
+    pub context: Vec<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn is_skipped_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+/// Parses a labelled TSV corpus.
+pub fn parse_corpus(src: &str) -> Result<Vec<CorpusEntry>, ParseError> {
+    let mut entries = Vec::new();
+
+    for (idx, raw) in src.lines().enumerate() {
+        let line = idx + 1;
+        if is_skipped_line(raw) {
+            continue;
+        }
+
+        let err = |message: String| ParseError { line, message };
+        let mut cols = raw.trim_end_matches('\r').split('\t');
+
+        let text = cols.next().unwrap_or_default();
+        if text.is_empty() {
+            return Err(err("typed_text is empty".to_string()));
+        }
+
+        let action = cols.next().map(str::trim).unwrap_or_default();
+        let expected = ExpectedAction::parse(action)
+            .ok_or_else(|| err(format!("unknown expected_action {action:?}")))?;
+
+        let direction = match cols.next().map(str::trim) {
+            None | Some("") => None,
+            Some("en") => Some(ConversionDirection::EnToRu),
+            Some("ru") => Some(ConversionDirection::RuToEn),
+            Some(other) => return Err(err(format!("unknown layout {other:?}"))),
+        };
+
+        let context = cols
+            .next()
+            .map(|words| words.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        if cols.next().is_some() {
+            return Err(err("too many columns".to_string()));
+        }
+
+        entries.push(CorpusEntry {
+            line,
+            text: text.to_string(),
+            expected,
+            direction,
+            context,
+        });
+    }
+
+    Ok(entries)
+}
+
+#[derive(Clone, Debug)]
+pub struct CaseOutcome {
+    pub entry: CorpusEntry,
+    pub converted: String,
+    pub decision: Result<(), SkipReason>,
+}
+
+impl CaseOutcome {
+    #[must_use]
+    pub fn predicted(&self) -> ExpectedAction {
+        if self.decision.is_ok() {
+            ExpectedAction::Convert
+        } else {
+            ExpectedAction::Keep
+        }
+    }
+}
+
+/// Confusion matrix of the `convert` class against `keep`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Metrics {
+    pub true_converts: usize,
+    pub false_converts: usize,
+    pub true_keeps: usize,
+    pub false_keeps: usize,
+}
+
+impl Metrics {
+    /// Share of performed conversions that were expected. `1.0` when nothing was converted.
+    #[must_use]
+    pub fn precision(&self) -> f64 {
+        ratio(self.true_converts, self.true_converts + self.false_converts)
+    }
+
+    /// Share of expected conversions that were performed. `1.0` when none were expected.
+    #[must_use]
+    pub fn recall(&self) -> f64 {
+        ratio(self.true_converts, self.true_converts + self.false_keeps)
+    }
+}
+
+#[allow(
+    clippy::cast_precision_loss,
+    reason = "corpus sizes are far below the f64 mantissa limit"
+)]
+fn ratio(num: usize, den: usize) -> f64 {
+    if den == 0 {
+        return 1.0;
+    }
+    num as f64 / den as f64
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EvalReport {
+    pub outcomes: Vec<CaseOutcome>,
+    pub metrics: Metrics,
+}
+
+impl EvalReport {
+    pub fn false_converts(&self) -> impl Iterator<Item = &CaseOutcome> {
+        self.outcomes.iter().filter(|o| {
+            o.entry.expected == ExpectedAction::Keep && o.predicted() == ExpectedAction::Convert
+        })
+    }
+
+    pub fn false_keeps(&self) -> impl Iterator<Item = &CaseOutcome> {
+        self.outcomes.iter().filter(|o| {
+            o.entry.expected == ExpectedAction::Convert && o.predicted() == ExpectedAction::Keep
+        })
+    }
+}
+
+/// Runs the same candidate and decision checks as autoconvert on a single case.
+fn evaluate_entry(detector: &lingua::LanguageDetector, entry: &CorpusEntry) -> CaseOutcome {
+    let direction = entry
+        .direction
+        .or_else(|| conversion_direction_for_text(&entry.text))
+        .unwrap_or(ConversionDirection::RuToEn);
+    let converted = convert_ru_en_with_direction(&entry.text, direction);
+    let prior = if entry.context.is_empty() {
+        ContextPrior::NEUTRAL
+    } else {
+        let mut context = SentenceContext::default();
+        for word in &entry.context {
+            context.observe(word, None, false);
+        }
+        ContextPrior::new(context, DEFAULT_CONTEXT_WEIGHT)
+    };
+
+    let decision = ensure_has_letters(&entry.text)
+        .and_then(|()| ensure_changed(&entry.text, &converted))
+        .and_then(|()| {
+            should_autoconvert_word_with_prior(
+                detector,
+                &ExceptionRules::default(),
+                &entry.text,
+                &converted,
+                &prior,
+            )
+        });
+
+    CaseOutcome {
+        entry: entry.clone(),
+        converted,
+        decision,
+    }
+}
+
+/// Evaluates every corpus entry and aggregates the confusion matrix.
+#[must_use]
+pub fn evaluate(detector: &lingua::LanguageDetector, entries: &[CorpusEntry]) -> EvalReport {
+    let mut report = EvalReport::default();
+
+    for entry in entries {
+        let outcome = evaluate_entry(detector, entry);
+        let m = &mut report.metrics;
+        match (outcome.entry.expected, outcome.predicted()) {
+            (ExpectedAction::Convert, ExpectedAction::Convert) => m.true_converts += 1,
+            (ExpectedAction::Keep, ExpectedAction::Convert) => m.false_converts += 1,
+            (ExpectedAction::Keep, ExpectedAction::Keep) => m.true_keeps += 1,
+            (ExpectedAction::Convert, ExpectedAction::Keep) => m.false_keeps += 1,
+        }
+        report.outcomes.push(outcome);
+    }
+
+    report
+}
+
+fn decision_label(outcome: &CaseOutcome) -> &'static str {
+    match outcome.decision {
+        Ok(()) => "convert",
+        Err(reason) => reason.as_str(),
+    }
+}
+
+/// Renders a human readable report: metrics, confusion matrix and misclassified cases.
+#[must_use]
+pub fn render_report(report: &EvalReport) -> String {
+    let m = report.metrics;
+    let mut out = String::new();
+
+    // `String` implements `fmt::Write` infallibly; ignore the `fmt::Result` for clarity.
+    let _ = writeln!(out, "cases:     {}", report.outcomes.len());
+    let _ = writeln!(out, "precision: {:.3}", m.precision());
+    let _ = writeln!(out, "recall:    {:.3}", m.recall());
+    let _ = writeln!(out);
+    let _ = writeln!(out, "confusion matrix (rows: expected, columns: predicted)");
+    let _ = writeln!(out, "{:>10} {:>8} {:>8}", "", "convert", "keep");
+    let _ = writeln!(
+        out,
+        "{:>10} {:>8} {:>8}",
+        "convert", m.true_converts, m.false_keeps
+    );
+    let _ = writeln!(
+        out,
+        "{:>10} {:>8} {:>8}",
+        "keep", m.false_converts, m.true_keeps
+    );
+
+    for (title, cases) in [
+        (
+            "false converts",
+            report.false_converts().collect::<Vec<_>>(),
+        ),
+        ("missed converts", report.false_keeps().collect::<Vec<_>>()),
+    ] {
+        if cases.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{title}:");
+        for o in cases {
+            let _ = writeln!(
+                out,
+                "  line {}: {:?} -> {:?} ({})",
+                o.entry.line,
+                o.entry.text,
+                o.converted,
+                decision_label(o)
+            );
+        }
+    }
+
+    out
+}
+
+/// Minimal acceptable metrics checked in next to the corpus.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Baseline {
+    pub min_precision: f64,
+    pub min_recall: f64,
+    pub max_false_converts: Option<usize>,
+}
+
+impl Default for Baseline {
+    fn default() -> Self {
+        Self {
+            min_precision: 0.0,
+            min_recall: 0.0,
+            max_false_converts: None,
+        }
+    }
+}
+
+/// Parses a baseline file made of `name = value` lines.
+pub fn parse_baseline(src: &str) -> Result<Baseline, ParseError> {
+    let mut baseline = Baseline::default();
+
+    for (idx, raw) in src.lines().enumerate() {
+        let line = idx + 1;
+        if is_skipped_line(raw) {
+            continue;
+        }
+
+        let err = |message: String| ParseError { line, message };
+        let (key, value) = raw
+            .split_once('=')
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .ok_or_else(|| err("expected `name = value`".to_string()))?;
+
+        let bad_value = || err(format!("invalid value {value:?} for {key}"));
+        match key {
+            "min_precision" => baseline.min_precision = value.parse().map_err(|_| bad_value())?,
+            "min_recall" => baseline.min_recall = value.parse().map_err(|_| bad_value())?,
+            "max_false_converts" => {
+                baseline.max_false_converts = Some(value.parse().map_err(|_| bad_value())?);
+            }
+            other => return Err(err(format!("unknown baseline key {other:?}"))),
+        }
+    }
+
+    Ok(baseline)
+}
+
+/// Returns a description of every metric that fell below the baseline.
+#[must_use]
+pub fn regressions(metrics: &Metrics, baseline: &Baseline) -> Vec<String> {
+    let mut out = Vec::new();
+
+    if metrics.precision() < baseline.min_precision {
+        out.push(format!(
+            "precision {:.3} is below baseline {:.3}",
+            metrics.precision(),
+            baseline.min_precision
+        ));
+    }
+    if metrics.recall() < baseline.min_recall {
+        out.push(format!(
+            "recall {:.3} is below baseline {:.3}",
+            metrics.recall(),
+            baseline.min_recall
+        ));
+    }
+    if let Some(max) = baseline.max_false_converts
+        && metrics.false_converts > max
+    {
+        out.push(format!(
+            "false converts {} exceed baseline {max}",
+            metrics.false_converts
+        ));
+    }
+
+    out
+}
@@ -0,0 +1,102 @@
+//! Bounded stack of recent text replacements, used by the undo conversion action.
+//!
+//! Entries only make sense while the window where the replacement happened keeps the focus, so
+//! the stack is bound to a focus visit (see `InputJournal::focus_visit`) and is dropped as soon
+//! as a different one is observed. Coming back to a window is a new visit.
+
+use std::collections::VecDeque;
+
+use zeroize::Zeroize;
+
+use crate::input::ring_buffer::{InputRun, LayoutTag};
+
+/// Number of replacements kept for undo.
+pub const DEFAULT_CAPACITY: usize = 16;
+
+/// A single replacement performed through the journal.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConversionRecord {
+    /// Runs that were replaced, with their original metadata.
+    pub original_runs: Vec<InputRun>,
+    /// Text inserted instead of the original runs.
+    pub converted: String,
+    /// Delimiter runs after the replaced text, kept in place by the conversion.
+    pub suffix_runs: Vec<InputRun>,
+    pub layout_before: LayoutTag,
+    pub layout_after: LayoutTag,
+}
+
+/// The converted text is what was typed in another layout, so it is wiped like the runs.
+impl Drop for ConversionRecord {
+    fn drop(&mut self) {
+        self.converted.zeroize();
+    }
+}
+
+impl ConversionRecord {
+    #[must_use]
+    pub fn original_text(&self) -> String {
+        self.original_runs.iter().map(|r| r.text.as_str()).collect()
+    }
+
+    #[must_use]
+    pub fn suffix_text(&self) -> String {
+        self.suffix_runs.iter().map(|r| r.text.as_str()).collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConversionHistory {
+    entries: VecDeque<ConversionRecord>,
+    capacity: usize,
+    focus: Option<u64>,
+}
+
+impl Default for ConversionHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ConversionHistory {
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            focus: None,
+        }
+    }
+
+    /// Drops entries recorded for another focus and remembers `focus` as the current one.
+    fn sync_focus(&mut self, focus: u64) {
+        if self.focus != Some(focus) {
+            self.entries.clear();
+            self.focus = Some(focus);
+        }
+    }
+
+    /// Records a replacement made during the focus visit `focus`.
+    pub fn push(&mut self, focus: u64, record: ConversionRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.sync_focus(focus);
+        if self.entries.len() == self.capacity {
+            let _ = self.entries.pop_front();
+        }
+        self.entries.push_back(record);
+    }
+
+    /// Takes the most recent replacement if it was made during the focus visit `focus`.
+    pub fn pop(&mut self, focus: u64) -> Option<ConversionRecord> {
+        self.sync_focus(focus);
+        self.entries.pop_back()
+    }
+
+    pub fn invalidate(&mut self) {
+        self.entries.clear();
+        self.focus = None;
+    }
+}
@@ -15,12 +15,15 @@ use windows::Win32::{
 };
 
 use super::{
+    alternatives::ConversionCycle,
     mapping,
     selection_probe::{SelectionProbe, probe_selection_uia, probe_selection_win32},
 };
 use crate::{
     app::AppState,
     conversion::input::{KeySequence, reselect_last_inserted_text_utf16_units, send_text_unicode},
+    input::ring_buffer::layout_tag_from_hkl,
+    input_journal::LayoutTag,
 };
 
 const MAX_SELECTION_CHARS: usize = 512;
@@ -148,8 +151,44 @@ impl ConvertSelectionError {
     }
 }
 
+/// Selection replaced by the last Convert press, kept to offer alternatives on the next press.
+#[derive(Debug)]
+pub struct SelectionRepeat {
+    focus: isize,
+    cycle: ConversionCycle,
+}
+
+/// Picks the text to insert for `text` and remembers it for the next press.
+///
+/// When `text` is exactly what the previous press inserted into the same window, the next
+/// alternative of that cycle is returned. Otherwise a new cycle starts with the layout conversion.
+fn next_selection_replacement(state: &mut AppState, text: &str) -> String {
+    let focus = foreground_window().map(|hwnd| hwnd.0 as isize);
+
+    if let Some(repeat) = state.selection_repeat.as_mut()
+        && Some(repeat.focus) == focus
+        && repeat.cycle.current() == text
+    {
+        let next = repeat.cycle.advance().to_string();
+        tracing::trace!(alternative = ?repeat.cycle.current_alternative(), "selection repeat");
+        return next;
+    }
+
+    let direction = conversion_direction_for_text(text)
+        .or_else(expected_direction_for_foreground_window)
+        .unwrap_or(ConversionDirection::RuToEn);
+    let converted = convert_ru_en_with_direction(text, direction);
+    state.selection_repeat = focus.map(|focus| SelectionRepeat {
+        focus,
+        cycle: ConversionCycle::new(text, &converted),
+    });
+    converted
+}
+
 /// Replaces currently selected text with layout converted text.
 ///
+/// Repeated presses on the inserted text cycle through the other alternatives.
+///
 /// Returns `Ok(())` when:
 /// - Delete tap succeeded
 /// - Unicode injection succeeded
@@ -162,10 +201,7 @@ fn convert_selection_from_text(
 ) -> Result<(), ConvertSelectionError> {
     let delay_ms = crate::helpers::get_edit_u32(state.edits.delay_ms).unwrap_or(100);
 
-    let direction = conversion_direction_for_text(text)
-        .or_else(expected_direction_for_foreground_window)
-        .unwrap_or(ConversionDirection::RuToEn);
-    let converted = convert_ru_en_with_direction(text, direction);
+    let converted = next_selection_replacement(state, text);
     let converted_units = converted.encode_utf16().count();
 
     thread::sleep(Duration::from_millis(u64::from(delay_ms)));
@@ -188,7 +224,9 @@ fn convert_selection_from_text(
     .then_some(())
     .ok_or(ConvertSelectionError::Reselect)?;
 
-    if let Err(e) = switch_keyboard_layout() {
+    if conversion_direction_for_text(text) != conversion_direction_for_text(&converted)
+        && let Err(e) = switch_keyboard_layout()
+    {
         tracing::trace!(error = ?e, "layout switch failed");
     }
 
@@ -296,6 +334,13 @@ fn installed_layouts() -> Vec<HKL> {
     layouts
 }
 
+/// Returns the installed keyboard layout for `tag`, the first one when several match.
+pub(crate) fn installed_layout_for(tag: LayoutTag) -> Option<HKL> {
+    installed_layouts()
+        .into_iter()
+        .find(|&hkl| layout_tag_from_hkl(hkl) == tag)
+}
+
 /// Switches the keyboard layout for the current foreground window to the next installed layout.
 ///
 /// Algorithm:
@@ -0,0 +1,77 @@
+//! Repair of words whose typing started in one layout and finished in another.
+//!
+//! Noticing a wrong layout halfway through a word and switching leaves text like `ghbвет`:
+//! the journal holds it as adjacent text runs with different layout tags. The layout the word
+//! was finished in is taken as the intended one, and only the prefix typed before the switch is
+//! converted.
+
+use super::mapping::{ConversionDirection, convert_ru_en_with_direction};
+use crate::input::ring_buffer::{InputRun, LayoutTag};
+
+/// How a mixed-layout word is repaired.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MixedWordRepair {
+    /// The word as typed, for example `ghbвет`.
+    pub typed: String,
+    /// The word with the prefix converted, for example `привет`.
+    pub repaired: String,
+    /// The word as if it had been typed entirely in the prefix layout, for example `ghbdtn`.
+    ///
+    /// The decision engine judges this form against `repaired`, since it cannot judge a word
+    /// written in two scripts.
+    pub as_typed_in_prefix_layout: String,
+    /// Layout the word was finished in.
+    pub layout: LayoutTag,
+}
+
+fn direction_from(layout: LayoutTag) -> Option<ConversionDirection> {
+    match layout {
+        LayoutTag::Ru => Some(ConversionDirection::RuToEn),
+        LayoutTag::En => Some(ConversionDirection::EnToRu),
+        LayoutTag::Other(_) | LayoutTag::Unknown => None,
+    }
+}
+
+/// Returns the repair for `runs` when they form one word that switches layout midway.
+///
+/// `runs` are the text runs of the last word, oldest first. Only RU/EN switches are handled;
+/// both the prefix and the rest must contain letters, so `hello` followed by a `,` typed after
+/// switching layouts is left alone.
+#[must_use]
+pub fn repair_mixed_word(runs: &[InputRun]) -> Option<MixedWordRepair> {
+    if runs.iter().any(|run| !run.kind.is_text()) {
+        return None;
+    }
+    let layout = runs.last()?.layout;
+    let tail_direction = direction_from(layout)?;
+
+    let split = runs.iter().rposition(|run| run.layout != layout)?;
+    let (prefix, tail) = runs.split_at(split + 1);
+    let prefix_layout = prefix.first()?.layout;
+    if prefix.iter().any(|run| run.layout != prefix_layout) {
+        return None;
+    }
+    let prefix_direction = direction_from(prefix_layout)?;
+
+    let prefix_text: String = prefix.iter().map(|run| run.text.as_str()).collect();
+    let tail_text: String = tail.iter().map(|run| run.text.as_str()).collect();
+    if !prefix_text.chars().any(char::is_alphabetic) || !tail_text.chars().any(char::is_alphabetic)
+    {
+        return None;
+    }
+
+    let repaired_prefix = convert_ru_en_with_direction(&prefix_text, prefix_direction);
+    if repaired_prefix == prefix_text {
+        return None;
+    }
+
+    Some(MixedWordRepair {
+        typed: format!("{prefix_text}{tail_text}"),
+        repaired: format!("{repaired_prefix}{tail_text}"),
+        as_typed_in_prefix_layout: format!(
+            "{prefix_text}{}",
+            convert_ru_en_with_direction(&tail_text, tail_direction)
+        ),
+        layout,
+    })
+}
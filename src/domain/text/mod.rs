@@ -1,10 +1,25 @@
+pub mod alternatives;
+pub mod autoconvert;
+pub mod conversion_history;
 #[cfg(windows)]
 pub mod convert;
 #[cfg(windows)]
 pub mod last_word;
 pub mod mapping;
+pub mod mixed_word;
+pub mod phrase;
+#[allow(
+    dead_code,
+    reason = "The Windows app runs the pipeline steps on its shared journal; the journal-owning reducer is used by the lib crate."
+)]
+pub mod pipeline;
 #[cfg(windows)]
 mod selection_probe;
+#[allow(
+    dead_code,
+    reason = "The Windows app only records sessions; replay is used by the lib crate and its tools."
+)]
+pub mod session;
 
 #[cfg(windows)]
 pub use convert::{switch_keyboard_layout, wait_shift_released};
@@ -0,0 +1,89 @@
+//! Per-token conversion for the "Convert last phrase" action.
+//!
+//! The journal finds the phrase, see [`phrase_start`](crate::input::ring_buffer::phrase_start). A phrase is the text typed since the last line break or sentence end. A sentence ends at
+//! `.`, `!`, `?` or `…` followed by whitespace and an uppercase letter. The uppercase check keeps
+//! `.` and `?` typed in the wrong layout (they are `ю` and `,` on the Russian layout) inside the phrase.
+//! The journal also ends a phrase at a pause in typing: a burst of fast typing is one phrase.
+
+use super::autoconvert::SkipReason;
+
+/// Decision of the first judged token in `decisions`, `false` when there is none.
+fn nearest_judged<'a>(mut decisions: impl Iterator<Item = &'a Option<(String, bool)>>) -> bool {
+    decisions
+        .find_map(|d| d.as_ref().map(|(_, convert)| *convert))
+        .unwrap_or(false)
+}
+
+/// Converts the tokens of `phrase` that the decision engine marks as typed in the wrong layout.
+///
+/// `convert` returns the layout conversion of the token with the given index, counting tokens
+/// but not whitespace, and `decide` judges a token against it.
+/// Tokens rejected only for being too short follow their neighbours: they are converted when the
+/// closest judged token on either side is converted. Whitespace is kept as is.
+///
+/// Returns `None` when no token changes.
+pub fn convert_phrase(
+    phrase: &str,
+    convert: impl Fn(usize, &str) -> String,
+    mut decide: impl FnMut(&str, &str) -> Result<(), SkipReason>,
+) -> Option<String> {
+    let mut segments: Vec<(&str, bool)> = Vec::new();
+    let mut start = 0usize;
+    let mut in_space: Option<bool> = None;
+    for (i, ch) in phrase.char_indices() {
+        let space = ch.is_whitespace();
+        if in_space.is_some_and(|s| s != space) {
+            segments.push((&phrase[start..i], in_space.unwrap_or(space)));
+            start = i;
+        }
+        in_space = Some(space);
+    }
+    if let Some(space) = in_space {
+        segments.push((&phrase[start..], space));
+    }
+
+    // Per token: converted text and the decision, `None` when the token is too short to judge.
+    let decisions: Vec<Option<(String, bool)>> = segments
+        .iter()
+        .filter(|(_, space)| !space)
+        .enumerate()
+        .map(|(index, (token, _))| {
+            let converted = convert(index, token);
+            if converted == *token {
+                return Some((converted, false));
+            }
+            match decide(token, &converted) {
+                Ok(()) => Some((converted, true)),
+                Err(SkipReason::TooShort) => None,
+                Err(_) => Some((converted, false)),
+            }
+        })
+        .collect();
+
+    let mut out = String::with_capacity(phrase.len());
+    let mut changed = false;
+    let mut token_idx = 0usize;
+    for (text, space) in segments {
+        if space {
+            out.push_str(text);
+            continue;
+        }
+        let replacement = match &decisions[token_idx] {
+            Some((converted, true)) => Some(converted.clone()),
+            Some((_, false)) => None,
+            None => (nearest_judged(decisions[..token_idx].iter().rev())
+                || nearest_judged(decisions[token_idx + 1..].iter()))
+            .then(|| convert(token_idx, text)),
+        };
+        match replacement {
+            Some(converted) if converted != text => {
+                out.push_str(&converted);
+                changed = true;
+            }
+            _ => out.push_str(text),
+        }
+        token_idx += 1;
+    }
+
+    changed.then_some(out)
+}
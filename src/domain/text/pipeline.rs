@@ -0,0 +1,1078 @@
+//! Platform-neutral typing pipeline.
+//!
+//! A backend turns what it observes into [`InputEvent`]s: typed text, editing keys, focus
+//! changes, hotkeys and timer ticks. [`TypingPipeline`] folds them into its journal and returns
+//! the [`Effect`]s the backend has to perform, such as retyping the last word or switching the
+//! layout. The pipeline does no I/O and reads time only through the journal clock, so the same
+//! events always give the same effects.
+//!
+//! The Windows hook shares its journal with the UI thread, so it cannot hand it to a pipeline.
+//! It applies key presses with [`InputJournal::apply`] and runs the remaining steps through
+//! [`TypingState`], the same state [`TypingPipeline`] keeps next to its journal.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::{
+    alternatives::{Alternative, ConversionCycle},
+    autoconvert::{
+        AutoconvertLearning, CONTEXT_WORDS, ContextPrior, DEFAULT_CONTEXT_WEIGHT,
+        DEFAULT_MAX_PAUSE_IN_WORD_MS, ExceptionRules, SentenceContext, SkipReason, TypingTiming,
+        check_typing_rhythm, ensure_changed, ensure_has_letters, language_detector,
+        should_autoconvert_word_with_prior,
+    },
+    conversion_history::{ConversionHistory, ConversionRecord},
+    mapping::{ConversionDirection, conversion_direction_for_text, convert_ru_en_with_direction},
+    mixed_word::{MixedWordRepair, repair_mixed_word},
+    phrase::convert_phrase,
+};
+pub use crate::input::input_event::{HotkeyCommand, InputEvent, Navigation};
+use crate::input::ring_buffer::{
+    AutoconvertRevert, InputJournal, InputRun, LayoutTag, RunKind, RunOrigin, SpanGrowth,
+};
+
+/// What a Convert press does to the text replaced by the previous press.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConvertRepeatMode {
+    /// Show the next alternative: original, transliteration, case-fixed.
+    #[default]
+    Cycle,
+    /// Pull the previous word into the converted span.
+    Extend,
+}
+
+/// How long after a Convert press the next one still cycles or extends its span.
+pub const DEFAULT_CONVERT_REPEAT_WINDOW_MS: u32 = 1500;
+
+/// Something the backend has to do in response to an event.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Effect {
+    /// Replace the `span_len` chars right before `suffix` with `replacement`.
+    ///
+    /// `suffix` is the text between the span and the caret; it is kept as it is. A one-char span
+    /// with no suffix and no replacement is a plain Backspace.
+    ReplaceSpan {
+        span_len: usize,
+        suffix: String,
+        replacement: String,
+    },
+    /// Move the caret right by that many chars.
+    MoveCaretRight(usize),
+    SwitchLayout(LayoutTag),
+    /// Add the word, as typed, to the never-convert list.
+    NeverConvert(String),
+    Notify(AutoconvertNotice),
+}
+
+/// Effects carry typed text, which is wiped like the journal runs.
+impl Drop for Effect {
+    fn drop(&mut self) {
+        match self {
+            Self::ReplaceSpan {
+                suffix,
+                replacement,
+                ..
+            } => {
+                suffix.zeroize();
+                replacement.zeroize();
+            }
+            Self::NeverConvert(word) => word.zeroize(),
+            Self::MoveCaretRight(_) | Self::SwitchLayout(_) | Self::Notify(_) => {}
+        }
+    }
+}
+
+/// Autoconvert state changes the user should be told about.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AutoconvertNotice {
+    Enabled,
+    Disabled,
+    /// Turned off after several reverts in a row.
+    Paused,
+}
+
+/// Autoconvert verdict on one word.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Decision {
+    /// The word as typed.
+    pub word: String,
+    pub converted: String,
+    pub verdict: Result<(), SkipReason>,
+}
+
+impl Drop for Decision {
+    fn drop(&mut self) {
+        self.word.zeroize();
+        self.converted.zeroize();
+    }
+}
+
+/// Configuration of the autoconvert decision.
+#[derive(Clone, Debug)]
+pub struct AutoconvertSettings {
+    pub exceptions: ExceptionRules,
+    /// Weight of the sentence context prior.
+    pub context_weight: f64,
+    /// Longest pause inside a word that is still judged, `ZERO` for any.
+    pub max_pause_in_word: Duration,
+}
+
+impl Default for AutoconvertSettings {
+    fn default() -> Self {
+        Self {
+            exceptions: ExceptionRules::default(),
+            context_weight: DEFAULT_CONTEXT_WEIGHT,
+            max_pause_in_word: Duration::from_millis(u64::from(DEFAULT_MAX_PAUSE_IN_WORD_MS)),
+        }
+    }
+}
+
+/// Layout knowledge a backend adds to the static conversion tables.
+pub trait LayoutConverter: fmt::Debug {
+    /// Text the key presses behind `runs`, typed in `layout`, give in the layout they convert to.
+    ///
+    /// `None` when that is not known; the static tables convert the text then.
+    fn redecode(&self, _runs: &[InputRun], _layout: LayoutTag) -> Option<String> {
+        None
+    }
+
+    /// Direction for text typed in a layout the tables do not cover, when its letters do not
+    /// tell.
+    fn fallback_direction(&self) -> Option<ConversionDirection> {
+        None
+    }
+}
+
+/// Converts with the static tables only.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StaticTables;
+
+impl LayoutConverter for StaticTables {}
+
+/// Layout a conversion of text typed in `layout` switches to.
+#[must_use]
+pub const fn flipped_layout(layout: LayoutTag) -> LayoutTag {
+    match layout {
+        LayoutTag::Ru => LayoutTag::En,
+        LayoutTag::En => LayoutTag::Ru,
+        other => other,
+    }
+}
+
+/// Converts text typed in `layout` with the static tables.
+///
+/// For other layouts the direction follows the letters of `text`, then `fallback`.
+#[must_use]
+pub fn convert_typed(
+    text: &str,
+    layout: LayoutTag,
+    fallback: impl FnOnce() -> Option<ConversionDirection>,
+) -> String {
+    let direction = match layout {
+        LayoutTag::Ru => Some(ConversionDirection::RuToEn),
+        LayoutTag::En => Some(ConversionDirection::EnToRu),
+        LayoutTag::Other(_) | LayoutTag::Unknown => {
+            conversion_direction_for_text(text).or_else(fallback)
+        }
+    }
+    .unwrap_or(ConversionDirection::RuToEn);
+    convert_ru_en_with_direction(text, direction)
+}
+
+/// Languages of `runs`, the words typed before the one being judged.
+#[must_use]
+pub fn sentence_context(runs: &[InputRun]) -> SentenceContext {
+    let mut context = SentenceContext::default();
+    for run in runs {
+        let layout = match run.layout {
+            LayoutTag::Ru => Some(lingua::Language::Russian),
+            LayoutTag::En => Some(lingua::Language::English),
+            LayoutTag::Other(_) | LayoutTag::Unknown => None,
+        };
+        context.observe(&run.text, layout, run.origin == RunOrigin::Programmatic);
+    }
+    context
+}
+
+/// Decides whether `word` should be replaced by `converted`.
+///
+/// Checks, in order, that the word has letters and changes when converted, that it was typed
+/// without a long pause and that the decision engine prefers the conversion in `context`.
+pub fn judge_word(
+    settings: &AutoconvertSettings,
+    word: &InputRun,
+    converted: &str,
+    context: SentenceContext,
+) -> Result<(), SkipReason> {
+    ensure_has_letters(&word.text)?;
+    ensure_changed(&word.text, converted)?;
+    check_typing_rhythm(
+        &TypingTiming::of(word.typed_at()),
+        settings.max_pause_in_word,
+    )?;
+    should_autoconvert_word_with_prior(
+        language_detector(),
+        &settings.exceptions,
+        &word.text,
+        converted,
+        &ContextPrior::new(context, settings.context_weight),
+    )
+}
+
+fn joined_text(runs: &[InputRun]) -> String {
+    runs.iter().map(|run| run.text.as_str()).collect()
+}
+
+fn has_line_break(text: &str) -> bool {
+    text.contains(['\n', '\r'])
+}
+
+/// Layout a text was most likely typed in, by letter balance.
+fn layout_tag_for_text(text: &str, fallback: LayoutTag) -> LayoutTag {
+    match conversion_direction_for_text(text) {
+        Some(ConversionDirection::RuToEn) => LayoutTag::Ru,
+        Some(ConversionDirection::EnToRu) => LayoutTag::En,
+        None => fallback,
+    }
+}
+
+/// A plain Backspace, for one the backend swallowed but the pipeline did not use.
+const fn backspace() -> Effect {
+    Effect::ReplaceSpan {
+        span_len: 1,
+        suffix: String::new(),
+        replacement: String::new(),
+    }
+}
+
+/// Word typed by the pipeline itself.
+fn inserted_word(text: String, layout: LayoutTag) -> InputRun {
+    InputRun {
+        text,
+        layout,
+        origin: RunOrigin::Programmatic,
+        kind: RunKind::Word,
+        typed: Vec::new(),
+    }
+}
+
+/// Span replaced by the last Convert press, kept to act on it again on the next press.
+#[derive(Debug)]
+struct SequenceRepeat {
+    /// Focus visit of the press, see [`InputJournal::focus_visit`].
+    focus: u64,
+    /// Key events recorded before the press, see [`InputJournal::key_events`].
+    keys: u64,
+    at: Instant,
+    cycle: ConversionCycle,
+    original_runs: Vec<InputRun>,
+    suffix_runs: Vec<InputRun>,
+    layout_before: LayoutTag,
+}
+
+/// Autoconvert, repeat and undo state of the pipeline, kept apart from the journal.
+///
+/// Each step takes the journal it works on, so a backend whose journal is shared with its input
+/// hook runs the same steps as [`TypingPipeline`].
+#[derive(Debug)]
+pub struct TypingState {
+    settings: AutoconvertSettings,
+    learning: AutoconvertLearning,
+    autoconvert_enabled: bool,
+    last_decision: Option<Decision>,
+    /// Last word replaced by autoconvert, as it was typed.
+    last_autoconverted_word: Option<Zeroizing<String>>,
+    history: ConversionHistory,
+    repeat: Option<SequenceRepeat>,
+    repeat_mode: ConvertRepeatMode,
+    repeat_window: Duration,
+    layouts: Box<dyn LayoutConverter>,
+}
+
+impl Default for TypingState {
+    fn default() -> Self {
+        Self::new(AutoconvertSettings::default())
+    }
+}
+
+impl TypingState {
+    /// State with autoconvert enabled that converts with the static tables.
+    #[must_use]
+    pub fn new(settings: AutoconvertSettings) -> Self {
+        Self {
+            settings,
+            learning: AutoconvertLearning::default(),
+            autoconvert_enabled: true,
+            last_decision: None,
+            last_autoconverted_word: None,
+            history: ConversionHistory::default(),
+            repeat: None,
+            repeat_mode: ConvertRepeatMode::default(),
+            repeat_window: Duration::from_millis(u64::from(DEFAULT_CONVERT_REPEAT_WINDOW_MS)),
+            layouts: Box::new(StaticTables),
+        }
+    }
+
+    pub fn set_layouts(&mut self, layouts: impl LayoutConverter + 'static) {
+        self.layouts = Box::new(layouts);
+    }
+
+    #[must_use]
+    pub const fn settings(&self) -> &AutoconvertSettings {
+        &self.settings
+    }
+
+    pub const fn settings_mut(&mut self) -> &mut AutoconvertSettings {
+        &mut self.settings
+    }
+
+    /// Sets after how many consecutive reverts autoconvert pauses itself, `0` for never.
+    pub fn set_pause_after_reverts(&mut self, reverts: u32) {
+        self.learning.set_pause_after_reverts(reverts);
+    }
+
+    /// Sets what a repeated Convert press does and how long after a press it still applies.
+    pub fn set_convert_repeat(&mut self, mode: ConvertRepeatMode, window: Duration) {
+        self.repeat_mode = mode;
+        self.repeat_window = window;
+        self.repeat = None;
+    }
+
+    #[must_use]
+    pub const fn autoconvert_enabled(&self) -> bool {
+        self.autoconvert_enabled
+    }
+
+    /// Turns autoconvert on or off; the count of consecutive reverts starts over.
+    pub fn set_autoconvert_enabled(&mut self, enabled: bool) {
+        self.autoconvert_enabled = enabled;
+        self.learning.reset();
+    }
+
+    #[must_use]
+    pub fn last_autoconverted_word(&self) -> Option<&str> {
+        self.last_autoconverted_word.as_deref().map(String::as_str)
+    }
+
+    pub fn forget_last_autoconverted_word(&mut self) {
+        self.last_autoconverted_word = None;
+    }
+
+    /// Takes the autoconvert decision made by the last step, if any.
+    pub const fn take_decision(&mut self) -> Option<Decision> {
+        self.last_decision.take()
+    }
+
+    /// Folds `event` into `journal` and returns what the backend has to do, in order.
+    pub fn handle(&mut self, journal: &mut InputJournal, event: &InputEvent) -> Vec<Effect> {
+        self.last_decision = None;
+        let _ = journal.expire_if_idle();
+        match event {
+            InputEvent::Backspace if journal.request_autoconvert_revert() => {
+                return self.revert_autoconvert(journal);
+            }
+            InputEvent::Hotkey(command) => return self.run_hotkey(journal, *command),
+            _ => {}
+        }
+        journal.apply(event);
+        if !matches!(
+            event,
+            InputEvent::Text {
+                origin: RunOrigin::Physical,
+                ..
+            }
+        ) {
+            return Vec::new();
+        }
+        self.word_ended(journal)
+    }
+
+    /// Autoconverts the last word when the last char typed into `journal` ended it.
+    ///
+    /// This is the step that follows [`InputJournal::apply`] for typed text.
+    pub fn word_ended(&mut self, journal: &mut InputJournal) -> Vec<Effect> {
+        self.last_decision = None;
+        let ends_word = self.autoconvert_enabled
+            && !journal.last_token_autoconverted()
+            && journal.last_char_triggers_autoconvert();
+        if !ends_word {
+            return Vec::new();
+        }
+        self.autoconvert_mixed_word(journal)
+            .unwrap_or_else(|| self.autoconvert_last_word(journal))
+    }
+
+    pub fn run_hotkey(
+        &mut self,
+        journal: &mut InputJournal,
+        command: HotkeyCommand,
+    ) -> Vec<Effect> {
+        self.last_decision = None;
+        match command {
+            HotkeyCommand::ConvertLastWord => self.convert_last_sequence(journal),
+            HotkeyCommand::ConvertLastPhrase => self.convert_last_phrase(journal),
+            HotkeyCommand::UndoConversion => self.undo_last_conversion(journal),
+            HotkeyCommand::ToggleAutoconvert => {
+                self.set_autoconvert_enabled(!self.autoconvert_enabled);
+                let notice = if self.autoconvert_enabled {
+                    AutoconvertNotice::Enabled
+                } else {
+                    AutoconvertNotice::Disabled
+                };
+                vec![Effect::Notify(notice)]
+            }
+        }
+    }
+
+    /// Undoes the last autoconvert for the Backspace that claimed it, see
+    /// [`InputJournal::request_autoconvert_revert`].
+    ///
+    /// When the journal no longer ends with the conversion, the Backspace is applied as a normal
+    /// edit instead.
+    pub fn revert_autoconvert(&mut self, journal: &mut InputJournal) -> Vec<Effect> {
+        self.last_decision = None;
+        let Some(revert) = journal.take_requested_autoconvert_revert() else {
+            return self.keep_backspace(journal);
+        };
+        let Some((word, suffix_runs)) = journal.take_last_layout_run_with_suffix() else {
+            return self.keep_backspace(journal);
+        };
+        if word.text != revert.converted.text || suffix_runs != revert.suffix_runs {
+            restore(journal, [word], suffix_runs);
+            return self.keep_backspace(journal);
+        }
+        let mut effects = vec![Effect::ReplaceSpan {
+            span_len: word.text.chars().count(),
+            suffix: joined_text(&suffix_runs),
+            replacement: revert.original.text.clone(),
+        }];
+        if revert.switched_layout {
+            effects.push(Effect::SwitchLayout(revert.original.layout));
+        }
+        // The conversion is gone from the text, so it can no longer be undone.
+        let _ = self.history.pop(journal.focus_visit());
+        self.last_autoconverted_word = None;
+        let outcome = self.learning.record_revert(&revert.original.text);
+        restore(journal, [revert.original], suffix_runs);
+        effects.push(Effect::NeverConvert(outcome.word));
+        if outcome.pause_autoconvert {
+            self.set_autoconvert_enabled(false);
+            effects.push(Effect::Notify(AutoconvertNotice::Paused));
+        }
+        effects
+    }
+
+    /// Gives up the revert claimed by the last Backspace, which becomes a normal edit.
+    pub fn keep_backspace(&mut self, journal: &mut InputJournal) -> Vec<Effect> {
+        let _ = journal.take_requested_autoconvert_revert();
+        journal.backspace();
+        vec![backspace()]
+    }
+
+    /// Forgets what is known about the text after the backend failed to perform an effect.
+    ///
+    /// Part of the effect may have reached the text, so the journal, the undo history and the
+    /// span kept for a repeated press no longer describe it.
+    pub fn effect_failed(&mut self, journal: &mut InputJournal) {
+        journal.clear();
+        self.history.invalidate();
+        self.repeat = None;
+    }
+
+    /// Forgets what the last step recorded about a conversion the backend dropped unperformed,
+    /// for example because the text changed while the step ran.
+    pub fn step_dropped(&mut self) {
+        self.history.invalidate();
+        self.repeat = None;
+        self.last_autoconverted_word = None;
+    }
+
+    fn context(journal: &InputJournal) -> SentenceContext {
+        sentence_context(&journal.recent_text_runs(CONTEXT_WORDS))
+    }
+
+    /// Converts `text` typed in `layout`, by the static tables.
+    fn convert_text(&self, text: &str, layout: LayoutTag) -> String {
+        convert_typed(text, layout, || self.layouts.fallback_direction())
+    }
+
+    /// Converts the text of `runs`, preferring their key presses over the static tables.
+    fn convert_runs(&self, runs: &[InputRun], text: &str, layout: LayoutTag) -> String {
+        self.layouts
+            .redecode(runs, layout)
+            .unwrap_or_else(|| self.convert_text(text, layout))
+    }
+
+    fn record_conversion(&mut self, journal: &InputJournal, record: ConversionRecord) {
+        self.history.push(journal.focus_visit(), record);
+    }
+
+    /// Autoconverts the prefix of a word whose layout was switched midway.
+    ///
+    /// The word is judged as if typed entirely in the prefix layout, the engine cannot judge two
+    /// scripts. Returns `None` when the last word is not a mixed-layout word.
+    fn autoconvert_mixed_word(&mut self, journal: &mut InputJournal) -> Option<Vec<Effect>> {
+        let (runs, suffix_runs) = journal.take_last_word_runs_with_suffix()?;
+        let Some(repair) = repair_mixed_word(&runs) else {
+            restore(journal, runs, suffix_runs);
+            return None;
+        };
+        let suffix = joined_text(&suffix_runs);
+        // Sized up front: a growing buffer would leave copies of the key presses behind.
+        let mut typed = Vec::with_capacity(runs.iter().map(|run| run.typed.len()).sum());
+        for run in &runs {
+            typed.extend_from_slice(&run.typed);
+        }
+        let original = InputRun {
+            text: repair.typed.clone(),
+            layout: repair.layout,
+            origin: RunOrigin::Physical,
+            kind: RunKind::Word,
+            typed,
+        };
+        let judged = InputRun {
+            text: repair.as_typed_in_prefix_layout.clone(),
+            layout: original.layout,
+            origin: original.origin,
+            kind: original.kind,
+            typed: original.typed.clone(),
+        };
+        let verdict = if has_line_break(&suffix) {
+            Err(SkipReason::SuffixHasNewline)
+        } else {
+            judge_word(
+                &self.settings,
+                &judged,
+                &repair.repaired,
+                Self::context(journal),
+            )
+        };
+        self.last_decision = Some(Decision {
+            word: repair.typed.clone(),
+            converted: repair.repaired.clone(),
+            verdict,
+        });
+        if verdict.is_err() {
+            restore(journal, runs, suffix_runs);
+            return Some(Vec::new());
+        }
+        let effect = self.replace_mixed_word(journal, runs, suffix_runs.clone(), &repair, suffix);
+        journal.mark_last_token_autoconverted(AutoconvertRevert {
+            original,
+            converted: inserted_word(repair.repaired, repair.layout),
+            suffix_runs,
+            switched_layout: false,
+        });
+        self.last_autoconverted_word = Some(Zeroizing::new(repair.as_typed_in_prefix_layout));
+        self.learning.record_autoconvert();
+        Some(vec![effect])
+    }
+
+    /// Replaces a mixed-layout word by its repair and records it for undo.
+    ///
+    /// The layout is left alone: the user already switched to the one the word was finished in.
+    fn replace_mixed_word(
+        &mut self,
+        journal: &mut InputJournal,
+        runs: Vec<InputRun>,
+        suffix_runs: Vec<InputRun>,
+        repair: &MixedWordRepair,
+        suffix: String,
+    ) -> Effect {
+        journal.push_run(inserted_word(repair.repaired.clone(), repair.layout));
+        journal.push_runs(suffix_runs.iter().cloned());
+        self.record_conversion(
+            journal,
+            ConversionRecord {
+                original_runs: runs,
+                converted: repair.repaired.clone(),
+                suffix_runs,
+                layout_before: repair.layout,
+                layout_after: repair.layout,
+            },
+        );
+        Effect::ReplaceSpan {
+            span_len: repair.typed.chars().count(),
+            suffix,
+            replacement: repair.repaired.clone(),
+        }
+    }
+
+    fn autoconvert_last_word(&mut self, journal: &mut InputJournal) -> Vec<Effect> {
+        let Some((word, suffix_runs)) = journal.take_last_layout_run_with_suffix() else {
+            return Vec::new();
+        };
+        if !word.kind.is_text() || word.text.is_empty() {
+            restore(journal, [word], suffix_runs);
+            return Vec::new();
+        }
+        let suffix = joined_text(&suffix_runs);
+        let converted = self.convert_runs(std::slice::from_ref(&word), &word.text, word.layout);
+        let verdict = if has_line_break(&suffix) {
+            Err(SkipReason::SuffixHasNewline)
+        } else {
+            judge_word(&self.settings, &word, &converted, Self::context(journal))
+        };
+        self.last_decision = Some(Decision {
+            word: word.text.clone(),
+            converted: converted.clone(),
+            verdict,
+        });
+        if verdict.is_err() {
+            restore(journal, [word], suffix_runs);
+            return Vec::new();
+        }
+        let layout = flipped_layout(word.layout);
+        journal.push_run(inserted_word(converted.clone(), layout));
+        journal.push_runs(suffix_runs.iter().cloned());
+        journal.mark_last_token_autoconverted(AutoconvertRevert {
+            original: word.clone(),
+            converted: inserted_word(converted.clone(), layout),
+            suffix_runs: suffix_runs.clone(),
+            switched_layout: true,
+        });
+        self.record_conversion(
+            journal,
+            ConversionRecord {
+                original_runs: vec![word.clone()],
+                converted: converted.clone(),
+                suffix_runs,
+                layout_before: word.layout,
+                layout_after: layout,
+            },
+        );
+        self.last_autoconverted_word = Some(Zeroizing::new(word.text.clone()));
+        self.learning.record_autoconvert();
+        vec![
+            Effect::ReplaceSpan {
+                span_len: word.text.chars().count(),
+                suffix,
+                replacement: converted,
+            },
+            Effect::SwitchLayout(layout),
+        ]
+    }
+
+    /// Converts the text typed since the last layout change, the way the Convert hotkey does.
+    ///
+    /// The caret is first moved past the rest of the word it is in, so that the word is converted
+    /// whole. A press right after another one acts on the converted text instead.
+    fn convert_last_sequence(&mut self, journal: &mut InputJournal) -> Vec<Effect> {
+        let repeated = match self.repeat_mode {
+            ConvertRepeatMode::Cycle => self.cycle_last_sequence(journal),
+            ConvertRepeatMode::Extend => self.extend_last_sequence(journal),
+        };
+        if let Some(effects) = repeated {
+            return effects;
+        }
+        let mut effects = move_caret_to_word_end(journal);
+        if let Some(mixed) = self.convert_mixed_word(journal) {
+            effects.extend(mixed);
+        } else {
+            effects.extend(self.convert_sequence(journal));
+        }
+        effects
+    }
+
+    /// Manual counterpart of [`Self::autoconvert_mixed_word`]: repairs the prefix without a
+    /// decision.
+    fn convert_mixed_word(&mut self, journal: &mut InputJournal) -> Option<Vec<Effect>> {
+        let (runs, suffix_runs) = journal.take_last_word_runs_with_suffix()?;
+        let Some(repair) = repair_mixed_word(&runs) else {
+            restore(journal, runs, suffix_runs);
+            return None;
+        };
+        let suffix = joined_text(&suffix_runs);
+        if has_line_break(&suffix) {
+            restore(journal, runs, suffix_runs);
+            return Some(Vec::new());
+        }
+        self.repeat = Some(SequenceRepeat {
+            focus: journal.focus_visit(),
+            keys: journal.key_events(),
+            at: journal.now(),
+            cycle: ConversionCycle::new(&repair.typed, &repair.repaired),
+            original_runs: runs.clone(),
+            suffix_runs: suffix_runs.clone(),
+            layout_before: repair.layout,
+        });
+        Some(vec![self.replace_mixed_word(
+            journal,
+            runs,
+            suffix_runs,
+            &repair,
+            suffix,
+        )])
+    }
+
+    fn convert_sequence(&mut self, journal: &mut InputJournal) -> Vec<Effect> {
+        let Some((runs, suffix_runs)) = journal.take_last_layout_sequence_with_suffix() else {
+            return Vec::new();
+        };
+        let text = joined_text(&runs);
+        let suffix = joined_text(&suffix_runs);
+        let Some(layout) = runs
+            .last()
+            .filter(|run| run.kind.is_text())
+            .map(|run| run.layout)
+        else {
+            restore(journal, runs, suffix_runs);
+            return Vec::new();
+        };
+        let converted = self.convert_runs(&runs, &text, layout);
+        if has_line_break(&text) || has_line_break(&suffix) || converted == text {
+            restore(journal, runs, suffix_runs);
+            return Vec::new();
+        }
+        let layout_after = flipped_layout(layout);
+        journal.push_text(&converted, layout_after, RunOrigin::Programmatic);
+        journal.push_runs(suffix_runs.iter().cloned());
+        self.record_conversion(
+            journal,
+            ConversionRecord {
+                original_runs: runs.clone(),
+                converted: converted.clone(),
+                suffix_runs: suffix_runs.clone(),
+                layout_before: layout,
+                layout_after,
+            },
+        );
+        self.repeat = Some(SequenceRepeat {
+            focus: journal.focus_visit(),
+            keys: journal.key_events(),
+            at: journal.now(),
+            cycle: ConversionCycle::new(&text, &converted),
+            original_runs: runs,
+            suffix_runs,
+            layout_before: layout,
+        });
+        vec![
+            Effect::ReplaceSpan {
+                span_len: text.chars().count(),
+                suffix,
+                replacement: converted,
+            },
+            Effect::SwitchLayout(layout_after),
+        ]
+    }
+
+    /// Takes the previous Convert press, if it was made during the current focus visit, within
+    /// the repeat window and with no key recorded since.
+    fn take_repeat(&mut self, journal: &InputJournal) -> Option<SequenceRepeat> {
+        let now = journal.now();
+        self.repeat.take().filter(|repeat| {
+            repeat.focus == journal.focus_visit()
+                && repeat.keys == journal.key_events()
+                && now.saturating_duration_since(repeat.at) <= self.repeat_window
+        })
+    }
+
+    /// Replaces the span shown by the previous Convert press with the next alternative.
+    ///
+    /// Returns `None` when the press must perform a fresh conversion: there was no previous
+    /// press, it was made before the repeat window, or a key was recorded since.
+    fn cycle_last_sequence(&mut self, journal: &mut InputJournal) -> Option<Vec<Effect>> {
+        let mut repeat = self.take_repeat(journal)?;
+        let shown = repeat.cycle.current().to_string();
+        let suffix = joined_text(&repeat.suffix_runs);
+        if !journal.take_tail_if_matches(&format!("{shown}{suffix}")) {
+            return None;
+        }
+        let next = repeat.cycle.advance().to_string();
+        let alternative = repeat.cycle.current_alternative();
+        let shown_layout = layout_tag_for_text(&shown, LayoutTag::Unknown);
+        let next_layout = if alternative == Alternative::Original {
+            journal.push_runs(repeat.original_runs.iter().cloned());
+            repeat.layout_before
+        } else {
+            let layout = layout_tag_for_text(&next, repeat.layout_before);
+            journal.push_text(&next, layout, RunOrigin::Programmatic);
+            layout
+        };
+        journal.push_runs(repeat.suffix_runs.iter().cloned());
+        // Keep undo pointing at the original text rather than at an intermediate alternative.
+        let visit = journal.focus_visit();
+        let _ = self.history.pop(visit);
+        if alternative != Alternative::Original {
+            self.history.push(
+                visit,
+                ConversionRecord {
+                    original_runs: repeat.original_runs.clone(),
+                    converted: next.clone(),
+                    suffix_runs: repeat.suffix_runs.clone(),
+                    layout_before: repeat.layout_before,
+                    layout_after: next_layout,
+                },
+            );
+        }
+        let mut effects = vec![Effect::ReplaceSpan {
+            span_len: shown.chars().count(),
+            suffix,
+            replacement: next,
+        }];
+        if next_layout != shown_layout {
+            effects.push(Effect::SwitchLayout(next_layout));
+        }
+        repeat.at = journal.now();
+        self.repeat = Some(repeat);
+        Some(effects)
+    }
+
+    /// Grows the span converted by the previous press by one word to the left.
+    ///
+    /// The word before the span and the whitespace between them are taken from the journal,
+    /// converted and typed again together with the already converted span. Growth stops at a
+    /// line break. Presses after the repeat window perform a fresh conversion.
+    fn extend_last_sequence(&mut self, journal: &mut InputJournal) -> Option<Vec<Effect>> {
+        let now = journal.now();
+        let mut repeat = self.take_repeat(journal)?;
+        let shown = repeat.cycle.current().to_string();
+        let suffix = joined_text(&repeat.suffix_runs);
+        let (word, gap_runs) = match journal.take_word_before_span(&shown, &suffix) {
+            SpanGrowth::Changed => return None,
+            SpanGrowth::Boundary => {
+                repeat.at = now;
+                self.repeat = Some(repeat);
+                return Some(Vec::new());
+            }
+            SpanGrowth::Grown { word, gap } => (word, gap),
+        };
+        let gap_text = joined_text(&gap_runs);
+        let word_converted =
+            self.convert_runs(std::slice::from_ref(&word), &word.text, word.layout);
+        let grown = format!("{word_converted}{gap_text}{shown}");
+        let span_len = word.text.chars().count() + gap_text.chars().count() + shown.chars().count();
+        let grown_layout = layout_tag_for_text(&grown, flipped_layout(repeat.layout_before));
+        journal.push_text(&grown, grown_layout, RunOrigin::Programmatic);
+        journal.push_runs(repeat.suffix_runs.iter().cloned());
+
+        let mut original_runs = Vec::with_capacity(repeat.original_runs.len() + gap_runs.len() + 1);
+        original_runs.push(word);
+        original_runs.extend(gap_runs);
+        original_runs.append(&mut repeat.original_runs);
+        let visit = journal.focus_visit();
+        let _ = self.history.pop(visit);
+        self.history.push(
+            visit,
+            ConversionRecord {
+                original_runs: original_runs.clone(),
+                converted: grown.clone(),
+                suffix_runs: repeat.suffix_runs.clone(),
+                layout_before: repeat.layout_before,
+                layout_after: grown_layout,
+            },
+        );
+        repeat.cycle = ConversionCycle::new(&joined_text(&original_runs), &grown);
+        repeat.original_runs = original_runs;
+        repeat.at = now;
+        self.repeat = Some(repeat);
+        Some(vec![Effect::ReplaceSpan {
+            span_len,
+            suffix,
+            replacement: grown,
+        }])
+    }
+
+    /// Converts the wrong-layout words typed since the last sentence end, line break or focus
+    /// change.
+    ///
+    /// Each word is converted from the layout it was typed in and judged by the autoconvert
+    /// decision engine, with the text before the phrase as context, so words already typed in the
+    /// right layout are kept. The layout is switched when the last word of the phrase was
+    /// converted.
+    fn convert_last_phrase(&mut self, journal: &mut InputJournal) -> Vec<Effect> {
+        let mut effects = move_caret_to_word_end(journal);
+        let Some((runs, suffix_runs)) = journal.take_last_phrase_with_suffix() else {
+            return effects;
+        };
+        let text = joined_text(&runs);
+        let suffix = joined_text(&suffix_runs);
+        let Some(layout) = runs
+            .last()
+            .filter(|run| run.kind.is_text())
+            .map(|run| run.layout)
+        else {
+            restore(journal, runs, suffix_runs);
+            return effects;
+        };
+        let detector = language_detector();
+        let prior = ContextPrior::new(Self::context(journal), self.settings.context_weight);
+        // Runs of each token, in the order `convert_phrase` numbers them.
+        let tokens: Vec<&[InputRun]> = runs
+            .split(|run| !run.kind.is_text())
+            .filter(|token| !token.is_empty())
+            .collect();
+        let converted = if has_line_break(&suffix) {
+            None
+        } else {
+            convert_phrase(
+                &text,
+                |index, token| {
+                    tokens.get(index).map_or_else(
+                        || self.convert_text(token, LayoutTag::Unknown),
+                        |runs| {
+                            runs.iter()
+                                .map(|run| {
+                                    self.convert_runs(
+                                        std::slice::from_ref(run),
+                                        &run.text,
+                                        run.layout,
+                                    )
+                                })
+                                .collect()
+                        },
+                    )
+                },
+                |token, converted| {
+                    ensure_has_letters(token)?;
+                    should_autoconvert_word_with_prior(
+                        detector,
+                        &self.settings.exceptions,
+                        token,
+                        converted,
+                        &prior,
+                    )
+                },
+            )
+        };
+        let Some(converted) = converted else {
+            restore(journal, runs, suffix_runs);
+            return effects;
+        };
+        let layout_after = layout_tag_for_text(&converted, layout);
+        journal.push_text(&converted, layout_after, RunOrigin::Programmatic);
+        journal.push_runs(suffix_runs.iter().cloned());
+        self.record_conversion(
+            journal,
+            ConversionRecord {
+                original_runs: runs,
+                converted: converted.clone(),
+                suffix_runs,
+                layout_before: layout,
+                layout_after,
+            },
+        );
+        self.repeat = None;
+        let last_word_changed =
+            text.split_whitespace().last() != converted.split_whitespace().last();
+        effects.push(Effect::ReplaceSpan {
+            span_len: text.chars().count(),
+            suffix,
+            replacement: converted,
+        });
+        if last_word_changed {
+            effects.push(Effect::SwitchLayout(layout_after));
+        }
+        effects
+    }
+
+    /// Restores the text replaced by the most recent conversion in the focused window.
+    ///
+    /// The converted text must still be right before the caret; otherwise the whole history is
+    /// dropped because none of it can be located any more.
+    fn undo_last_conversion(&mut self, journal: &mut InputJournal) -> Vec<Effect> {
+        let Some(mut record) = self.history.pop(journal.focus_visit()) else {
+            return Vec::new();
+        };
+        let suffix = record.suffix_text();
+        let tail = Zeroizing::new(format!("{}{suffix}", record.converted));
+        if !journal.take_tail_if_matches(&tail) {
+            self.history.invalidate();
+            return Vec::new();
+        }
+        let mut effects = vec![Effect::ReplaceSpan {
+            span_len: record.converted.chars().count(),
+            suffix,
+            replacement: record.original_text(),
+        }];
+        if record.layout_before != record.layout_after {
+            effects.push(Effect::SwitchLayout(record.layout_before));
+        }
+        restore(
+            journal,
+            std::mem::take(&mut record.original_runs),
+            std::mem::take(&mut record.suffix_runs),
+        );
+        self.last_autoconverted_word = None;
+        effects
+    }
+}
+
+/// Puts runs taken off the journal back unchanged.
+fn restore(
+    journal: &mut InputJournal,
+    runs: impl IntoIterator<Item = InputRun>,
+    suffix_runs: Vec<InputRun>,
+) {
+    journal.push_runs(runs);
+    journal.push_runs(suffix_runs);
+}
+
+/// Moves the journal caret past the rest of the word it is in.
+fn move_caret_to_word_end(journal: &mut InputJournal) -> Vec<Effect> {
+    match journal.move_caret_to_word_end() {
+        0 => Vec::new(),
+        moved => vec![Effect::MoveCaretRight(moved)],
+    }
+}
+
+/// Journal plus autoconvert state, driven by [`InputEvent`]s.
+#[derive(Debug)]
+pub struct TypingPipeline {
+    journal: InputJournal,
+    state: TypingState,
+}
+
+impl TypingPipeline {
+    /// Pipeline over `journal` with autoconvert enabled.
+    #[must_use]
+    pub fn new(journal: InputJournal, settings: AutoconvertSettings) -> Self {
+        Self {
+            journal,
+            state: TypingState::new(settings),
+        }
+    }
+
+    #[must_use]
+    pub const fn journal(&self) -> &InputJournal {
+        &self.journal
+    }
+
+    pub const fn journal_mut(&mut self) -> &mut InputJournal {
+        &mut self.journal
+    }
+
+    pub const fn state_mut(&mut self) -> &mut TypingState {
+        &mut self.state
+    }
+
+    pub const fn settings_mut(&mut self) -> &mut AutoconvertSettings {
+        self.state.settings_mut()
+    }
+
+    /// Sets after how many consecutive reverts autoconvert pauses itself, `0` for never.
+    pub fn set_pause_after_reverts(&mut self, reverts: u32) {
+        self.state.set_pause_after_reverts(reverts);
+    }
+
+    #[must_use]
+    pub const fn autoconvert_enabled(&self) -> bool {
+        self.state.autoconvert_enabled()
+    }
+
+    /// Takes the autoconvert decision made by the last [`Self::handle`] call, if any.
+    pub const fn take_decision(&mut self) -> Option<Decision> {
+        self.state.take_decision()
+    }
+
+    /// Folds `event` into the pipeline and returns what the backend has to do, in order.
+    pub fn handle(&mut self, event: &InputEvent) -> Vec<Effect> {
+        self.state.handle(&mut self.journal, event)
+    }
+}
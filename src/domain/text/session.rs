@@ -0,0 +1,455 @@
+//! Recording and replay of typing sessions.
+//!
+//! A session file is JSON Lines, one object per line. Every line has `ms`, the time since the
+//! recording started, and a `type`:
+//!
+//! - `start`: the autoconvert settings of the session, always the first line: `context_weight`,
+//!   `max_pause_in_word_ms`, `trigger_chars`, `exceptions` and `pause_after_reverts`.
+//! - `text`: text typed by one key press, or inserted at once when `origin` is `programmatic`,
+//!   with its `layout`.
+//! - `backspace`, `delete`, `delete_word_backward`, `delete_word_forward`, `untracked`, `tick`.
+//! - `navigate`: caret movement, `to` is `caret_left`, `caret_right` or `unknown` (the journal
+//!   is cleared).
+//! - `focus_changed`: `window` numbers the windows in the order the session first saw them,
+//!   `null` when nothing has focus.
+//! - `hotkey`: `command` is `convert_last_word`, `convert_last_phrase`, `undo_conversion` or
+//!   `toggle_autoconvert`.
+//! - `decision`: an autoconvert verdict on `word`, `verdict` is `convert` or a skip reason.
+//!
+//! Replaying runs the lines through the steps the Windows app takes, on a clock driven by `ms`,
+//! and collects the decisions, so a recorded session doubles as a regression test: the replayed
+//! decisions must match the recorded ones. Sessions recorded before the exception lists were
+//! replay without them.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, LineWriter, Write},
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    autoconvert::{
+        AutoconvertExceptions, DEFAULT_CONTEXT_WEIGHT, DEFAULT_MAX_PAUSE_IN_WORD_MS,
+        DEFAULT_PAUSE_AFTER_REVERTS, ExceptionRules,
+    },
+    pipeline::{
+        AutoconvertSettings, Decision, Effect, HotkeyCommand, InputEvent, Navigation, TypingState,
+    },
+};
+use crate::input::ring_buffer::{
+    CharKey, DEFAULT_AUTOCONVERT_TRIGGER_CHARS, DEFAULT_CAPACITY, Focus, InputJournal, LayoutTag,
+    RunOrigin, SingleWindow, WindowId,
+};
+
+/// One line of a session file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionLine {
+    /// Milliseconds since the recording started.
+    pub ms: u64,
+    #[serde(flatten)]
+    pub entry: SessionEntry,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEntry {
+    Start(SessionSettings),
+    Text {
+        text: String,
+        layout: LayoutTag,
+        #[serde(default = "physical")]
+        origin: RunOrigin,
+    },
+    Backspace,
+    Delete,
+    DeleteWordBackward,
+    DeleteWordForward,
+    Navigate {
+        to: Navigation,
+    },
+    FocusChanged {
+        window: Option<u32>,
+    },
+    Untracked,
+    Hotkey {
+        command: HotkeyCommand,
+    },
+    Tick,
+    Decision(DecisionRecord),
+}
+
+const fn physical() -> RunOrigin {
+    RunOrigin::Physical
+}
+
+/// Settings a session was recorded with, written on its `start` line.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionSettings {
+    pub context_weight: f64,
+    pub max_pause_in_word_ms: u64,
+    pub trigger_chars: String,
+    #[serde(default)]
+    pub exceptions: AutoconvertExceptions,
+    #[serde(default = "default_pause_after_reverts")]
+    pub pause_after_reverts: u32,
+}
+
+const fn default_pause_after_reverts() -> u32 {
+    DEFAULT_PAUSE_AFTER_REVERTS
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            context_weight: DEFAULT_CONTEXT_WEIGHT,
+            max_pause_in_word_ms: u64::from(DEFAULT_MAX_PAUSE_IN_WORD_MS),
+            trigger_chars: DEFAULT_AUTOCONVERT_TRIGGER_CHARS.to_string(),
+            exceptions: AutoconvertExceptions::default(),
+            pause_after_reverts: DEFAULT_PAUSE_AFTER_REVERTS,
+        }
+    }
+}
+
+impl SessionSettings {
+    /// Sets up `state` and `journal` as the app does for these settings.
+    fn apply(&self, state: &mut TypingState, journal: &mut InputJournal) {
+        let settings = state.settings_mut();
+        settings.exceptions = compile_exceptions(&self.exceptions);
+        settings.context_weight = self.context_weight;
+        settings.max_pause_in_word = Duration::from_millis(self.max_pause_in_word_ms);
+        state.set_pause_after_reverts(self.pause_after_reverts);
+        journal.set_trigger_chars(&self.trigger_chars);
+    }
+}
+
+fn compile_exceptions(exceptions: &AutoconvertExceptions) -> ExceptionRules {
+    ExceptionRules::compile(exceptions).unwrap_or_default()
+}
+
+/// A [`Decision`] as written to a session file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DecisionRecord {
+    pub word: String,
+    pub converted: String,
+    /// `convert`, or the reason the word was left alone.
+    pub verdict: String,
+}
+
+impl From<&Decision> for DecisionRecord {
+    fn from(decision: &Decision) -> Self {
+        Self {
+            word: decision.word.clone(),
+            converted: decision.converted.clone(),
+            verdict: match decision.verdict {
+                Ok(()) => "convert".to_string(),
+                Err(reason) => reason.as_str().to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for DecisionRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}: {}", self.word, self.converted, self.verdict)
+    }
+}
+
+/// Writes the events of a session as they happen.
+#[derive(Debug)]
+pub struct SessionRecorder<W: Write> {
+    out: W,
+    started: Instant,
+    /// Windows seen so far; a window is recorded by its index.
+    windows: Vec<Focus>,
+}
+
+impl<W: Write> SessionRecorder<W> {
+    /// Starts a session at `started` and writes its `start` line.
+    pub fn start(out: W, started: Instant, settings: &SessionSettings) -> io::Result<Self> {
+        let mut recorder = Self {
+            out,
+            started,
+            windows: Vec::new(),
+        };
+        recorder.write(started, SessionEntry::Start(settings.clone()))?;
+        Ok(recorder)
+    }
+
+    pub fn record_event(&mut self, event: &InputEvent, at: Instant) -> io::Result<()> {
+        let entry = match event {
+            InputEvent::Text {
+                text,
+                layout,
+                origin,
+                ..
+            } => SessionEntry::Text {
+                text: text.clone(),
+                layout: *layout,
+                origin: *origin,
+            },
+            InputEvent::Backspace => SessionEntry::Backspace,
+            InputEvent::Delete => SessionEntry::Delete,
+            InputEvent::DeleteWordBackward => SessionEntry::DeleteWordBackward,
+            InputEvent::DeleteWordForward => SessionEntry::DeleteWordForward,
+            InputEvent::Navigate(to) => SessionEntry::Navigate { to: *to },
+            InputEvent::FocusChanged(focus) => SessionEntry::FocusChanged {
+                window: focus.map(|focus| self.window_number(focus)),
+            },
+            InputEvent::Untracked => SessionEntry::Untracked,
+            InputEvent::Hotkey(command) => SessionEntry::Hotkey { command: *command },
+            InputEvent::Tick => SessionEntry::Tick,
+        };
+        self.write(at, entry)
+    }
+
+    pub fn record_decision(&mut self, decision: &Decision, at: Instant) -> io::Result<()> {
+        self.write(at, SessionEntry::Decision(decision.into()))
+    }
+
+    /// Gives up the writer, for example to inspect what was recorded.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn window_number(&mut self, focus: Focus) -> u32 {
+        let index = self
+            .windows
+            .iter()
+            .position(|seen| *seen == focus)
+            .unwrap_or_else(|| {
+                self.windows.push(focus);
+                self.windows.len() - 1
+            });
+        u32::try_from(index).unwrap_or(u32::MAX)
+    }
+
+    fn write(&mut self, at: Instant, entry: SessionEntry) -> io::Result<()> {
+        let line = SessionLine {
+            ms: u64::try_from(at.saturating_duration_since(self.started).as_millis())
+                .unwrap_or(u64::MAX),
+            entry,
+        };
+        serde_json::to_writer(&mut self.out, &line)?;
+        self.out.write_all(b"\n")
+    }
+}
+
+static RECORDER: Mutex<Option<SessionRecorder<LineWriter<File>>>> = Mutex::new(None);
+
+fn with_recorder(f: impl FnOnce(&mut SessionRecorder<LineWriter<File>>) -> io::Result<()>) {
+    let mut guard = RECORDER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let Some(recorder) = guard.as_mut() else {
+        return;
+    };
+    if let Err(e) = f(recorder) {
+        tracing::warn!(error = %e, "session recording stopped");
+        *guard = None;
+    }
+}
+
+/// Starts recording the process-wide session to `path`, replacing the file.
+///
+/// Does nothing when a recording is already running.
+pub fn start_recording(path: &Path, settings: &SessionSettings) -> io::Result<()> {
+    let mut guard = RECORDER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if guard.is_none() {
+        let out = LineWriter::new(File::create(path)?);
+        *guard = Some(SessionRecorder::start(out, Instant::now(), settings)?);
+    }
+    Ok(())
+}
+
+pub fn stop_recording() {
+    *RECORDER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+}
+
+/// Records `event` when a recording is running.
+pub fn record_event(event: &InputEvent) {
+    with_recorder(|recorder| recorder.record_event(event, Instant::now()));
+}
+
+/// Records `decision` when a recording is running.
+pub fn record_decision(decision: &Decision) {
+    with_recorder(|recorder| recorder.record_decision(decision, Instant::now()));
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SessionParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SessionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Parses a session file into its lines, each with its 1-based line number.
+///
+/// Blank lines are skipped.
+pub fn parse_session(src: &str) -> Result<Vec<(usize, SessionLine)>, SessionParseError> {
+    src.lines()
+        .enumerate()
+        .filter(|(_, raw)| !raw.trim().is_empty())
+        .map(|(index, raw)| {
+            serde_json::from_str(raw)
+                .map(|line| (index + 1, line))
+                .map_err(|e| SessionParseError {
+                    line: index + 1,
+                    message: e.to_string(),
+                })
+        })
+        .collect()
+}
+
+/// Decision made while replaying the event on `line`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReplayedDecision {
+    pub line: usize,
+    pub decision: DecisionRecord,
+}
+
+/// Outcome of [`replay`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Replay {
+    /// Decisions written to the session file.
+    pub recorded: Vec<DecisionRecord>,
+    /// Decisions the pipeline makes now.
+    pub replayed: Vec<ReplayedDecision>,
+}
+
+impl Replay {
+    /// Whether the pipeline still decides as it did when the session was recorded.
+    #[must_use]
+    pub fn matches_recording(&self) -> bool {
+        self.recorded
+            .iter()
+            .eq(self.replayed.iter().map(|replayed| &replayed.decision))
+    }
+}
+
+fn replayed_event(entry: &SessionEntry) -> Option<InputEvent> {
+    let event = match entry {
+        SessionEntry::Text {
+            text,
+            layout,
+            origin,
+        } => InputEvent::Text {
+            text: text.clone(),
+            layout: *layout,
+            key: CharKey::Unknown,
+            origin: *origin,
+        },
+        SessionEntry::Backspace => InputEvent::Backspace,
+        SessionEntry::Delete => InputEvent::Delete,
+        SessionEntry::DeleteWordBackward => InputEvent::DeleteWordBackward,
+        SessionEntry::DeleteWordForward => InputEvent::DeleteWordForward,
+        SessionEntry::Navigate { to } => InputEvent::Navigate(*to),
+        SessionEntry::FocusChanged { window } => InputEvent::FocusChanged(window.map(|n| Focus {
+            window: WindowId(isize::try_from(n).unwrap_or(isize::MAX)),
+            control: None,
+        })),
+        SessionEntry::Untracked => InputEvent::Untracked,
+        SessionEntry::Hotkey { command } => InputEvent::Hotkey(*command),
+        SessionEntry::Tick => InputEvent::Tick,
+        SessionEntry::Start { .. } | SessionEntry::Decision(_) => return None,
+    };
+    Some(event)
+}
+
+/// Runs a parsed session through the steps of the Windows app and collects the decisions.
+#[must_use]
+pub fn replay(lines: &[(usize, SessionLine)]) -> Replay {
+    let started = Instant::now();
+    let now_ms = Arc::new(AtomicU64::new(0));
+    let clock_ms = Arc::clone(&now_ms);
+    let clock = move || started + Duration::from_millis(clock_ms.load(Ordering::Relaxed));
+    let mut journal = InputJournal::with_sources(DEFAULT_CAPACITY, clock, SingleWindow);
+    let mut state = TypingState::new(AutoconvertSettings::default());
+    let mut exceptions = AutoconvertExceptions::default();
+    let mut replay = Replay::default();
+
+    for (line, session_line) in lines {
+        now_ms.store(session_line.ms, Ordering::Relaxed);
+        let event = match &session_line.entry {
+            SessionEntry::Start(settings) => {
+                settings.apply(&mut state, &mut journal);
+                exceptions = settings.exceptions.clone();
+                continue;
+            }
+            SessionEntry::Decision(decision) => {
+                replay.recorded.push(decision.clone());
+                continue;
+            }
+            entry => match replayed_event(entry) {
+                Some(event) => event,
+                None => continue,
+            },
+        };
+        let effects = app_step(&mut state, &mut journal, &event);
+        if let Some(decision) = state.take_decision() {
+            replay.replayed.push(ReplayedDecision {
+                line: *line,
+                decision: (&decision).into(),
+            });
+        }
+        for effect in effects {
+            // The app saves a reverted word to its config and reloads the rules.
+            if let Effect::NeverConvert(word) = &effect
+                && exceptions.add_never_convert(word)
+            {
+                state.settings_mut().exceptions = compile_exceptions(&exceptions);
+            }
+        }
+    }
+    replay
+}
+
+/// What the Windows app does for a recorded `event`.
+///
+/// The keyboard hook applies a key press to the journal and, when it ends a word, the UI thread
+/// judges the word. A Backspace claimed by the hook reverts the last autoconvert instead, and a
+/// hotkey runs on the UI thread.
+fn app_step(
+    state: &mut TypingState,
+    journal: &mut InputJournal,
+    event: &InputEvent,
+) -> Vec<Effect> {
+    match event {
+        InputEvent::Backspace if journal.request_autoconvert_revert() => {
+            state.revert_autoconvert(journal)
+        }
+        InputEvent::Hotkey(command) => state.run_hotkey(journal, *command),
+        event => {
+            let _ = journal.expire_if_idle();
+            journal.apply(event);
+            let typed = matches!(
+                event,
+                InputEvent::Text {
+                    origin: RunOrigin::Physical,
+                    ..
+                }
+            );
+            if typed && journal.last_char_triggers_autoconvert() {
+                state.word_ended(journal)
+            } else {
+                Vec::new()
+            }
+        }
+    }
+}
@@ -1,3 +1,7 @@
+pub(crate) mod hotkey_sequence;
 #[cfg(windows)]
 pub mod hotkeys;
+pub(crate) mod input_event;
+pub(crate) mod key_code;
 pub(crate) mod ring_buffer;
+pub(crate) mod sequence_matcher;
@@ -0,0 +1,428 @@
+//! Hotkey sequences as stored in the config, and their text syntax.
+//!
+//! A sequence is written as its steps separated by commas, for example `LShift, LShift`,
+//! `Ctrl+K, Ctrl+C` or `CapsLock (hold 400 ms)`:
+//!
+//! - A step is a chord: modifiers and at most one key joined by `+`, such as `Ctrl+Alt+K`,
+//!   `LShift+RShift` or `CapsLock`. Generic modifiers (`Ctrl`, `Alt`, `Shift`, `Win`) match
+//!   either side; side-specific ones (`LCtrl`, `RShift`, ...) cannot be mixed with the generic
+//!   name of the same modifier. Next to side-specific modifiers a generic one has no side of its
+//!   own, like the Ctrl that Windows reports for AltGr in `Ctrl+RAlt+Q`.
+//! - `None` is the chord of no keys, which older configs may hold.
+//! - A step may be followed by `(tap)` or `(hold N ms)`, and by `(within N ms)` to limit the
+//!   pause since the previous step.
+//! - `; gap N ms` at the end sets the pause allowed between the other steps, 1000 ms if omitted.
+//!
+//! Names are case-insensitive; keys are named as in [`KeyCode`], and a modifier name always means
+//! the modifier. Formatting gives the canonical spelling, which parses back to the same sequence.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::key_code::{self, KeyCode};
+
+/// Generic modifier bits of [`HotkeyChord::mods`], the values `RegisterHotKey` uses.
+pub const MOD_ALT: u32 = 0x0001;
+pub const MOD_CONTROL: u32 = 0x0002;
+pub const MOD_SHIFT: u32 = 0x0004;
+pub const MOD_WIN: u32 = 0x0008;
+
+pub const MODVK_LCTRL: u32 = 1 << 0;
+pub const MODVK_RCTRL: u32 = 1 << 1;
+pub const MODVK_LSHIFT: u32 = 1 << 2;
+pub const MODVK_RSHIFT: u32 = 1 << 3;
+pub const MODVK_LALT: u32 = 1 << 4;
+pub const MODVK_RALT: u32 = 1 << 5;
+pub const MODVK_LWIN: u32 = 1 << 6;
+pub const MODVK_RWIN: u32 = 1 << 7;
+
+/// Pause allowed between steps when neither the sequence nor the step sets one.
+pub const DEFAULT_MAX_GAP_MS: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HotkeyChord {
+    pub mods: u32,
+
+    #[serde(default)]
+    pub mods_vks: u32,
+
+    /// Older configs call it `vk` and store a Win32 virtual-key number.
+    #[serde(
+        default,
+        alias = "vk",
+        deserialize_with = "key_code::deserialize_optional"
+    )]
+    pub key: Option<KeyCode>,
+}
+
+/// How a key must be pressed for a step to match.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyPress {
+    /// As soon as the chord goes down.
+    #[default]
+    Down,
+    /// Released before it counts as held: before the shortest hold bound to the same chord, or
+    /// within [`DEFAULT_TAP_MAX_MS`] when there is none.
+    Tap,
+    /// Held down for at least this many milliseconds.
+    Hold(u32),
+}
+
+impl HotkeyPress {
+    #[must_use]
+    pub const fn is_down(&self) -> bool {
+        matches!(self, Self::Down)
+    }
+}
+
+/// Longest press that counts as a tap of a key with no hold binding.
+pub const DEFAULT_TAP_MAX_MS: u32 = 500;
+
+/// One chord of a [`HotkeySequence`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HotkeyStep {
+    #[serde(flatten)]
+    pub chord: HotkeyChord,
+
+    /// Tap and hold apply to chords with a key; modifier-only chords match on release.
+    #[serde(default, skip_serializing_if = "HotkeyPress::is_down")]
+    pub press: HotkeyPress,
+
+    /// Longest pause allowed since the previous step, overriding the sequence limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gap_ms: Option<u32>,
+}
+
+/// Chords pressed one after another, such as RCtrl three times or Ctrl+K then Ctrl+C.
+///
+/// Saved as an object with `steps`. The text syntax of this module and the form of older configs,
+/// with `first` and an optional `second` chord, are read as well.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "HotkeySequenceRepr")]
+pub struct HotkeySequence {
+    /// Never empty.
+    pub steps: Vec<HotkeyStep>,
+    /// Longest pause allowed between steps that set no limit of their own.
+    pub max_gap_ms: u32,
+}
+
+impl HotkeySequence {
+    /// Sequence of `chords` with the same gap limit between all of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chords` is empty.
+    #[must_use]
+    pub fn of(chords: impl IntoIterator<Item = HotkeyChord>, max_gap_ms: u32) -> Self {
+        let steps: Vec<_> = chords
+            .into_iter()
+            .map(|chord| HotkeyStep {
+                chord,
+                press: HotkeyPress::Down,
+                max_gap_ms: None,
+            })
+            .collect();
+        assert!(!steps.is_empty(), "a hotkey sequence needs a chord");
+        Self { steps, max_gap_ms }
+    }
+
+    /// Whether the sequence is a single chord.
+    #[must_use]
+    pub fn is_single(&self) -> bool {
+        self.steps.len() == 1
+    }
+
+    /// Longest pause allowed before step `index`.
+    #[must_use]
+    pub fn gap_before_ms(&self, index: usize) -> u32 {
+        self.steps
+            .get(index)
+            .and_then(|step| step.max_gap_ms)
+            .unwrap_or(self.max_gap_ms)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HotkeySequenceRepr {
+    Text(String),
+    Steps {
+        steps: Vec<HotkeyStep>,
+        max_gap_ms: u32,
+    },
+    Legacy {
+        first: HotkeyChord,
+        second: Option<HotkeyChord>,
+        max_gap_ms: u32,
+    },
+}
+
+impl TryFrom<HotkeySequenceRepr> for HotkeySequence {
+    type Error = String;
+
+    fn try_from(repr: HotkeySequenceRepr) -> Result<Self, Self::Error> {
+        match repr {
+            HotkeySequenceRepr::Text(text) => text.parse().map_err(|e| format!("{text:?}: {e}")),
+            HotkeySequenceRepr::Steps { steps, max_gap_ms } => {
+                if steps.is_empty() {
+                    return Err("hotkey sequence has no steps".to_string());
+                }
+                Ok(Self { steps, max_gap_ms })
+            }
+            HotkeySequenceRepr::Legacy {
+                first,
+                second,
+                max_gap_ms,
+            } => Ok(Self::of(std::iter::once(first).chain(second), max_gap_ms)),
+        }
+    }
+}
+
+/// Side-specific modifiers in display order, with their generic bit.
+const SIDE_MODIFIERS: &[(u32, u32, KeyCode)] = &[
+    (MODVK_LCTRL, MOD_CONTROL, KeyCode::LCtrl),
+    (MODVK_RCTRL, MOD_CONTROL, KeyCode::RCtrl),
+    (MODVK_LALT, MOD_ALT, KeyCode::LAlt),
+    (MODVK_RALT, MOD_ALT, KeyCode::RAlt),
+    (MODVK_LSHIFT, MOD_SHIFT, KeyCode::LShift),
+    (MODVK_RSHIFT, MOD_SHIFT, KeyCode::RShift),
+    (MODVK_LWIN, MOD_WIN, KeyCode::LWin),
+    (MODVK_RWIN, MOD_WIN, KeyCode::RWin),
+];
+
+const GENERIC_MODIFIERS: &[(u32, &str)] = &[
+    (MOD_CONTROL, "Ctrl"),
+    (MOD_ALT, "Alt"),
+    (MOD_SHIFT, "Shift"),
+    (MOD_WIN, "Win"),
+];
+
+/// Spellings of generic modifiers accepted besides the canonical names.
+const MODIFIER_ALIASES: &[(&str, &str)] = &[("control", "Ctrl"), ("super", "Win"), ("meta", "Win")];
+
+/// Side-specific modifier keys of `mods_vks`, in display order.
+pub fn side_modifier_keys(mods_vks: u32) -> impl Iterator<Item = KeyCode> {
+    SIDE_MODIFIERS
+        .iter()
+        .filter(move |(mask, _, _)| mods_vks & mask != 0)
+        .map(|(_, _, modifier)| *modifier)
+}
+
+/// Generic bits of the side-specific modifiers in `mods_vks`.
+fn side_generic_bits(mods_vks: u32) -> u32 {
+    SIDE_MODIFIERS
+        .iter()
+        .filter(|(mask, _, _)| mods_vks & mask != 0)
+        .fold(0, |bits, (_, generic, _)| bits | generic)
+}
+
+/// Generic and side-specific bits of a modifier key, `None` for other keys.
+#[must_use]
+pub fn modifier_bits(key: KeyCode) -> Option<(u32, u32)> {
+    SIDE_MODIFIERS
+        .iter()
+        .find(|(_, _, modifier)| *modifier == key)
+        .map(|(side, generic, _)| (*generic, *side))
+}
+
+/// Why a hotkey text does not parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotkeySyntaxError {
+    Empty,
+    UnknownKey(String),
+    /// A key other than a modifier that is not last in its chord.
+    KeyNotLast(String),
+    /// A generic modifier and a side-specific one of the same kind in one chord.
+    MixedModifiers,
+    BadOption(String),
+}
+
+impl fmt::Display for HotkeySyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty hotkey step"),
+            Self::UnknownKey(name) => write!(f, "unknown key {name:?}"),
+            Self::KeyNotLast(name) => write!(f, "{name:?} must be the last key of its chord"),
+            Self::MixedModifiers => {
+                write!(f, "generic and left/right names of one modifier are mixed")
+            }
+            Self::BadOption(text) => write!(f, "unknown option {text:?}"),
+        }
+    }
+}
+
+impl std::error::Error for HotkeySyntaxError {}
+
+impl fmt::Display for HotkeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sided = side_generic_bits(self.mods_vks);
+        let mut parts: Vec<String> = GENERIC_MODIFIERS
+            .iter()
+            .filter(|(mask, _)| self.mods & mask != 0 && sided & mask == 0)
+            .map(|(_, label)| (*label).to_string())
+            .collect();
+        parts.extend(side_modifier_keys(self.mods_vks).map(|modifier| modifier.to_string()));
+        parts.extend(self.key.map(|key| key.to_string()));
+
+        if parts.is_empty() {
+            f.write_str("None")
+        } else {
+            f.write_str(&parts.join("+"))
+        }
+    }
+}
+
+impl FromStr for HotkeyChord {
+    type Err = HotkeySyntaxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chord = Self {
+            mods: 0,
+            mods_vks: 0,
+            key: None,
+        };
+        if s.trim().eq_ignore_ascii_case("none") {
+            return Ok(chord);
+        }
+
+        let names: Vec<&str> = s.split('+').map(str::trim).collect();
+        if names.iter().any(|name| name.is_empty()) {
+            return Err(HotkeySyntaxError::Empty);
+        }
+
+        let mut generic = 0;
+
+        for (i, name) in names.iter().enumerate() {
+            let generic_name = MODIFIER_ALIASES
+                .iter()
+                .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+                .map_or(*name, |(_, canonical)| *canonical);
+            if let Some((bit, _)) = GENERIC_MODIFIERS
+                .iter()
+                .find(|(_, label)| label.eq_ignore_ascii_case(generic_name))
+            {
+                generic |= bit;
+                continue;
+            }
+
+            let key: KeyCode = name
+                .parse()
+                .map_err(|_| HotkeySyntaxError::UnknownKey((*name).to_string()))?;
+            if let Some((bit, side)) = modifier_bits(key) {
+                chord.mods |= bit;
+                chord.mods_vks |= side;
+            } else if i + 1 == names.len() {
+                chord.key = Some(key);
+            } else {
+                return Err(HotkeySyntaxError::KeyNotLast((*name).to_string()));
+            }
+        }
+
+        if generic & side_generic_bits(chord.mods_vks) != 0 {
+            return Err(HotkeySyntaxError::MixedModifiers);
+        }
+        chord.mods |= generic;
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for HotkeyStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.chord)?;
+        match self.press {
+            HotkeyPress::Down => {}
+            HotkeyPress::Tap => f.write_str(" (tap)")?,
+            HotkeyPress::Hold(ms) => write!(f, " (hold {ms} ms)")?,
+        }
+        if let Some(ms) = self.max_gap_ms {
+            write!(f, " (within {ms} ms)")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for HotkeyStep {
+    type Err = HotkeySyntaxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (chord, mut options) = s.find('(').map_or((s, ""), |at| s.split_at(at));
+        let mut step = Self {
+            chord: chord.parse()?,
+            press: HotkeyPress::Down,
+            max_gap_ms: None,
+        };
+
+        loop {
+            options = options.trim_start();
+            if options.is_empty() {
+                return Ok(step);
+            }
+            let bad = || HotkeySyntaxError::BadOption(options.trim().to_string());
+            let (option, rest) = options
+                .strip_prefix('(')
+                .and_then(|inner| inner.split_once(')'))
+                .ok_or_else(bad)?;
+
+            let mut words = option.split_whitespace();
+            match words.next().map(str::to_ascii_lowercase).as_deref() {
+                Some("tap") if words.next().is_none() => step.press = HotkeyPress::Tap,
+                Some("hold") => step.press = HotkeyPress::Hold(parse_ms(words).ok_or_else(bad)?),
+                Some("within") => step.max_gap_ms = Some(parse_ms(words).ok_or_else(bad)?),
+                _ => return Err(bad()),
+            }
+            options = rest;
+        }
+    }
+}
+
+/// Reads `N ms` or `Nms` and nothing after it.
+fn parse_ms<'a>(words: impl Iterator<Item = &'a str>) -> Option<u32> {
+    let text: String = words.collect();
+    text.to_ascii_lowercase().strip_suffix("ms")?.parse().ok()
+}
+
+impl fmt::Display for HotkeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{step}")?;
+        }
+        if self.max_gap_ms != DEFAULT_MAX_GAP_MS {
+            write!(f, "; gap {} ms", self.max_gap_ms)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for HotkeySequence {
+    type Err = HotkeySyntaxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (steps, gap) = s.split_once(';').unwrap_or((s, ""));
+
+        let max_gap_ms = if gap.trim().is_empty() {
+            DEFAULT_MAX_GAP_MS
+        } else {
+            let mut words = gap.split_whitespace();
+            words
+                .next()
+                .filter(|word| word.eq_ignore_ascii_case("gap"))
+                .and_then(|_| parse_ms(words))
+                .ok_or_else(|| HotkeySyntaxError::BadOption(gap.trim().to_string()))?
+        };
+
+        if steps.trim().is_empty() {
+            return Err(HotkeySyntaxError::Empty);
+        }
+        let steps = steps
+            .split(',')
+            .map(|step| step.trim().parse())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { steps, max_gap_ms })
+    }
+}
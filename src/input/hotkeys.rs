@@ -14,6 +14,8 @@ pub enum HotkeyAction {
     PauseToggle,
     ConvertSelection,
     SwitchLayout,
+    UndoConversion,
+    ConvertLastPhrase,
 }
 
 // Диапазон 20000+ чтобы не пересекаться с control ids в WM_COMMAND
@@ -23,6 +25,8 @@ pub const HK_CONVERT_LAST_WORD_ID: i32 = HK_ID_BASE + 1;
 pub const HK_PAUSE_TOGGLE_ID: i32 = HK_ID_BASE + 2;
 pub const HK_CONVERT_SELECTION_ID: i32 = HK_ID_BASE + 3;
 pub const HK_SWITCH_LAYOUT_ID: i32 = HK_ID_BASE + 4;
+pub const HK_UNDO_CONVERSION_ID: i32 = HK_ID_BASE + 5;
+pub const HK_CONVERT_LAST_PHRASE_ID: i32 = HK_ID_BASE + 6;
 
 pub fn action_from_id(id: i32) -> Option<HotkeyAction> {
     match id {
@@ -30,6 +34,8 @@ pub fn action_from_id(id: i32) -> Option<HotkeyAction> {
         HK_PAUSE_TOGGLE_ID => Some(HotkeyAction::PauseToggle),
         HK_CONVERT_SELECTION_ID => Some(HotkeyAction::ConvertSelection),
         HK_SWITCH_LAYOUT_ID => Some(HotkeyAction::SwitchLayout),
+        HK_UNDO_CONVERSION_ID => Some(HotkeyAction::UndoConversion),
+        HK_CONVERT_LAST_PHRASE_ID => Some(HotkeyAction::ConvertLastPhrase),
         _ => None,
     }
 }
@@ -49,6 +55,8 @@ pub fn unregister_all(hwnd: HWND) -> windows::core::Result<()> {
         HK_PAUSE_TOGGLE_ID,
         HK_CONVERT_SELECTION_ID,
         HK_SWITCH_LAYOUT_ID,
+        HK_UNDO_CONVERSION_ID,
+        HK_CONVERT_LAST_PHRASE_ID,
     ] {
         unregister_one_quiet(hwnd, id)?;
     }
@@ -63,23 +71,24 @@ fn register_one(hwnd: HWND, id: i32, hk: Option<config::Hotkey>) -> windows::cor
         return Ok(());
     };
 
-    if hk.vk == 0 {
+    let Some(key) = hk.key else {
         #[cfg(debug_assertions)]
         crate::utils::helpers::debug_log(&format!(
-            "hotkey id={} ignored: invalid vk=0x0 mods=0x{:X}",
+            "hotkey id={} ignored: no key mods=0x{:X}",
             id, hk.mods
         ));
         return Ok(());
-    }
+    };
+    let vk = key.win32_vk();
 
     #[cfg(debug_assertions)]
     crate::utils::helpers::debug_log(&format!(
         "RegisterHotKey id={} mods=0x{:X} vk=0x{:X}",
-        id, hk.mods, hk.vk
+        id, hk.mods, vk
     ));
 
     unsafe {
-        RegisterHotKey(Some(hwnd), id, HOT_KEY_MODIFIERS(hk.mods), hk.vk)?;
+        RegisterHotKey(Some(hwnd), id, HOT_KEY_MODIFIERS(hk.mods), vk)?;
     }
 
     #[cfg(debug_assertions)]
@@ -95,6 +104,12 @@ pub fn register_from_config(hwnd: HWND, cfg: &config::Config) -> windows::core::
     register_one(hwnd, HK_PAUSE_TOGGLE_ID, cfg.hotkey_pause)?;
     register_one(hwnd, HK_CONVERT_SELECTION_ID, cfg.hotkey_convert_selection)?;
     register_one(hwnd, HK_SWITCH_LAYOUT_ID, cfg.hotkey_switch_layout)?;
+    register_one(hwnd, HK_UNDO_CONVERSION_ID, cfg.hotkey_undo_conversion)?;
+    register_one(
+        hwnd,
+        HK_CONVERT_LAST_PHRASE_ID,
+        cfg.hotkey_convert_last_phrase,
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,56 @@
+//! Events a backend observes and feeds to the input journal and the typing pipeline.
+//!
+//! They live below the text domain so that the journal can apply them without depending on it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::ring_buffer::{CharKey, Focus, LayoutTag, RunOrigin};
+
+/// Caret movement reported by a backend.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Navigation {
+    CaretLeft,
+    CaretRight,
+    /// The caret moved in a way the journal cannot follow: Home, a click, a selection.
+    Unknown,
+}
+
+/// Hotkey actions the pipeline performs itself.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyCommand {
+    /// Converts the text typed since the last layout change and switches the layout, like the
+    /// Convert hotkey does without a selection. A press right after another one acts on the
+    /// converted text as the configured repeat mode says.
+    ConvertLastWord,
+    /// Converts the wrong-layout words typed since the last sentence end.
+    ConvertLastPhrase,
+    /// Restores the text replaced by the most recent conversion.
+    UndoConversion,
+    ToggleAutoconvert,
+}
+
+/// Something a backend observed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InputEvent {
+    /// Text typed by one key press in `layout`, or inserted at once by a program.
+    Text {
+        text: String,
+        layout: LayoutTag,
+        key: CharKey,
+        origin: RunOrigin,
+    },
+    Backspace,
+    Delete,
+    DeleteWordBackward,
+    DeleteWordForward,
+    Navigate(Navigation),
+    /// Keyboard focus moved to another window or control; `None` when nothing has focus.
+    FocusChanged(Option<Focus>),
+    /// Text may have changed in a way the backend could not see, for example by mouse input.
+    Untracked,
+    Hotkey(HotkeyCommand),
+    /// Periodic tick, lets an idle journal expire.
+    Tick,
+}
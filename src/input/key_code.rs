@@ -0,0 +1,330 @@
+//! Platform-neutral key codes.
+//!
+//! Hotkeys name keys with [`KeyCode`]; each platform layer converts to and from its own codes:
+//! Win32 virtual keys, Linux evdev codes (`KEY_*` in `input-event-codes.h`) and X11 keysyms.
+//! Configs store keys by name. Older configs store Win32 virtual-key numbers, which are still read.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+struct KeyInfo {
+    key: KeyCode,
+    name: &'static str,
+    vk: u8,
+    #[cfg_attr(windows, allow(dead_code, reason = "read on Linux only"))]
+    evdev: u16,
+    #[cfg_attr(windows, allow(dead_code, reason = "read on Linux only"))]
+    keysym: u32,
+}
+
+macro_rules! key_codes {
+    ($($key:ident = $name:literal, $vk:literal, $evdev:literal, $keysym:literal;)*) => {
+        /// A physical key, named after its US layout legend.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum KeyCode {
+            $($key,)*
+            /// A Win32 virtual key without a name here, so that any numeric config still loads.
+            /// It has no evdev code or keysym.
+            Other(u8),
+        }
+
+        const KEYS: &[KeyInfo] = &[
+            $(KeyInfo { key: KeyCode::$key, name: $name, vk: $vk, evdev: $evdev, keysym: $keysym },)*
+        ];
+    };
+}
+
+key_codes! {
+    A = "A", 0x41, 30, 0x61;
+    B = "B", 0x42, 48, 0x62;
+    C = "C", 0x43, 46, 0x63;
+    D = "D", 0x44, 32, 0x64;
+    E = "E", 0x45, 18, 0x65;
+    F = "F", 0x46, 33, 0x66;
+    G = "G", 0x47, 34, 0x67;
+    H = "H", 0x48, 35, 0x68;
+    I = "I", 0x49, 23, 0x69;
+    J = "J", 0x4A, 36, 0x6A;
+    K = "K", 0x4B, 37, 0x6B;
+    L = "L", 0x4C, 38, 0x6C;
+    M = "M", 0x4D, 50, 0x6D;
+    N = "N", 0x4E, 49, 0x6E;
+    O = "O", 0x4F, 24, 0x6F;
+    P = "P", 0x50, 25, 0x70;
+    Q = "Q", 0x51, 16, 0x71;
+    R = "R", 0x52, 19, 0x72;
+    S = "S", 0x53, 31, 0x73;
+    T = "T", 0x54, 20, 0x74;
+    U = "U", 0x55, 22, 0x75;
+    V = "V", 0x56, 47, 0x76;
+    W = "W", 0x57, 17, 0x77;
+    X = "X", 0x58, 45, 0x78;
+    Y = "Y", 0x59, 21, 0x79;
+    Z = "Z", 0x5A, 44, 0x7A;
+    Digit0 = "0", 0x30, 11, 0x30;
+    Digit1 = "1", 0x31, 2, 0x31;
+    Digit2 = "2", 0x32, 3, 0x32;
+    Digit3 = "3", 0x33, 4, 0x33;
+    Digit4 = "4", 0x34, 5, 0x34;
+    Digit5 = "5", 0x35, 6, 0x35;
+    Digit6 = "6", 0x36, 7, 0x36;
+    Digit7 = "7", 0x37, 8, 0x37;
+    Digit8 = "8", 0x38, 9, 0x38;
+    Digit9 = "9", 0x39, 10, 0x39;
+    F1 = "F1", 0x70, 59, 0xFFBE;
+    F2 = "F2", 0x71, 60, 0xFFBF;
+    F3 = "F3", 0x72, 61, 0xFFC0;
+    F4 = "F4", 0x73, 62, 0xFFC1;
+    F5 = "F5", 0x74, 63, 0xFFC2;
+    F6 = "F6", 0x75, 64, 0xFFC3;
+    F7 = "F7", 0x76, 65, 0xFFC4;
+    F8 = "F8", 0x77, 66, 0xFFC5;
+    F9 = "F9", 0x78, 67, 0xFFC6;
+    F10 = "F10", 0x79, 68, 0xFFC7;
+    F11 = "F11", 0x7A, 87, 0xFFC8;
+    F12 = "F12", 0x7B, 88, 0xFFC9;
+    F13 = "F13", 0x7C, 183, 0xFFCA;
+    F14 = "F14", 0x7D, 184, 0xFFCB;
+    F15 = "F15", 0x7E, 185, 0xFFCC;
+    F16 = "F16", 0x7F, 186, 0xFFCD;
+    F17 = "F17", 0x80, 187, 0xFFCE;
+    F18 = "F18", 0x81, 188, 0xFFCF;
+    F19 = "F19", 0x82, 189, 0xFFD0;
+    F20 = "F20", 0x83, 190, 0xFFD1;
+    F21 = "F21", 0x84, 191, 0xFFD2;
+    F22 = "F22", 0x85, 192, 0xFFD3;
+    F23 = "F23", 0x86, 193, 0xFFD4;
+    F24 = "F24", 0x87, 194, 0xFFD5;
+    Backspace = "Backspace", 0x08, 14, 0xFF08;
+    Tab = "Tab", 0x09, 15, 0xFF09;
+    Enter = "Enter", 0x0D, 28, 0xFF0D;
+    Pause = "Pause", 0x13, 119, 0xFF13;
+    CapsLock = "CapsLock", 0x14, 58, 0xFFE5;
+    Esc = "Esc", 0x1B, 1, 0xFF1B;
+    Space = "Space", 0x20, 57, 0x20;
+    PageUp = "PageUp", 0x21, 104, 0xFF55;
+    PageDown = "PageDown", 0x22, 109, 0xFF56;
+    End = "End", 0x23, 107, 0xFF57;
+    Home = "Home", 0x24, 102, 0xFF50;
+    Left = "Left", 0x25, 105, 0xFF51;
+    Up = "Up", 0x26, 103, 0xFF52;
+    Right = "Right", 0x27, 106, 0xFF53;
+    Down = "Down", 0x28, 108, 0xFF54;
+    PrintScreen = "PrintScreen", 0x2C, 99, 0xFF61;
+    Insert = "Insert", 0x2D, 110, 0xFF63;
+    Delete = "Delete", 0x2E, 111, 0xFFFF;
+    Menu = "Menu", 0x5D, 127, 0xFF67;
+    Num0 = "Num0", 0x60, 82, 0xFFB0;
+    Num1 = "Num1", 0x61, 79, 0xFFB1;
+    Num2 = "Num2", 0x62, 80, 0xFFB2;
+    Num3 = "Num3", 0x63, 81, 0xFFB3;
+    Num4 = "Num4", 0x64, 75, 0xFFB4;
+    Num5 = "Num5", 0x65, 76, 0xFFB5;
+    Num6 = "Num6", 0x66, 77, 0xFFB6;
+    Num7 = "Num7", 0x67, 71, 0xFFB7;
+    Num8 = "Num8", 0x68, 72, 0xFFB8;
+    Num9 = "Num9", 0x69, 73, 0xFFB9;
+    NumMultiply = "NumMultiply", 0x6A, 55, 0xFFAA;
+    NumAdd = "NumAdd", 0x6B, 78, 0xFFAB;
+    NumSubtract = "NumSubtract", 0x6D, 74, 0xFFAD;
+    NumDecimal = "NumDecimal", 0x6E, 83, 0xFFAE;
+    NumDivide = "NumDivide", 0x6F, 98, 0xFFAF;
+    NumLock = "NumLock", 0x90, 69, 0xFF7F;
+    ScrollLock = "ScrollLock", 0x91, 70, 0xFF14;
+    LShift = "LShift", 0xA0, 42, 0xFFE1;
+    RShift = "RShift", 0xA1, 54, 0xFFE2;
+    LCtrl = "LCtrl", 0xA2, 29, 0xFFE3;
+    RCtrl = "RCtrl", 0xA3, 97, 0xFFE4;
+    LAlt = "LAlt", 0xA4, 56, 0xFFE9;
+    RAlt = "RAlt", 0xA5, 100, 0xFFEA;
+    LWin = "LWin", 0x5B, 125, 0xFFEB;
+    RWin = "RWin", 0x5C, 126, 0xFFEC;
+    Semicolon = "Semicolon", 0xBA, 39, 0x3B;
+    Equals = "Equals", 0xBB, 13, 0x3D;
+    Comma = "Comma", 0xBC, 51, 0x2C;
+    Minus = "Minus", 0xBD, 12, 0x2D;
+    Period = "Period", 0xBE, 52, 0x2E;
+    Slash = "Slash", 0xBF, 53, 0x2F;
+    Backquote = "Backquote", 0xC0, 41, 0x60;
+    LBracket = "LBracket", 0xDB, 26, 0x5B;
+    Backslash = "Backslash", 0xDC, 43, 0x5C;
+    RBracket = "RBracket", 0xDD, 27, 0x5D;
+    Quote = "Quote", 0xDE, 40, 0x27;
+    IntlBackslash = "IntlBackslash", 0xE2, 86, 0x3C;
+}
+
+/// Spellings accepted besides the canonical names.
+const ALIASES: &[(&str, KeyCode)] = &[
+    ("caps", KeyCode::CapsLock),
+    ("escape", KeyCode::Esc),
+    ("return", KeyCode::Enter),
+    ("del", KeyCode::Delete),
+    ("ins", KeyCode::Insert),
+    ("pgup", KeyCode::PageUp),
+    ("pgdn", KeyCode::PageDown),
+    ("apps", KeyCode::Menu),
+    ("grave", KeyCode::Backquote),
+    ("lcontrol", KeyCode::LCtrl),
+    ("rcontrol", KeyCode::RCtrl),
+];
+
+impl KeyCode {
+    fn info(self) -> Option<&'static KeyInfo> {
+        KEYS.iter().find(|info| info.key == self)
+    }
+
+    /// Key of a Win32 virtual-key code; `None` for 0 and codes above 0xFE.
+    #[must_use]
+    pub fn from_win32_vk(vk: u32) -> Option<Self> {
+        let vk = u8::try_from(vk).ok().filter(|vk| (1..=0xFE).contains(vk))?;
+        Some(
+            KEYS.iter()
+                .find(|info| info.vk == vk)
+                .map_or(Self::Other(vk), |info| info.key),
+        )
+    }
+
+    #[must_use]
+    pub fn win32_vk(self) -> u32 {
+        match self {
+            Self::Other(vk) => u32::from(vk),
+            key => key.info().map_or(0, |info| u32::from(info.vk)),
+        }
+    }
+}
+
+/// Linux key codes, for hooks built on the lib crate outside Windows.
+#[cfg_attr(
+    windows,
+    allow(dead_code, reason = "the Windows app reads virtual keys only")
+)]
+impl KeyCode {
+    /// Key of a Linux evdev code, such as 30 for `KEY_A`.
+    #[must_use]
+    pub fn from_evdev(code: u16) -> Option<Self> {
+        KEYS.iter()
+            .find(|info| info.evdev == code)
+            .map(|info| info.key)
+    }
+
+    #[must_use]
+    pub fn evdev(self) -> Option<u16> {
+        self.info().map(|info| info.evdev)
+    }
+
+    /// Key of an X11 keysym. Letters are found by either case.
+    #[must_use]
+    pub fn from_x11_keysym(keysym: u32) -> Option<Self> {
+        let keysym = if (0x41..=0x5A).contains(&keysym) {
+            keysym + 0x20
+        } else {
+            keysym
+        };
+        KEYS.iter()
+            .find(|info| info.keysym == keysym)
+            .map(|info| info.key)
+    }
+
+    /// Keysym of the key without modifiers, lowercase for letters.
+    #[must_use]
+    pub fn x11_keysym(self) -> Option<u32> {
+        self.info().map(|info| info.keysym)
+    }
+}
+
+impl fmt::Display for KeyCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.info() {
+            Some(info) => f.write_str(info.name),
+            None => write!(f, "0x{:02X}", self.win32_vk()),
+        }
+    }
+}
+
+/// A key name that is neither known nor a virtual-key code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey(pub String);
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownKey {}
+
+impl FromStr for KeyCode {
+    type Err = UnknownKey;
+
+    /// Reads a name in any case, or a Win32 virtual-key code written as `0x..`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KEYS.iter()
+            .find(|info| info.name.eq_ignore_ascii_case(s))
+            .map(|info| info.key)
+            .or_else(|| {
+                ALIASES
+                    .iter()
+                    .find(|(alias, _)| alias.eq_ignore_ascii_case(s))
+                    .map(|(_, key)| *key)
+            })
+            .or_else(|| {
+                s.strip_prefix("0x")
+                    .or_else(|| s.strip_prefix("0X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(Self::from_win32_vk)
+            })
+            .ok_or_else(|| UnknownKey(s.to_string()))
+    }
+}
+
+impl Serialize for KeyCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A key as configs store it: a name, or the Win32 virtual-key number of older configs.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyRepr {
+    Name(String),
+    Vk(u32),
+}
+
+impl KeyRepr {
+    fn into_key(self) -> Result<Option<KeyCode>, String> {
+        match self {
+            Self::Name(name) => name
+                .parse()
+                .map(Some)
+                .map_err(|e: UnknownKey| e.to_string()),
+            Self::Vk(0) => Ok(None),
+            Self::Vk(vk) => KeyCode::from_win32_vk(vk)
+                .map(Some)
+                .ok_or_else(|| format!("virtual-key code {vk} is out of range")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        KeyRepr::deserialize(deserializer)?
+            .into_key()
+            .map_err(serde::de::Error::custom)?
+            .ok_or_else(|| serde::de::Error::custom("0 is not a key"))
+    }
+}
+
+/// Reads an optional key; `null` and the virtual-key number 0 of older configs mean none.
+///
+/// # Errors
+///
+/// Fails on an unknown name or an out-of-range number.
+pub fn deserialize_optional<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<KeyCode>, D::Error> {
+    Option::<KeyRepr>::deserialize(deserializer)?
+        .map_or(Ok(None), KeyRepr::into_key)
+        .map_err(serde::de::Error::custom)
+}
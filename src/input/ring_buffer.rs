@@ -1,27 +1,67 @@
 use std::{
+    borrow::Cow,
     collections::VecDeque,
-    sync::{Mutex, OnceLock},
+    fmt,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
 };
+#[cfg(windows)]
+use std::{collections::HashMap, sync::mpsc, thread};
 
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
 #[cfg(windows)]
 use windows::Win32::UI::{
     Input::KeyboardAndMouse::{
         GetAsyncKeyState, GetKeyboardLayout, GetKeyboardState, HKL, ToUnicodeEx, VIRTUAL_KEY,
-        VK_BACK, VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE, VK_HOME, VK_INSERT, VK_LEFT, VK_LSHIFT,
-        VK_NEXT, VK_PRIOR, VK_RETURN, VK_RIGHT, VK_RSHIFT, VK_SHIFT, VK_TAB, VK_UP,
+        VK_BACK, VK_CAPITAL, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE, VK_HOME, VK_INSERT,
+        VK_LCONTROL, VK_LEFT, VK_LMENU, VK_LSHIFT, VK_MENU, VK_NEXT, VK_PRIOR, VK_RCONTROL,
+        VK_RETURN, VK_RIGHT, VK_RMENU, VK_RSHIFT, VK_SHIFT, VK_TAB, VK_UP,
     },
     WindowsAndMessaging::{
-        GetForegroundWindow, GetWindowThreadProcessId, KBDLLHOOKSTRUCT, LLKHF_INJECTED,
+        ES_PASSWORD, GUITHREADINFO, GWL_STYLE, GetClassNameW, GetForegroundWindow,
+        GetGUIThreadInfo, GetWindowLongW, GetWindowThreadProcessId, KBDLLHOOKSTRUCT,
+        LLKHF_INJECTED,
     },
 };
+#[cfg(windows)]
+use windows::{
+    Win32::{
+        Foundation::{CloseHandle, HWND},
+        System::Threading::{
+            OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+            QueryFullProcessImageNameW,
+        },
+    },
+    core::PWSTR,
+};
+use zeroize::Zeroize;
+
+use crate::input::input_event::{InputEvent, Navigation};
+
+/// Capacity of the process-wide journal, in chars.
+pub const DEFAULT_CAPACITY: usize = 100;
 
 static JOURNAL: OnceLock<Mutex<InputJournal>> = OnceLock::new();
 
 fn journal() -> &'static Mutex<InputJournal> {
-    JOURNAL.get_or_init(|| Mutex::new(InputJournal::new(100)))
+    JOURNAL.get_or_init(|| {
+        #[cfg(windows)]
+        let journal = InputJournal::with_sources(DEFAULT_CAPACITY, SystemClock, ForegroundWindow);
+        #[cfg(not(windows))]
+        let journal = InputJournal::new(DEFAULT_CAPACITY);
+        Mutex::new(journal)
+    })
 }
 
-fn with_journal_mut<R>(f: impl FnOnce(&mut InputJournal) -> R) -> R {
+/// Number of times the shared journal was handed out for changes, see [`with_journal_detached`].
+static JOURNAL_REVISION: AtomicU64 = AtomicU64::new(0);
+
+/// Runs `f` on the journal shared by the keyboard hook and the UI thread.
+pub fn with_journal_mut<R>(f: impl FnOnce(&mut InputJournal) -> R) -> R {
     let mut guard = match journal().lock() {
         Ok(g) => g,
         Err(poison) => {
@@ -30,9 +70,31 @@ fn with_journal_mut<R>(f: impl FnOnce(&mut InputJournal) -> R) -> R {
             poison.into_inner()
         }
     };
+    let _ = JOURNAL_REVISION.fetch_add(1, Ordering::Relaxed);
     f(&mut guard)
 }
 
+/// Runs `step` on a copy of the shared journal, without holding its lock, and keeps the text the
+/// step left if nothing changed the journal meanwhile.
+///
+/// A slow step, such as language detection, must not hold the lock the keyboard hook waits on.
+/// Returns `Err` with the result of the step, and the journal as the hook left it, when the step
+/// raced with other input.
+#[cfg(windows)]
+pub fn with_journal_detached<R>(step: impl FnOnce(&mut InputJournal) -> R) -> Result<R, R> {
+    let (mut detached, revision) =
+        with_journal_mut(|j| (j.detach(), JOURNAL_REVISION.load(Ordering::Relaxed)));
+    let result = step(&mut detached);
+    with_journal_mut(|j| {
+        // Reattaching itself is the one change expected since the copy was made.
+        if JOURNAL_REVISION.load(Ordering::Relaxed) != revision + 1 {
+            return Err(result);
+        }
+        j.reattach(detached);
+        Ok(result)
+    })
+}
+
 #[cfg(any(test, windows))]
 fn with_journal<R>(f: impl FnOnce(&InputJournal) -> R) -> R {
     let guard = match journal().lock() {
@@ -51,7 +113,8 @@ const LANG_ENGLISH_PRIMARY: u16 = 0x09;
 #[cfg(windows)]
 const LANG_RUSSIAN_PRIMARY: u16 = 0x19;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum LayoutTag {
     Ru,
     En,
@@ -59,202 +122,1538 @@ pub enum LayoutTag {
     Unknown,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RunOrigin {
     Physical,
     Programmatic,
 }
 
+/// Token kind of a run, following UAX #29 word boundaries.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum RunKind {
-    Text,
+    /// Contains letters, for example `word`, `it's` or `e.g`.
+    Word,
+    Digit,
+    Punctuation,
+    Symbol,
     Whitespace,
 }
 
+impl RunKind {
+    /// Everything but whitespace.
+    #[must_use]
+    pub const fn is_text(self) -> bool {
+        !matches!(self, Self::Whitespace)
+    }
+
+    /// Kind of a token, or of several adjacent tokens taken as one word.
+    #[must_use]
+    pub fn of(token: &str) -> Self {
+        if token.chars().all(char::is_whitespace) {
+            Self::Whitespace
+        } else if token.chars().any(char::is_alphabetic) {
+            Self::Word
+        } else if token.chars().any(char::is_numeric) {
+            Self::Digit
+        } else if token.chars().all(is_punctuation) {
+            Self::Punctuation
+        } else {
+            Self::Symbol
+        }
+    }
+}
+
+fn is_punctuation(ch: char) -> bool {
+    matches!(
+        ch,
+        '!' | '"'
+            | '#'
+            | '%'
+            | '&'
+            | '\''
+            | '('
+            | ')'
+            | '*'
+            | ','
+            | '-'
+            | '.'
+            | '/'
+            | ':'
+            | ';'
+            | '?'
+            | '@'
+            | '['
+            | '\\'
+            | ']'
+            | '_'
+            | '{'
+            | '}'
+            | '«'
+            | '»'
+            | '…'
+            | '–'
+            | '—'
+            | '‘'
+            | '’'
+            | '“'
+            | '”'
+            | '„'
+    )
+}
+
+/// Chars in a run held by the journal, which records one [`TypedChar`] per char.
+fn stored_len(run: &InputRun) -> usize {
+    debug_assert_eq!(run.typed.len(), run.text.chars().count());
+    run.typed.len()
+}
+
+fn single_char_run(
+    ch: char,
+    typed: Option<TypedChar>,
+    layout: LayoutTag,
+    origin: RunOrigin,
+) -> InputRun {
+    let text = ch.to_string();
+    InputRun {
+        kind: RunKind::of(&text),
+        text,
+        layout,
+        origin,
+        typed: typed.into_iter().collect(),
+    }
+}
+
+/// Classes of chars that word-wise editing keys treat as one word.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum WordClass {
+    Space,
+    Alphanumeric,
+    Punctuation,
+}
+
+impl WordClass {
+    const fn of(kind: RunKind) -> Self {
+        match kind {
+            RunKind::Whitespace => Self::Space,
+            RunKind::Word | RunKind::Digit => Self::Alphanumeric,
+            RunKind::Punctuation | RunKind::Symbol => Self::Punctuation,
+        }
+    }
+}
+
+/// Longest pause between two words of one phrase.
+pub const PHRASE_PAUSE: Duration = Duration::from_secs(3);
+
+fn is_sentence_end(ch: char) -> bool {
+    matches!(ch, '.' | '!' | '?' | '…')
+}
+
+/// Returns the char offset where the last phrase of `text` starts.
+///
+/// The offset points at the first non-whitespace char of the phrase, or at the end of `text`
+/// when there is no such char.
+#[must_use]
+pub fn phrase_start(text: &str) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut boundary = 0usize;
+
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch == '\n' || ch == '\r' {
+            boundary = i + 1;
+            continue;
+        }
+        if !is_sentence_end(ch) {
+            continue;
+        }
+        let gap = chars[i + 1..]
+            .iter()
+            .take_while(|c| c.is_whitespace())
+            .count();
+        if gap > 0
+            && chars
+                .get(i + 1 + gap)
+                .is_some_and(|next| next.is_uppercase())
+        {
+            boundary = i + 1 + gap;
+        }
+    }
+
+    boundary
+        + chars[boundary..]
+            .iter()
+            .take_while(|c| c.is_whitespace())
+            .count()
+}
+
+/// Punctuation that makes autoconvert judge the word before it.
+pub const DEFAULT_AUTOCONVERT_TRIGGER_CHARS: &str = ".,!?;:";
+
+/// Physical key press that produced a char.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct KeyStroke {
+    pub vk: u16,
+    pub scan_code: u16,
+    pub shift: bool,
+    /// Right Alt, or Ctrl+Alt, was held.
+    pub altgr: bool,
+    pub caps_lock: bool,
+}
+
+impl Zeroize for KeyStroke {
+    fn zeroize(&mut self) {
+        self.vk.zeroize();
+        self.scan_code.zeroize();
+        self.shift.zeroize();
+        self.altgr.zeroize();
+        self.caps_lock.zeroize();
+    }
+}
+
+/// How a char of the journal came to be.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CharKey {
+    /// Inserted by a conversion or pushed without key information.
+    Unknown,
+    /// Produced by `stroke`, composed with `dead_key` when one was pressed before it.
+    Pressed {
+        stroke: KeyStroke,
+        dead_key: Option<KeyStroke>,
+    },
+    /// Produced by the same press as the char before it, for keys that type several chars.
+    SamePress,
+}
+
+/// Key and time behind one char of an [`InputRun`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TypedChar {
+    pub at: Instant,
+    pub key: CharKey,
+}
+
+impl TypedChar {
+    /// Char with no known key.
+    #[must_use]
+    pub const fn inserted(at: Instant) -> Self {
+        Self {
+            at,
+            key: CharKey::Unknown,
+        }
+    }
+}
+
+/// Decodes key presses through a keyboard layout.
+pub trait KeyDecoder {
+    /// Text typed by `stroke` after `dead_key`, or `None` when the layout types nothing for it.
+    fn decode(&self, stroke: KeyStroke, dead_key: Option<KeyStroke>) -> Option<String>;
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct InputRun {
+    /// Decoded text of the run, as it appeared on screen.
+    ///
+    /// `typed` is what was pressed; the text is kept alongside it so that runs without key
+    /// information still read the same.
     pub text: String,
     pub layout: LayoutTag,
     pub origin: RunOrigin,
     pub kind: RunKind,
+    /// Key and time of each char of `text`, in order.
+    ///
+    /// Runs built outside the journal may leave it empty: the journal records their chars as
+    /// inserted at the time they are pushed.
+    pub typed: Vec<TypedChar>,
+}
+
+/// Typed text may be a secret, so it is wiped from memory rather than left to the allocator.
+/// The key presses give it away as easily, so they are wiped too.
+impl Drop for InputRun {
+    fn drop(&mut self) {
+        self.text.zeroize();
+        wipe_typed(&mut self.typed);
+    }
+}
+
+/// Appends `s` to `dst`, wiping the old buffer when it has to grow.
+fn push_str_wiping(dst: &mut String, s: &str) {
+    reserve_wiping(dst, s.len());
+    dst.push_str(s);
+}
+
+/// Inserts `s` at the start of `dst`, wiping the old buffer when it has to grow.
+fn prepend_str_wiping(dst: &mut String, s: &str) {
+    reserve_wiping(dst, s.len());
+    dst.insert_str(0, s);
+}
+
+fn reserve_wiping(dst: &mut String, additional: usize) {
+    if dst.capacity() - dst.len() >= additional {
+        return;
+    }
+    let mut grown = String::with_capacity((dst.len() + additional).max(dst.capacity() * 2));
+    grown.push_str(dst);
+    let mut old = std::mem::replace(dst, grown);
+    old.zeroize();
+}
+
+/// Appends `src` to `dst`, wiping the old buffer when it has to grow.
+fn extend_typed_wiping(dst: &mut Vec<TypedChar>, src: &[TypedChar]) {
+    reserve_typed_wiping(dst, src.len());
+    dst.extend_from_slice(src);
+}
+
+/// Inserts `src` at the start of `dst`, wiping the old buffer when it has to grow.
+fn prepend_typed_wiping(dst: &mut Vec<TypedChar>, src: &[TypedChar]) {
+    reserve_typed_wiping(dst, src.len());
+    let _ = dst.splice(0..0, src.iter().copied());
+}
+
+fn reserve_typed_wiping(dst: &mut Vec<TypedChar>, additional: usize) {
+    if dst.capacity() - dst.len() >= additional {
+        return;
+    }
+    let mut grown = Vec::with_capacity((dst.len() + additional).max(dst.capacity() * 2));
+    grown.extend_from_slice(dst);
+    let mut old = std::mem::replace(dst, grown);
+    wipe_typed(&mut old);
+}
+
+/// Wipes the whole buffer of `typed`, including what shrinking left past its end.
+fn wipe_typed(typed: &mut Vec<TypedChar>) {
+    typed.clear();
+    typed.spare_capacity_mut().zeroize();
+}
+
+impl InputRun {
+    /// When each char was typed or inserted.
+    pub fn typed_at(&self) -> impl Iterator<Item = Instant> + '_ {
+        self.typed.iter().map(|typed| typed.at)
+    }
+
+    /// Text the same key presses type in the layout of `decoder`.
+    ///
+    /// Returns `None` unless every char was typed on a known key.
+    #[must_use]
+    pub fn redecode(&self, decoder: &impl KeyDecoder) -> Option<String> {
+        if self.typed.is_empty() || self.typed.len() != self.text.chars().count() {
+            return None;
+        }
+        let mut out = String::new();
+        for typed in &self.typed {
+            match typed.key {
+                CharKey::Pressed { stroke, dead_key } => {
+                    out.push_str(&decoder.decode(stroke, dead_key)?);
+                }
+                CharKey::SamePress => {}
+                CharKey::Unknown => return None,
+            }
+        }
+        Some(out)
+    }
+}
+
+/// Outcome of [`InputJournal::take_word_before_span`].
+#[derive(Debug, Eq, PartialEq)]
+pub enum SpanGrowth {
+    /// The journal no longer ends with the span: something was typed or erased since.
+    Changed,
+    /// No word precedes the span on its line; the journal is left as it was.
+    Boundary,
+    /// The span and its suffix were removed along with the word before them.
+    Grown {
+        word: InputRun,
+        /// Whitespace between the word and the span.
+        gap: Vec<InputRun>,
+    },
 }
 
-#[derive(Debug, Default)]
-struct InputJournal {
+/// What autoconvert replaced, kept so that an immediate Backspace can undo it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AutoconvertRevert {
+    /// Run as it was typed before the conversion.
+    pub original: InputRun,
+    /// Run inserted by autoconvert.
+    pub converted: InputRun,
+    /// Delimiter runs typed after the word, kept in place by the conversion.
+    pub suffix_runs: Vec<InputRun>,
+    /// The conversion switched the keyboard layout, so the revert switches it back.
+    pub switched_layout: bool,
+}
+
+/// Time source of a journal.
+pub trait Clock: Send {
+    fn now(&self) -> Instant;
+}
+
+impl<F: Fn() -> Instant + Send> Clock for F {
+    fn now(&self) -> Instant {
+        self()
+    }
+}
+
+/// The system monotonic clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Opaque identity of the window that receives the typed text, for example an `HWND`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct WindowId(pub isize);
+
+/// Reports which window currently receives the keys.
+///
+/// `None` means that no window has focus.
+pub trait WindowSource: Send {
+    fn current_window(&self) -> Option<WindowId>;
+
+    /// Control with the keyboard focus inside the current window, when the backend knows it.
+    fn focused_control(&self) -> Option<WindowId> {
+        None
+    }
+
+    /// Whether the focused control takes secret input, such as a password field.
+    fn is_secure_input(&self) -> bool {
+        false
+    }
+
+    /// Process that owns the current window.
+    fn process_name(&self) -> ProcessName {
+        ProcessName::Unknown
+    }
+}
+
+/// What a [`WindowSource`] knows about the process that owns the current window.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ProcessName {
+    /// Executable file name, such as `keepass.exe`.
+    Known(String),
+    /// Still being looked up. The window counts as a blocked process until the name is known.
+    Pending,
+    Unknown,
+}
+
+impl<F: Fn() -> Option<WindowId> + Send> WindowSource for F {
+    fn current_window(&self) -> Option<WindowId> {
+        self()
+    }
+}
+
+/// Window source for backends that do not track windows: the focus never changes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SingleWindow;
+
+impl WindowSource for SingleWindow {
+    fn current_window(&self) -> Option<WindowId> {
+        Some(WindowId(0))
+    }
+}
+
+/// The Win32 foreground window.
+#[cfg(windows)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ForegroundWindow;
+
+#[cfg(windows)]
+impl WindowSource for ForegroundWindow {
+    fn current_window(&self) -> Option<WindowId> {
+        let fg = unsafe { GetForegroundWindow() };
+        (!fg.0.is_null()).then_some(WindowId(fg.0 as isize))
+    }
+
+    fn focused_control(&self) -> Option<WindowId> {
+        let fg = unsafe { GetForegroundWindow() };
+        if fg.0.is_null() {
+            return None;
+        }
+        let tid = unsafe { GetWindowThreadProcessId(fg, None) };
+        let mut info = GUITHREADINFO {
+            cbSize: u32::try_from(std::mem::size_of::<GUITHREADINFO>()).ok()?,
+            ..Default::default()
+        };
+        unsafe { GetGUIThreadInfo(tid, &mut info) }.ok()?;
+        (!info.hwndFocus.0.is_null()).then_some(WindowId(info.hwndFocus.0 as isize))
+    }
+
+    /// An edit control with the `ES_PASSWORD` style. Controls drawn without a window of their
+    /// own, as in browsers, are not recognised.
+    fn is_secure_input(&self) -> bool {
+        let Some(control) = self.focused_control() else {
+            return false;
+        };
+        let hwnd = HWND(control.0 as *mut std::ffi::c_void);
+        let mut class = [0u16; 64];
+        let len = unsafe { GetClassNameW(hwnd, &mut class) };
+        let class = String::from_utf16_lossy(&class[..usize::try_from(len).unwrap_or(0)]);
+        if !class.to_ascii_lowercase().contains("edit") {
+            return false;
+        }
+        let style = unsafe { GetWindowLongW(hwnd, GWL_STYLE) };
+        style & ES_PASSWORD != 0
+    }
+
+    /// Looked up on a background thread and cached per window, so that the keyboard hook
+    /// never opens a process.
+    fn process_name(&self) -> ProcessName {
+        let fg = unsafe { GetForegroundWindow() };
+        if fg.0.is_null() {
+            return ProcessName::Unknown;
+        }
+        let mut pid = 0u32;
+        unsafe { GetWindowThreadProcessId(fg, Some(&raw mut pid)) };
+        cached_process_name(fg.0 as isize, pid)
+    }
+}
+
+/// Windows kept in the process name cache before it starts over.
+#[cfg(windows)]
+const PROCESS_NAME_CACHE_SIZE: usize = 256;
+
+/// Owning process of a window and its name, `None` while it is looked up.
+#[cfg(windows)]
+type CachedProcess = (u32, Option<Option<String>>);
+
+#[cfg(windows)]
+static PROCESS_NAMES: OnceLock<Mutex<HashMap<isize, CachedProcess>>> = OnceLock::new();
+
+#[cfg(windows)]
+fn with_process_names<R>(f: impl FnOnce(&mut HashMap<isize, CachedProcess>) -> R) -> R {
+    let mut names = PROCESS_NAMES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    f(&mut names)
+}
+
+/// Name of the process `pid` that owns `window`, from the cache or else looked up.
+///
+/// A window whose process changed since it was cached, because its handle was reused, is
+/// looked up again.
+#[cfg(windows)]
+fn cached_process_name(window: isize, pid: u32) -> ProcessName {
+    let cached = with_process_names(|names| match names.get(&window) {
+        Some((cached_pid, name)) if *cached_pid == pid => Some(name.clone()),
+        _ => {
+            if names.len() >= PROCESS_NAME_CACHE_SIZE {
+                names.clear();
+            }
+            names.insert(window, (pid, None));
+            None
+        }
+    });
+    match cached {
+        Some(Some(Some(name))) => ProcessName::Known(name),
+        Some(Some(None)) => ProcessName::Unknown,
+        Some(None) => ProcessName::Pending,
+        None => {
+            look_up_process_name(window, pid);
+            ProcessName::Pending
+        }
+    }
+}
+
+/// Looks up the process name of `window` on the lookup thread, which then re-checks whether
+/// the focus is private.
+#[cfg(windows)]
+fn look_up_process_name(window: isize, pid: u32) {
+    static LOOKUPS: OnceLock<Mutex<mpsc::Sender<(isize, u32)>>> = OnceLock::new();
+
+    let lookups = LOOKUPS.get_or_init(|| {
+        let (sender, requests) = mpsc::channel::<(isize, u32)>();
+        let spawned = thread::Builder::new()
+            .name("process-names".to_string())
+            .spawn(move || {
+                for (window, pid) in requests {
+                    store_process_name(window, pid);
+                    with_journal_mut(InputJournal::recheck_privacy);
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::warn!(error = %e, "process name lookup thread not started");
+        }
+        Mutex::new(sender)
+    });
+    let sent = lookups
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .send((window, pid));
+    // Without the thread the name is looked up here, rather than never.
+    if sent.is_err() {
+        store_process_name(window, pid);
+    }
+}
+
+#[cfg(windows)]
+fn store_process_name(window: isize, pid: u32) {
+    let name = query_process_name(pid);
+    with_process_names(|names| {
+        if let Some((cached_pid, cached)) = names.get_mut(&window)
+            && *cached_pid == pid
+        {
+            *cached = Some(name);
+        }
+    });
+}
+
+/// Executable file name of the process `pid`.
+#[cfg(windows)]
+fn query_process_name(pid: u32) -> Option<String> {
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
+    let mut path = [0u16; 1024];
+    let mut len = u32::try_from(path.len()).ok()?;
+    let queried = unsafe {
+        QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(path.as_mut_ptr()),
+            &raw mut len,
+        )
+    };
+    let _ = unsafe { CloseHandle(process) };
+    queried.ok()?;
+    let path = String::from_utf16_lossy(&path[..usize::try_from(len).ok()?]);
+    path.rsplit('\\').next().map(str::to_owned)
+}
+
+/// Where typed text goes: a window and, when known, the control focused in it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Focus {
+    pub window: WindowId,
+    pub control: Option<WindowId>,
+}
+
+/// Number of windows whose text is kept while they are in the background.
+pub const DEFAULT_WINDOW_SLOTS: usize = 8;
+
+/// Text of the journal around the caret, with the time it was last typed into.
+#[derive(Clone)]
+struct TextState {
     runs: VecDeque<InputRun>,
+    after_caret: VecDeque<InputRun>,
+    total_chars: usize,
+    after_chars: usize,
+    last_input_at: Option<Instant>,
+}
+
+/// Text of a window that lost the focus, restored when it gets it back.
+struct SavedText {
+    focus: Focus,
+    text: TextState,
+}
+
+/// Recently typed text, split into tokens and tagged with layout and origin.
+///
+/// Holds at most `capacity` chars; the ones farthest from the caret are dropped first. The process-wide journal
+/// fed by the keyboard hook is one instance, reached through the free functions of this module.
+pub struct InputJournal {
+    /// Runs before the caret. Typing, erasing and extraction all happen at their end.
+    runs: VecDeque<InputRun>,
+    /// Runs after the caret, left there by moving it left over known text.
+    after_caret: VecDeque<InputRun>,
     cap_chars: usize,
     total_chars: usize,
+    after_chars: usize,
     last_token_autoconverted: bool,
-    #[cfg(windows)]
-    last_fg_hwnd: isize,
+    /// Set right after an autoconvert and dropped by the next recorded key; ticks, hotkeys and a
+    /// focus report for the same window keep it.
+    pending_revert: Option<AutoconvertRevert>,
+    /// Revert claimed by the hook and waiting for the UI thread.
+    requested_revert: Option<AutoconvertRevert>,
+    /// Punctuation and symbols that trigger autoconvert, see [`Self::last_char_triggers_autoconvert`].
+    trigger_chars: Cow<'static, str>,
+    clock: Box<dyn Clock>,
+    window: Box<dyn WindowSource>,
+    last_focus: Option<Focus>,
+    /// Number of focus changes seen, see [`Self::focus_visit`].
+    focus_visits: u64,
+    /// Number of key events applied, see [`Self::key_events`].
+    key_events: u64,
+    /// Text of background windows, least recently focused first.
+    saved: VecDeque<SavedText>,
+    window_slots: usize,
+    last_input_at: Option<Instant>,
+    /// Start of the current focus visit; a phrase does not reach text typed before it.
+    visit_started_at: Option<Instant>,
+    /// Inactivity after which the text is forgotten, `None` to keep it.
+    idle_expiry: Option<Duration>,
+    /// Executable names, such as `keepass.exe`, whose windows are never journalled.
+    blocked_processes: Vec<String>,
+    /// Set while the focus is on secret input; nothing typed is recorded then.
+    suppressed: bool,
+}
+
+impl fmt::Debug for InputJournal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The text and the keys behind it may be a secret, so only their size is shown.
+        f.debug_struct("InputJournal")
+            .field("chars", &self.total_chars)
+            .field("chars_after_caret", &self.after_chars)
+            .field("cap_chars", &self.cap_chars)
+            .field("last_focus", &self.last_focus)
+            .field("saved_windows", &self.saved.len())
+            .field("last_input_at", &self.last_input_at)
+            .field("suppressed", &self.suppressed)
+            .finish_non_exhaustive()
+    }
 }
 
+/// State readers, for tests and tools built on the lib crate. The app only feeds the journal.
+#[cfg_attr(windows, allow(dead_code, reason = "not read by the Windows app"))]
 impl InputJournal {
-    const fn new(cap_chars: usize) -> Self {
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.cap_chars
+    }
+
+    /// Runs before the caret, in typing order.
+    pub fn runs(&self) -> impl DoubleEndedIterator<Item = &InputRun> {
+        self.runs.iter()
+    }
+
+    /// Runs after the caret, in text order.
+    pub fn runs_after_caret(&self) -> impl DoubleEndedIterator<Item = &InputRun> {
+        self.after_caret.iter()
+    }
+
+    /// Joined text on both sides of the caret.
+    #[must_use]
+    pub fn text(&self) -> String {
+        self.runs
+            .iter()
+            .chain(&self.after_caret)
+            .map(|run| run.text.as_str())
+            .collect()
+    }
+
+    /// Number of known chars after the caret.
+    #[must_use]
+    pub const fn chars_after_caret(&self) -> usize {
+        self.after_chars
+    }
+
+    /// When text was last typed, erased or inserted.
+    #[must_use]
+    pub const fn last_input_at(&self) -> Option<Instant> {
+        self.last_input_at
+    }
+
+    /// Number of background windows whose text is kept.
+    #[must_use]
+    pub fn saved_windows(&self) -> usize {
+        self.saved.len()
+    }
+}
+
+impl InputJournal {
+    /// Journal of `capacity` chars on the system clock, for a single window.
+    #[cfg_attr(windows, allow(dead_code, reason = "the app uses the global journal"))]
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self::with_sources(capacity, SystemClock, SingleWindow)
+    }
+
+    #[must_use]
+    pub fn with_sources(
+        capacity: usize,
+        clock: impl Clock + 'static,
+        window: impl WindowSource + 'static,
+    ) -> Self {
         Self {
             runs: VecDeque::new(),
-            cap_chars,
+            after_caret: VecDeque::new(),
+            cap_chars: capacity,
             total_chars: 0,
+            after_chars: 0,
             last_token_autoconverted: false,
-            #[cfg(windows)]
-            last_fg_hwnd: 0,
+            pending_revert: None,
+            requested_revert: None,
+            trigger_chars: Cow::Borrowed(DEFAULT_AUTOCONVERT_TRIGGER_CHARS),
+            clock: Box::new(clock),
+            window: Box::new(window),
+            last_focus: None,
+            focus_visits: 0,
+            key_events: 0,
+            saved: VecDeque::new(),
+            window_slots: DEFAULT_WINDOW_SLOTS,
+            last_input_at: None,
+            visit_started_at: None,
+            idle_expiry: None,
+            blocked_processes: Vec::new(),
+            suppressed: false,
         }
     }
 
-    #[cfg(any(test, windows))]
-    fn clear(&mut self) {
+    /// Copy of the text and focus state, on the system clock and with no window source.
+    ///
+    /// Text kept for background windows is not copied. See [`Self::reattach`].
+    #[must_use]
+    pub fn detach(&self) -> Self {
+        Self {
+            runs: self.runs.clone(),
+            after_caret: self.after_caret.clone(),
+            cap_chars: self.cap_chars,
+            total_chars: self.total_chars,
+            after_chars: self.after_chars,
+            last_token_autoconverted: self.last_token_autoconverted,
+            pending_revert: self.pending_revert.clone(),
+            requested_revert: self.requested_revert.clone(),
+            trigger_chars: self.trigger_chars.clone(),
+            clock: Box::new(SystemClock),
+            window: Box::new(|| None),
+            last_focus: self.last_focus,
+            focus_visits: self.focus_visits,
+            key_events: self.key_events,
+            saved: VecDeque::new(),
+            window_slots: 0,
+            last_input_at: self.last_input_at,
+            visit_started_at: self.visit_started_at,
+            idle_expiry: self.idle_expiry,
+            blocked_processes: Vec::new(),
+            suppressed: self.suppressed,
+        }
+    }
+
+    /// Takes back the text a step left in a copy made by [`Self::detach`].
+    pub fn reattach(&mut self, detached: Self) {
+        let Self {
+            runs,
+            after_caret,
+            total_chars,
+            after_chars,
+            last_token_autoconverted,
+            pending_revert,
+            requested_revert,
+            last_input_at,
+            ..
+        } = detached;
+        self.set_text_state(TextState {
+            runs,
+            after_caret,
+            total_chars,
+            after_chars,
+            last_input_at,
+        });
+        self.last_token_autoconverted = last_token_autoconverted;
+        self.pending_revert = pending_revert;
+        self.requested_revert = requested_revert;
+    }
+
+    /// Forgets the text once nothing was typed for `expiry`. `Duration::ZERO` keeps it forever.
+    pub fn set_idle_expiry(&mut self, expiry: Duration) {
+        self.idle_expiry = (!expiry.is_zero()).then_some(expiry);
+    }
+
+    /// Clears the journal if it has been idle for at least the expiry.
+    ///
+    /// Returns whether it was cleared.
+    pub fn expire_if_idle(&mut self) -> bool {
+        let (Some(expiry), Some(last)) = (self.idle_expiry, self.last_input_at) else {
+            return false;
+        };
+        if self.clock.now().saturating_duration_since(last) < expiry {
+            return false;
+        }
+        self.clear();
+        self.last_input_at = None;
+        true
+    }
+
+    fn touch(&mut self) {
+        self.last_input_at = Some(self.clock.now());
+    }
+
+    /// Current time on the journal clock.
+    #[must_use]
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    /// Sets the punctuation and symbols that trigger autoconvert; whitespace always does.
+    pub fn set_trigger_chars(&mut self, chars: &str) {
+        let chars: String = chars.chars().filter(|ch| !ch.is_whitespace()).collect();
+        self.trigger_chars = Cow::Owned(chars);
+    }
+
+    /// Forgets the text, for example when it can no longer be trusted to match the screen.
+    pub fn clear(&mut self) {
         self.runs.clear();
+        self.after_caret.clear();
         self.total_chars = 0;
+        self.after_chars = 0;
         self.last_token_autoconverted = false;
+        self.pending_revert = None;
+        self.requested_revert = None;
     }
 
-    fn append_segment(&mut self, text: &str, layout: LayoutTag, origin: RunOrigin, kind: RunKind) {
-        if text.is_empty() {
-            return;
+    /// Appends non-whitespace text and re-segments the text runs it continues.
+    ///
+    /// Keys arrive one at a time, so tokens change as the word grows: `it'` is a word and a `'`
+    /// until `s` turns it into the single word `it's`.
+    fn append_text(&mut self, run: InputRun) {
+        let (layout, origin) = (run.layout, run.origin);
+        let mut tail_rev: Vec<InputRun> = Vec::new();
+        while self.runs.back().is_some_and(|last| {
+            last.kind.is_text() && last.layout == layout && last.origin == origin
+        }) {
+            let Some(last) = self.runs.pop_back() else {
+                break;
+            };
+            self.total_chars = self.total_chars.saturating_sub(stored_len(&last));
+            tail_rev.push(last);
         }
 
+        let mut joined = String::new();
+        let mut typed: Vec<TypedChar> = Vec::new();
+        for part in tail_rev.into_iter().rev().chain(std::iter::once(run)) {
+            push_str_wiping(&mut joined, &part.text);
+            extend_typed_wiping(&mut typed, &part.typed);
+        }
+        let mut offset = 0usize;
+        for token in joined.split_word_bounds() {
+            let end = (offset + token.chars().count()).min(typed.len());
+            self.append_token(
+                token,
+                &typed[offset..end],
+                layout,
+                origin,
+                RunKind::of(token),
+            );
+            offset = end;
+        }
+        joined.zeroize();
+        wipe_typed(&mut typed);
+        self.enforce_cap_chars();
+    }
+
+    /// Appends one token, merging it into the last run when all metadata matches.
+    fn append_token(
+        &mut self,
+        token: &str,
+        typed: &[TypedChar],
+        layout: LayoutTag,
+        origin: RunOrigin,
+        kind: RunKind,
+    ) {
+        self.total_chars += token.chars().count();
         if let Some(last) = self.runs.back_mut()
             && last.layout == layout
             && last.origin == origin
             && last.kind == kind
         {
-            last.text.push_str(text);
-            self.total_chars += text.chars().count();
-            self.enforce_cap_chars();
+            push_str_wiping(&mut last.text, token);
+            extend_typed_wiping(&mut last.typed, typed);
             return;
         }
 
-        self.total_chars += text.chars().count();
         self.runs.push_back(InputRun {
-            text: text.to_string(),
+            text: token.to_string(),
             layout,
             origin,
             kind,
+            typed: typed.to_vec(),
         });
-        self.enforce_cap_chars();
     }
 
-    #[cfg(any(test, windows))]
-    fn push_text_internal(&mut self, text: &str, layout: LayoutTag, origin: RunOrigin) {
-        if text.is_empty() {
-            return;
-        }
-
-        // Segment the input without intermediate allocations by slicing `text` at kind boundaries.
-        // This keeps the journal cheap for frequent short inputs (single key presses) and also
-        // efficient for programmatic multi-character inserts.
+    /// Records typed or inserted text.
+    pub fn push_text(&mut self, text: &str, layout: LayoutTag, origin: RunOrigin) {
+        self.touch();
+        // Slice `text` at whitespace boundaries; the text in between is tokenised by `append_text`.
         let mut start = 0usize;
-        let mut current_kind: Option<RunKind> = None;
+        let mut in_space: Option<bool> = None;
 
         for (i, ch) in text.char_indices() {
-            let kind = if ch.is_whitespace() {
-                RunKind::Whitespace
-            } else {
-                RunKind::Text
-            };
-
-            match current_kind {
-                None => {
+            let space = ch.is_whitespace();
+            match in_space {
+                Some(s) if s == space => {}
+                Some(s) => {
+                    self.append_slice(&text[start..i], layout, origin, s);
                     start = i;
-                    current_kind = Some(kind);
+                    in_space = Some(space);
                 }
-                Some(k) if k == kind => {}
-                Some(k) => {
-                    self.append_segment(&text[start..i], layout, origin, k);
+                None => {
                     start = i;
-                    current_kind = Some(kind);
+                    in_space = Some(space);
                 }
             }
         }
 
-        if let Some(kind) = current_kind {
-            self.append_segment(&text[start..], layout, origin, kind);
+        if let Some(space) = in_space {
+            self.append_slice(&text[start..], layout, origin, space);
         }
     }
 
-    fn push_run(&mut self, run: InputRun) {
-        self.append_segment(&run.text, run.layout, run.origin, run.kind);
+    /// Records the text typed by one physical key press.
+    ///
+    /// The first char carries `key`; the others, for keys that type several chars, are marked
+    /// as produced by the same press.
+    pub fn push_key_press(&mut self, text: &str, layout: LayoutTag, key: CharKey) {
+        self.touch();
+        let at = self.last_input_at.unwrap_or_else(|| self.clock.now());
+        let rest = match key {
+            CharKey::Pressed { .. } => CharKey::SamePress,
+            other => other,
+        };
+        let typed = text
+            .chars()
+            .enumerate()
+            .map(|(i, _)| TypedChar {
+                at,
+                key: if i == 0 { key } else { rest },
+            })
+            .collect();
+        self.append_run(InputRun {
+            text: text.to_string(),
+            layout,
+            origin: RunOrigin::Physical,
+            kind: RunKind::of(text),
+            typed,
+        });
+    }
+
+    fn append_slice(&mut self, text: &str, layout: LayoutTag, origin: RunOrigin, space: bool) {
+        self.append_run(InputRun {
+            text: text.to_string(),
+            layout,
+            origin,
+            kind: if space {
+                RunKind::Whitespace
+            } else {
+                RunKind::Word
+            },
+            typed: Vec::new(),
+        });
+    }
+
+    /// Records a run, re-tokenising it with the text it continues.
+    ///
+    /// Chars without a [`TypedChar`] record get the current time and no key.
+    pub fn push_run(&mut self, run: InputRun) {
+        self.touch();
+        self.append_run(run);
+    }
+
+    fn append_run(&mut self, mut run: InputRun) {
+        if run.text.is_empty() || self.suppressed {
+            return;
+        }
+        let len = run.text.chars().count();
+        if run.typed.len() != len {
+            let now = self.last_input_at.unwrap_or_else(|| self.clock.now());
+            let missing = len.saturating_sub(run.typed.len());
+            reserve_typed_wiping(&mut run.typed, missing);
+            run.typed.resize(len, TypedChar::inserted(now));
+        }
+        if run.kind.is_text() {
+            self.append_text(run);
+        } else {
+            self.append_token(
+                &run.text,
+                &run.typed,
+                run.layout,
+                run.origin,
+                RunKind::Whitespace,
+            );
+            self.enforce_cap_chars();
+        }
     }
 
-    fn push_runs(&mut self, runs: impl IntoIterator<Item = InputRun>) {
+    pub fn push_runs(&mut self, runs: impl IntoIterator<Item = InputRun>) {
         for run in runs {
             self.push_run(run);
         }
     }
 
+    /// Drops the chars beyond the capacity, keeping the text near the caret.
+    ///
+    /// The far end of the longer side goes first, then both sides alternately. Whole runs are
+    /// dropped in one step and only the run the cut falls in is trimmed, so the cost is linear in
+    /// the text that was added, however large an insert is.
     fn enforce_cap_chars(&mut self) {
-        while self.total_chars > self.cap_chars {
-            let mut remove_front_run = false;
-
-            if let Some(front) = self.runs.front_mut() {
-                if let Some((idx, _)) = front.text.char_indices().nth(1) {
-                    front.text.drain(..idx);
-                } else {
-                    front.text.clear();
-                    remove_front_run = true;
-                }
-                self.total_chars = self.total_chars.saturating_sub(1);
+        let mut excess = (self.total_chars + self.after_chars).saturating_sub(self.cap_chars);
+        if excess == 0 {
+            return;
+        }
+        let (mut before, mut after) = (0usize, 0usize);
+        let imbalance = self.after_chars.abs_diff(self.total_chars).min(excess);
+        if self.after_chars > self.total_chars {
+            after += imbalance;
+        } else {
+            before += imbalance;
+        }
+        excess -= imbalance;
+        before += excess.div_ceil(2);
+        after += excess / 2;
 
-                if front.text.is_empty() {
-                    remove_front_run = true;
-                }
-            } else {
+        self.drop_front_chars(before);
+        self.drop_after_caret_back_chars(after);
+    }
+
+    fn drop_front_chars(&mut self, mut count: usize) {
+        self.total_chars = self.total_chars.saturating_sub(count);
+        while count > 0 {
+            let Some(front) = self.runs.front_mut() else {
                 self.total_chars = 0;
+                return;
+            };
+            let len = stored_len(front);
+            if len <= count {
+                count -= len;
+                let _ = self.runs.pop_front();
+                continue;
+            }
+            let cut = front.text.char_indices().nth(count).map_or(0, |(i, _)| i);
+            front.text.drain(..cut);
+            front.typed.drain(..count);
+            return;
+        }
+    }
+
+    fn drop_after_caret_back_chars(&mut self, mut count: usize) {
+        self.after_chars = self.after_chars.saturating_sub(count);
+        while count > 0 {
+            let Some(back) = self.after_caret.back_mut() else {
+                self.after_chars = 0;
+                return;
+            };
+            let len = stored_len(back);
+            if len <= count {
+                count -= len;
+                let _ = self.after_caret.pop_back();
+                continue;
+            }
+            let keep = len - count;
+            let cut = back.text.char_indices().nth(keep).map_or(0, |(i, _)| i);
+            back.text.truncate(cut);
+            back.typed.truncate(keep);
+            return;
+        }
+    }
+
+    /// Erases the char before the caret.
+    pub fn backspace(&mut self) {
+        self.touch();
+        let _ = self.pop_before_caret();
+    }
+
+    /// Erases the char after the caret. At the end of the known text it is unknown text that
+    /// goes, so the journal stays as it is.
+    pub fn delete_forward(&mut self) {
+        self.touch();
+        let _ = self.pop_after_caret_front();
+    }
+
+    /// Moves the caret one char left. Moving past the start of the known text clears the journal.
+    pub fn caret_left(&mut self) {
+        let Some(moved) = self.pop_before_caret() else {
+            self.clear();
+            return;
+        };
+
+        self.after_chars += 1;
+        if let Some(front) = self.after_caret.front_mut()
+            && front.layout == moved.layout
+            && front.origin == moved.origin
+            && WordClass::of(front.kind) == WordClass::of(moved.kind)
+        {
+            prepend_str_wiping(&mut front.text, &moved.text);
+            prepend_typed_wiping(&mut front.typed, &moved.typed);
+            front.kind = RunKind::of(&front.text);
+            return;
+        }
+        self.after_caret.push_front(moved);
+    }
+
+    /// Mirrors Ctrl+Backspace: erases the whitespace before the caret, then the word, or the
+    /// run of punctuation, before it. The parts of a word typed in different layouts go together.
+    pub fn delete_word_backward(&mut self) {
+        self.touch();
+        let mut class = WordClass::Space;
+        while let Some(run) = self.runs.back() {
+            let run_class = WordClass::of(run.kind);
+            if run_class != class {
+                if class != WordClass::Space {
+                    break;
+                }
+                class = run_class;
+            }
+            let Some(run) = self.runs.pop_back() else {
                 break;
+            };
+            self.total_chars = self.total_chars.saturating_sub(stored_len(&run));
+        }
+    }
+
+    /// Mirrors Ctrl+Delete: erases the word, or the run of punctuation, after the caret and the
+    /// whitespace that follows it. At the end of the known text the journal stays as it is.
+    pub fn delete_word_forward(&mut self) {
+        self.touch();
+        let Some(class) = self.after_caret.front().map(|run| WordClass::of(run.kind)) else {
+            return;
+        };
+        for erase in [class, WordClass::Space] {
+            while self
+                .after_caret
+                .front()
+                .is_some_and(|run| WordClass::of(run.kind) == erase)
+            {
+                let Some(run) = self.after_caret.pop_front() else {
+                    break;
+                };
+                self.after_chars = self.after_chars.saturating_sub(stored_len(&run));
             }
+        }
+    }
 
-            if remove_front_run {
-                let _ = self.runs.pop_front();
+    /// Moves the caret one char right. Moving past the end of the known text clears the journal.
+    pub fn caret_right(&mut self) {
+        let Some(moved) = self.pop_after_caret_front() else {
+            self.clear();
+            return;
+        };
+        self.append_run(moved);
+    }
+
+    /// Moves the caret to the end of the word it is in and returns how many chars it moved.
+    ///
+    /// A caret between two letters of a word moves past the rest of the word, so that the word
+    /// can be taken whole; anywhere else it stays put.
+    pub fn move_caret_to_word_end(&mut self) -> usize {
+        if self.runs.back().is_none_or(|run| !run.kind.is_text()) {
+            return 0;
+        }
+        let mut moved = 0usize;
+        while self
+            .after_caret
+            .front()
+            .is_some_and(|run| run.kind.is_text())
+        {
+            self.caret_right();
+            moved += 1;
+        }
+        moved
+    }
+
+    /// Removes the char before the caret, re-tokenising what is left of its run.
+    fn pop_before_caret(&mut self) -> Option<InputRun> {
+        let mut last = self.runs.pop_back()?;
+        self.total_chars = self.total_chars.saturating_sub(stored_len(&last));
+        let ch = last.text.pop();
+        let typed = last.typed.pop();
+        let popped = ch.map(|ch| single_char_run(ch, typed, last.layout, last.origin));
+
+        // Re-segment: erasing `s` from `it's` leaves the word `it` and a `'`.
+        self.append_run(last);
+        popped
+    }
+
+    fn pop_after_caret_front(&mut self) -> Option<InputRun> {
+        let front = self.after_caret.front_mut()?;
+        let ch = front.text.chars().next()?;
+        front.text.drain(..ch.len_utf8());
+        let typed = (!front.typed.is_empty()).then(|| front.typed.remove(0));
+        let popped = single_char_run(ch, typed, front.layout, front.origin);
+        if front.text.is_empty() {
+            let _ = self.after_caret.pop_front();
+        }
+        self.after_chars = self.after_chars.saturating_sub(1);
+        Some(popped)
+    }
+
+    /// Takes the word before `span`, which ends the journal followed by `suffix`, to grow a
+    /// conversion of the span by one word to the left.
+    ///
+    /// The word stops growth when a line break separates it from the span; the journal is then
+    /// left as it was, apart from an idle expiry.
+    pub fn take_word_before_span(&mut self, span: &str, suffix: &str) -> SpanGrowth {
+        let _ = self.expire_if_idle();
+        let before = self.text_state();
+        if !self.take_tail_if_matches(&format!("{span}{suffix}")) {
+            return SpanGrowth::Changed;
+        }
+        if let Some((word, gap)) = self.take_last_layout_run_with_suffix()
+            && !gap.iter().any(|run| run.text.contains(['\n', '\r']))
+        {
+            return SpanGrowth::Grown { word, gap };
+        }
+        self.set_text_state(before);
+        SpanGrowth::Boundary
+    }
+
+    /// Removes the trailing `expected` text if the journal ends with it.
+    pub fn take_tail_if_matches(&mut self, expected: &str) -> bool {
+        let _ = self.expire_if_idle();
+        let need = expected.chars().count();
+        let mut have = 0usize;
+        let mut tail_rev: Vec<&str> = Vec::new();
+        for run in self.runs.iter().rev() {
+            if have >= need {
+                break;
             }
+            tail_rev.push(&run.text);
+            have += run.text.chars().count();
+        }
+
+        let mut tail: String = tail_rev.into_iter().rev().collect();
+        let matches = have >= need && tail.ends_with(expected);
+        tail.zeroize();
+        if !matches {
+            return false;
+        }
+
+        for _ in 0..need {
+            self.backspace();
         }
+        true
     }
 
-    #[cfg(any(test, windows))]
-    fn backspace(&mut self) {
-        let mut pop_last = false;
+    /// Follows the focus: the text of the window being left is put aside and the text of the
+    /// window being entered is restored, if it was put aside before and is still trusted.
+    ///
+    /// Text typed while no window has focus is kept for the next window that has no saved text.
+    /// Recording is suppressed while the focus is on secure input or in a blocked process.
+    pub fn observe_window(&mut self) {
+        let focus = self.window.current_window().map(|window| Focus {
+            window,
+            control: self.window.focused_control(),
+        });
+        if focus == self.last_focus {
+            return;
+        }
+        let private = focus.is_some() && self.focus_is_private();
+        self.set_focus(focus);
+        self.set_suppressed(private);
+    }
 
-        if let Some(last) = self.runs.back_mut()
-            && let Some((idx, _)) = last.text.char_indices().last()
+    /// Checks again whether the focused window takes private input, without a focus change.
+    ///
+    /// A field can turn into a password field and back while it keeps the focus, and the
+    /// process of a window may only become known after the window got the focus.
+    pub fn recheck_privacy(&mut self) {
+        if self.last_focus.is_none() {
+            return;
+        }
+        let private = self.focus_is_private();
+        if private != self.suppressed {
+            self.set_suppressed(private);
+        }
+    }
+
+    fn focus_is_private(&self) -> bool {
+        if self.window.is_secure_input() {
+            return true;
+        }
+        if self.blocked_processes.is_empty() {
+            return false;
+        }
+        match self.window.process_name() {
+            ProcessName::Known(name) => self.is_blocked_process(&name),
+            ProcessName::Pending => true,
+            ProcessName::Unknown => false,
+        }
+    }
+
+    /// Sets the executables whose windows are never journalled, compared without case.
+    pub fn set_blocked_processes(&mut self, names: &[String]) {
+        self.blocked_processes = names
+            .iter()
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+    }
+
+    fn is_blocked_process(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.blocked_processes.contains(&name)
+    }
+
+    /// Stops or resumes recording. Suppressing forgets the text of the focused window.
+    pub fn set_suppressed(&mut self, suppressed: bool) {
+        self.suppressed = suppressed;
+        if suppressed {
+            self.clear();
+        }
+    }
+
+    /// Whether typed text is currently not recorded.
+    #[must_use]
+    pub const fn is_suppressed(&self) -> bool {
+        self.suppressed
+    }
+
+    /// Window and control the text goes to, as last observed.
+    #[must_use]
+    pub const fn focus(&self) -> Option<Focus> {
+        self.last_focus
+    }
+
+    /// Identity of the current stay in the focused window: it changes on every focus change, so
+    /// leaving a window and coming back to it gives a new one.
+    #[must_use]
+    pub const fn focus_visit(&self) -> u64 {
+        self.focus_visits
+    }
+
+    /// Number of key events applied so far; ticks, hotkeys and focus reports do not count.
+    ///
+    /// A step that saw the same count as the previous one knows no key was recorded in between.
+    #[must_use]
+    pub const fn key_events(&self) -> u64 {
+        self.key_events
+    }
+
+    /// Same as [`Self::observe_window`] for a focus reported by the caller.
+    pub fn set_focus(&mut self, focus: Option<Focus>) {
+        if focus == self.last_focus {
+            return;
+        }
+        if let Some(left) = self.last_focus {
+            self.save_text(left);
+            self.clear();
+        }
+        if let Some(entered) = focus {
+            self.restore_text(entered);
+        }
+        self.last_focus = focus;
+        self.focus_visits += 1;
+        self.visit_started_at = Some(self.clock.now());
+    }
+
+    /// Applies an input event that concerns the typed text; other events are ignored.
+    ///
+    /// This is the journal step of the typing pipeline, shared with the keyboard hook.
+    ///
+    /// While recording is suppressed only focus changes, untracked input and ticks apply.
+    pub fn apply(&mut self, event: &InputEvent) {
+        let key_event = !matches!(
+            event,
+            InputEvent::FocusChanged(_)
+                | InputEvent::Untracked
+                | InputEvent::Tick
+                | InputEvent::Hotkey(_)
+        );
+        if key_event {
+            self.pending_revert = None;
+            self.key_events += 1;
+        }
+        if self.suppressed
+            && !matches!(
+                event,
+                InputEvent::FocusChanged(_) | InputEvent::Untracked | InputEvent::Tick
+            )
         {
-            last.text.drain(idx..);
-            self.total_chars = self.total_chars.saturating_sub(1);
-            if last.text.is_empty() {
-                pop_last = true;
+            return;
+        }
+        match event {
+            InputEvent::Text {
+                text,
+                layout,
+                key,
+                origin,
+            } => {
+                if text.chars().any(char::is_alphanumeric) {
+                    self.last_token_autoconverted = false;
+                }
+                match origin {
+                    RunOrigin::Physical => self.push_key_press(text, *layout, *key),
+                    RunOrigin::Programmatic => self.push_text(text, *layout, *origin),
+                }
             }
+            InputEvent::Backspace => self.backspace(),
+            InputEvent::Delete => self.delete_forward(),
+            InputEvent::DeleteWordBackward => self.delete_word_backward(),
+            InputEvent::DeleteWordForward => self.delete_word_forward(),
+            InputEvent::Navigate(Navigation::CaretLeft) => self.caret_left(),
+            InputEvent::Navigate(Navigation::CaretRight) => self.caret_right(),
+            InputEvent::Navigate(Navigation::Unknown) => self.clear(),
+            InputEvent::FocusChanged(focus) => self.set_focus(*focus),
+            InputEvent::Untracked => self.untracked_input(),
+            InputEvent::Tick => {
+                let _ = self.expire_if_idle();
+            }
+            InputEvent::Hotkey(_) => {}
         }
+    }
 
-        if pop_last {
-            let _ = self.runs.pop_back();
+    /// Sets how many background windows keep their text. `0` forgets it on every focus change.
+    #[cfg_attr(
+        windows,
+        allow(dead_code, reason = "the app keeps the default number of slots")
+    )]
+    pub fn set_window_slots(&mut self, slots: usize) {
+        self.window_slots = slots;
+        while self.saved.len() > slots {
+            let _ = self.saved.pop_front();
         }
     }
 
-    #[cfg(windows)]
-    fn invalidate_if_foreground_changed(&mut self) {
-        let fg = unsafe { GetForegroundWindow() };
-        let raw = fg.0 as isize;
-        if raw == 0 {
-            self.clear();
-            self.last_fg_hwnd = 0;
+    /// Records input the journal cannot follow, such as a mouse click: the text of the focused
+    /// window is cleared and the text kept for background windows is no longer trusted.
+    pub fn untracked_input(&mut self) {
+        self.clear();
+        self.forget_background_windows();
+    }
+
+    /// Drops the text kept for background windows, for example after input sent by another program.
+    pub fn forget_background_windows(&mut self) {
+        self.saved.clear();
+    }
+
+    fn save_text(&mut self, focus: Focus) {
+        self.saved.retain(|saved| saved.focus != focus);
+        if self.window_slots == 0 || self.total_chars + self.after_chars == 0 {
             return;
         }
+        if self.saved.len() == self.window_slots {
+            let _ = self.saved.pop_front();
+        }
+        let text = TextState {
+            runs: std::mem::take(&mut self.runs),
+            after_caret: std::mem::take(&mut self.after_caret),
+            total_chars: self.total_chars,
+            after_chars: self.after_chars,
+            last_input_at: self.last_input_at,
+        };
+        self.saved.push_back(SavedText { focus, text });
+    }
 
-        if self.last_fg_hwnd == 0 {
-            self.last_fg_hwnd = raw;
+    fn restore_text(&mut self, focus: Focus) {
+        let Some(index) = self.saved.iter().position(|saved| saved.focus == focus) else {
             return;
-        }
+        };
+        let Some(saved) = self.saved.remove(index) else {
+            return;
+        };
+        self.clear();
+        self.set_text_state(saved.text);
+        let _ = self.expire_if_idle();
+    }
 
-        if self.last_fg_hwnd != raw {
-            self.clear();
-            self.last_fg_hwnd = raw;
+    fn text_state(&self) -> TextState {
+        TextState {
+            runs: self.runs.clone(),
+            after_caret: self.after_caret.clone(),
+            total_chars: self.total_chars,
+            after_chars: self.after_chars,
+            last_input_at: self.last_input_at,
         }
     }
 
-    #[cfg(any(test, windows))]
+    fn set_text_state(&mut self, text: TextState) {
+        self.runs = text.runs;
+        self.after_caret = text.after_caret;
+        self.total_chars = text.total_chars;
+        self.after_chars = text.after_chars;
+        self.last_input_at = text.last_input_at;
+    }
+
     fn last_char(&self) -> Option<char> {
         self.runs.back()?.text.chars().last()
     }
 
-    #[cfg(any(test, windows))]
     fn prev_char_before_last(&self) -> Option<char> {
         let mut runs_it = self.runs.iter().rev();
         let last_run = runs_it.next()?;
@@ -274,24 +1673,55 @@ impl InputJournal {
         None
     }
 
-    fn take_last_layout_run_with_suffix(&mut self) -> Option<(InputRun, Vec<InputRun>)> {
+    /// Takes the last word as one run, with the whitespace after it.
+    ///
+    /// The word is every token back to the previous whitespace typed in the same layout, so
+    /// punctuation typed in the wrong layout (`ghbdtn,` for `привет,`) stays part of it.
+    pub fn take_last_layout_run_with_suffix(&mut self) -> Option<(InputRun, Vec<InputRun>)> {
+        let _ = self.expire_if_idle();
         let mut suffix_runs = self.pop_suffix_whitespace();
 
-        if self.runs.back().is_none_or(|run| run.kind != RunKind::Text) {
+        let Some(last) = self.runs.back().filter(|run| run.kind.is_text()) else {
             self.restore_suffix(&mut suffix_runs);
             return None;
+        };
+        let (layout, origin) = (last.layout, last.origin);
+
+        let mut tokens_rev: Vec<InputRun> = Vec::new();
+        while self
+            .runs
+            .back()
+            .is_some_and(|run| run.kind.is_text() && run.layout == layout && run.origin == origin)
+        {
+            let run = self.runs.pop_back()?;
+            self.total_chars = self.total_chars.saturating_sub(stored_len(&run));
+            tokens_rev.push(run);
         }
 
-        let run = self.runs.pop_back()?;
-        self.total_chars = self.total_chars.saturating_sub(run.text.chars().count());
+        let mut text = String::new();
+        let mut typed: Vec<TypedChar> = Vec::new();
+        for token in tokens_rev.into_iter().rev() {
+            push_str_wiping(&mut text, &token.text);
+            extend_typed_wiping(&mut typed, &token.typed);
+        }
+        let run = InputRun {
+            kind: RunKind::of(&text),
+            text,
+            layout,
+            origin,
+            typed,
+        };
         suffix_runs.reverse();
         Some((run, suffix_runs))
     }
 
-    fn take_last_layout_sequence_with_suffix(&mut self) -> Option<(Vec<InputRun>, Vec<InputRun>)> {
+    pub fn take_last_layout_sequence_with_suffix(
+        &mut self,
+    ) -> Option<(Vec<InputRun>, Vec<InputRun>)> {
+        let _ = self.expire_if_idle();
         let mut suffix_runs = self.pop_suffix_whitespace();
 
-        if self.runs.back().is_none_or(|run| run.kind != RunKind::Text) {
+        if self.runs.back().is_none_or(|run| !run.kind.is_text()) {
             self.restore_suffix(&mut suffix_runs);
             return None;
         }
@@ -305,7 +1735,7 @@ impl InputJournal {
                 break;
             }
             let run = self.runs.pop_back()?;
-            self.total_chars = self.total_chars.saturating_sub(run.text.chars().count());
+            self.total_chars = self.total_chars.saturating_sub(stored_len(&run));
             seq_rev.push(run);
         }
 
@@ -319,6 +1749,161 @@ impl InputJournal {
         Some((seq_rev, suffix_runs))
     }
 
+    /// Words before the end of the journal, oldest first, not crossing a line break.
+    ///
+    /// Tokens are joined into words the way [`Self::take_last_layout_run_with_suffix`] joins them.
+    #[must_use]
+    pub fn recent_text_runs(&self, limit: usize) -> Vec<InputRun> {
+        let mut words_rev: Vec<InputRun> = Vec::new();
+        let mut joinable = false;
+        for run in self.runs.iter().rev() {
+            if !run.kind.is_text() {
+                if run.text.contains(['\n', '\r']) {
+                    break;
+                }
+                joinable = false;
+                continue;
+            }
+            let full = words_rev.len() == limit;
+            match words_rev.last_mut() {
+                Some(word)
+                    if joinable && word.layout == run.layout && word.origin == run.origin =>
+                {
+                    prepend_str_wiping(&mut word.text, &run.text);
+                    prepend_typed_wiping(&mut word.typed, &run.typed);
+                }
+                _ if full => break,
+                _ => words_rev.push(run.clone()),
+            }
+            joinable = true;
+        }
+        for word in &mut words_rev {
+            word.kind = RunKind::of(&word.text);
+        }
+        words_rev.reverse();
+        words_rev
+    }
+
+    /// Takes the text runs of the last word, whatever their layouts, with the whitespace after it.
+    pub fn take_last_word_runs_with_suffix(&mut self) -> Option<(Vec<InputRun>, Vec<InputRun>)> {
+        let _ = self.expire_if_idle();
+        let mut suffix_runs = self.pop_suffix_whitespace();
+
+        let mut word_rev: Vec<InputRun> = Vec::new();
+        while self.runs.back().is_some_and(|run| run.kind.is_text()) {
+            let run = self.runs.pop_back()?;
+            self.total_chars = self.total_chars.saturating_sub(stored_len(&run));
+            word_rev.push(run);
+        }
+
+        if word_rev.is_empty() {
+            self.restore_suffix(&mut suffix_runs);
+            return None;
+        }
+
+        word_rev.reverse();
+        suffix_runs.reverse();
+        Some((word_rev, suffix_runs))
+    }
+
+    /// Takes the runs typed since the last sentence end, line break, focus change or pause longer
+    /// than [`PHRASE_PAUSE`], see [`phrase_start`].
+    pub fn take_last_phrase_with_suffix(&mut self) -> Option<(Vec<InputRun>, Vec<InputRun>)> {
+        let _ = self.expire_if_idle();
+        let mut suffix_runs = self.pop_suffix_whitespace();
+
+        if self.runs.back().is_none_or(|run| !run.kind.is_text()) {
+            self.restore_suffix(&mut suffix_runs);
+            return None;
+        }
+
+        let mut text: String = self.runs.iter().map(|run| run.text.as_str()).collect();
+        let start = phrase_start(&text);
+        let mut remaining = text.chars().count();
+        text.zeroize();
+        let mut phrase_rev: Vec<InputRun> = Vec::new();
+        // Time of the first char after `run`, to find the pause that ends the burst.
+        let mut next_at: Option<Instant> = None;
+        while let Some(run) = self.runs.back() {
+            let len = stored_len(run);
+            if remaining - len < start {
+                break;
+            }
+            if let (Some(next_at), Some(last)) = (next_at, run.typed.last())
+                && next_at.saturating_duration_since(last.at) > PHRASE_PAUSE
+            {
+                break;
+            }
+            if let (Some(visit), Some(last)) = (self.visit_started_at, run.typed.last())
+                && last.at < visit
+            {
+                break;
+            }
+            remaining -= len;
+            let run = self.runs.pop_back()?;
+            self.total_chars = self.total_chars.saturating_sub(len);
+            next_at = run.typed.first().map_or(next_at, |typed| Some(typed.at));
+            phrase_rev.push(run);
+        }
+        // A pause before whitespace leaves it at the start of the phrase; it stays in place.
+        while let Some(run) = phrase_rev.pop_if(|run| run.kind == RunKind::Whitespace) {
+            self.total_chars += stored_len(&run);
+            self.runs.push_back(run);
+        }
+
+        if phrase_rev.is_empty() {
+            self.restore_suffix(&mut suffix_runs);
+            return None;
+        }
+
+        phrase_rev.reverse();
+        suffix_runs.reverse();
+        Some((phrase_rev, suffix_runs))
+    }
+
+    /// Remembers the last autoconvert so that a Backspace right after it can revert it.
+    pub fn mark_last_token_autoconverted(&mut self, revert: AutoconvertRevert) {
+        self.last_token_autoconverted = true;
+        self.pending_revert = Some(revert);
+    }
+
+    #[must_use]
+    pub const fn last_token_autoconverted(&self) -> bool {
+        self.last_token_autoconverted
+    }
+
+    /// See [`request_autoconvert_revert`].
+    pub fn request_autoconvert_revert(&mut self) -> bool {
+        let Some(revert) = self.pending_revert.take() else {
+            return false;
+        };
+        self.requested_revert = Some(revert);
+        true
+    }
+
+    pub const fn take_requested_autoconvert_revert(&mut self) -> Option<AutoconvertRevert> {
+        self.requested_revert.take()
+    }
+
+    /// Whether the last key ended a word: it typed whitespace, or a punctuation or symbol token
+    /// ending in a trigger char, right after text.
+    #[must_use]
+    pub fn last_char_triggers_autoconvert(&self) -> bool {
+        let (Some(run), Some(last)) = (self.runs.back(), self.last_char()) else {
+            return false;
+        };
+
+        let triggers = match run.kind {
+            RunKind::Whitespace => true,
+            RunKind::Punctuation | RunKind::Symbol => self.trigger_chars.contains(last),
+            RunKind::Word | RunKind::Digit => false,
+        };
+        triggers
+            && self
+                .prev_char_before_last()
+                .is_some_and(|prev| !prev.is_whitespace())
+    }
+
     fn pop_suffix_whitespace(&mut self) -> Vec<InputRun> {
         let mut suffix_runs: Vec<InputRun> = Vec::new();
         while self
@@ -329,7 +1914,7 @@ impl InputJournal {
             let Some(run) = self.runs.pop_back() else {
                 break;
             };
-            self.total_chars = self.total_chars.saturating_sub(run.text.chars().count());
+            self.total_chars = self.total_chars.saturating_sub(stored_len(&run));
             suffix_runs.push(run);
         }
         suffix_runs
@@ -338,7 +1923,7 @@ impl InputJournal {
     fn restore_suffix(&mut self, suffix_runs: &mut Vec<InputRun>) {
         // `suffix_runs` is expected to be in reverse order (from repeated `pop_back`).
         while let Some(run) = suffix_runs.pop() {
-            self.total_chars += run.text.chars().count();
+            self.total_chars += stored_len(&run);
             self.runs.push_back(run);
         }
     }
@@ -349,6 +1934,7 @@ impl InputJournal {
 struct DecodedText {
     text: String,
     layout: LayoutTag,
+    key: CharKey,
 }
 
 #[cfg(windows)]
@@ -381,27 +1967,132 @@ fn current_foreground_layout_tag() -> LayoutTag {
     layout_tag_from_hkl(hkl)
 }
 
-pub fn mark_last_token_autoconverted() {
-    with_journal_mut(|j| j.last_token_autoconverted = true);
+#[cfg(test)]
+pub fn mark_last_token_autoconverted(revert: AutoconvertRevert) {
+    with_journal_mut(|j| j.mark_last_token_autoconverted(revert));
+}
+
+/// Claims the revert of the last autoconvert for a Backspace typed right after it.
+///
+/// Returns `false` when anything else was recorded since the conversion.
+/// On success the Backspace must not reach the application: the revert replaces it.
+#[must_use]
+pub fn request_autoconvert_revert() -> bool {
+    with_journal_mut(InputJournal::request_autoconvert_revert)
+}
+
+/// Takes the revert claimed by [`request_autoconvert_revert`].
+#[cfg(test)]
+#[must_use]
+pub fn take_requested_autoconvert_revert() -> Option<AutoconvertRevert> {
+    with_journal_mut(InputJournal::take_requested_autoconvert_revert)
 }
 
 #[cfg(any(test, windows))]
 #[must_use]
 pub fn last_token_autoconverted() -> bool {
-    with_journal(|j| j.last_token_autoconverted)
+    with_journal(InputJournal::last_token_autoconverted)
+}
+
+#[cfg(windows)]
+fn key_down(vk: i32) -> bool {
+    let state = unsafe { GetAsyncKeyState(vk) }.cast_unsigned();
+    (state & 0x8000) != 0
+}
+
+// Keep this module independent from `crate::platform` so it can be built from the minimal lib target.
+// VK_CONTROL = 0x11, VK_MENU (Alt) = 0x12.
+#[cfg(windows)]
+fn ctrl_down() -> bool {
+    key_down(0x11)
+}
+
+#[cfg(windows)]
+fn alt_down() -> bool {
+    key_down(0x12)
 }
 
 #[cfg(windows)]
 fn mods_ctrl_or_alt_down() -> bool {
-    // Keep this module independent from `crate::platform` so it can be built from the minimal lib target.
-    // VK_CONTROL = 0x11, VK_MENU (Alt) = 0x12.
-    let ctrl = unsafe { GetAsyncKeyState(0x11) }.cast_unsigned();
-    let alt = unsafe { GetAsyncKeyState(0x12) }.cast_unsigned();
-    (ctrl & 0x8000) != 0 || (alt & 0x8000) != 0
+    ctrl_down() || alt_down()
+}
+
+/// Modifier keys a key press is decoded with.
+///
+/// A key held back by the hook is recorded after the fact, with the modifiers that were down
+/// when it went down rather than the ones down now.
+#[cfg(windows)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeldModifiers {
+    pub left_shift: bool,
+    pub right_shift: bool,
+    pub left_ctrl: bool,
+    pub right_ctrl: bool,
+    pub left_alt: bool,
+    pub right_alt: bool,
+}
+
+#[cfg(windows)]
+impl HeldModifiers {
+    fn now() -> Self {
+        let down = |vk: VIRTUAL_KEY| key_down(i32::from(vk.0));
+        Self {
+            left_shift: down(VK_LSHIFT),
+            right_shift: down(VK_RSHIFT),
+            left_ctrl: down(VK_LCONTROL),
+            right_ctrl: down(VK_RCONTROL),
+            left_alt: down(VK_LMENU),
+            right_alt: down(VK_RMENU),
+        }
+    }
+
+    const fn shift(self) -> bool {
+        self.left_shift || self.right_shift
+    }
+
+    const fn ctrl(self) -> bool {
+        self.left_ctrl || self.right_ctrl
+    }
+
+    const fn alt(self) -> bool {
+        self.left_alt || self.right_alt
+    }
+
+    /// Right Alt on a layout with AltGr, which Windows reports together with a left Ctrl.
+    const fn altgr(self) -> bool {
+        self.left_ctrl && self.right_alt
+    }
+
+    /// Sets the modifier entries of a keyboard state to these modifiers.
+    fn apply(self, state: &mut [u8; 256]) {
+        let keys = [
+            (VK_SHIFT, self.shift()),
+            (VK_LSHIFT, self.left_shift),
+            (VK_RSHIFT, self.right_shift),
+            (VK_CONTROL, self.ctrl()),
+            (VK_LCONTROL, self.left_ctrl),
+            (VK_RCONTROL, self.right_ctrl),
+            (VK_MENU, self.alt()),
+            (VK_LMENU, self.left_alt),
+            (VK_RMENU, self.right_alt),
+        ];
+        for (vk, down) in keys {
+            let entry = &mut state[usize::from(vk.0)];
+            if down {
+                *entry |= 0x80;
+            } else {
+                *entry &= !0x80;
+            }
+        }
+    }
 }
 
 #[cfg(windows)]
-fn decode_typed_text(kb: &KBDLLHOOKSTRUCT, vk: VIRTUAL_KEY) -> Option<DecodedText> {
+fn decode_typed_text(
+    kb: &KBDLLHOOKSTRUCT,
+    vk: VIRTUAL_KEY,
+    mods: HeldModifiers,
+) -> Option<DecodedText> {
     let fg = unsafe { GetForegroundWindow() };
     if fg.0.is_null() {
         return None;
@@ -416,27 +2107,18 @@ fn decode_typed_text(kb: &KBDLLHOOKSTRUCT, vk: VIRTUAL_KEY) -> Option<DecodedTex
         return None;
     }
 
-    let async_down = |vk: VIRTUAL_KEY| -> bool {
-        let v = unsafe { GetAsyncKeyState(i32::from(vk.0)) }.cast_unsigned();
-        (v & 0x8000) != 0
-    };
-
-    let apply_async_key = |state: &mut [u8; 256], vk: VIRTUAL_KEY| {
-        let idx = usize::from(vk.0);
-        if idx >= state.len() {
-            return;
-        }
+    // The hook runs before the system updates the keyboard state, so modifiers come from `mods`.
+    mods.apply(&mut state);
 
-        if async_down(vk) {
-            state[idx] |= 0x80;
-        } else {
-            state[idx] &= !0x80;
-        }
+    let stroke = KeyStroke {
+        vk: vk.0,
+        scan_code: u16::try_from(kb.scanCode).unwrap_or_default(),
+        shift: state[usize::from(VK_SHIFT.0)] & 0x80 != 0,
+        altgr: state[usize::from(VK_CONTROL.0)] & 0x80 != 0
+            && state[usize::from(VK_MENU.0)] & 0x80 != 0,
+        caps_lock: state[usize::from(VK_CAPITAL.0)] & 0x01 != 0,
     };
-
-    apply_async_key(&mut state, VK_SHIFT);
-    apply_async_key(&mut state, VK_LSHIFT);
-    apply_async_key(&mut state, VK_RSHIFT);
+    let dead_key = take_pending_dead_key();
 
     let mut buf = [0u16; 8];
     let rc = unsafe { ToUnicodeEx(u32::from(vk.0), kb.scanCode, &state, &mut buf, 0, Some(hkl)) };
@@ -444,6 +2126,7 @@ fn decode_typed_text(kb: &KBDLLHOOKSTRUCT, vk: VIRTUAL_KEY) -> Option<DecodedTex
     if rc == -1 {
         let _ =
             unsafe { ToUnicodeEx(u32::from(vk.0), kb.scanCode, &state, &mut buf, 0, Some(hkl)) };
+        set_pending_dead_key(stroke);
         return None;
     }
 
@@ -458,124 +2141,302 @@ fn decode_typed_text(kb: &KBDLLHOOKSTRUCT, vk: VIRTUAL_KEY) -> Option<DecodedTex
         return None;
     }
 
-    Some(DecodedText { text: s, layout })
+    Some(DecodedText {
+        text: s,
+        layout,
+        key: CharKey::Pressed { stroke, dead_key },
+    })
+}
+
+/// Dead key pressed before the next typed char.
+#[cfg(windows)]
+static PENDING_DEAD_KEY: Mutex<Option<KeyStroke>> = Mutex::new(None);
+
+#[cfg(windows)]
+fn set_pending_dead_key(stroke: KeyStroke) {
+    if let Ok(mut pending) = PENDING_DEAD_KEY.lock() {
+        *pending = Some(stroke);
+    }
+}
+
+#[cfg(windows)]
+fn take_pending_dead_key() -> Option<KeyStroke> {
+    PENDING_DEAD_KEY.lock().ok()?.take()
+}
+
+#[cfg(windows)]
+fn dead_key_pending() -> bool {
+    PENDING_DEAD_KEY
+        .lock()
+        .is_ok_and(|pending| pending.is_some())
+}
+
+/// Decodes key presses through an installed keyboard layout with `ToUnicodeEx`.
+#[cfg(windows)]
+#[derive(Clone, Copy, Debug)]
+pub struct LayoutDecoder(pub HKL);
+
+#[cfg(windows)]
+impl LayoutDecoder {
+    fn to_unicode(self, stroke: KeyStroke, buf: &mut [u16; 8]) -> i32 {
+        const DOWN: u8 = 0x80;
+        let mut state = [0u8; 256];
+        if stroke.shift {
+            state[usize::from(VK_SHIFT.0)] = DOWN;
+            state[usize::from(VK_LSHIFT.0)] = DOWN;
+        }
+        if stroke.altgr {
+            state[usize::from(VK_CONTROL.0)] = DOWN;
+            state[usize::from(VK_LCONTROL.0)] = DOWN;
+            state[usize::from(VK_MENU.0)] = DOWN;
+            state[usize::from(VK_RMENU.0)] = DOWN;
+        }
+        if stroke.caps_lock {
+            state[usize::from(VK_CAPITAL.0)] = 0x01;
+        }
+        unsafe {
+            ToUnicodeEx(
+                u32::from(stroke.vk),
+                u32::from(stroke.scan_code),
+                &state,
+                buf,
+                0,
+                Some(self.0),
+            )
+        }
+    }
+}
+
+#[cfg(windows)]
+impl KeyDecoder for LayoutDecoder {
+    fn decode(&self, stroke: KeyStroke, dead_key: Option<KeyStroke>) -> Option<String> {
+        let mut buf = [0u16; 8];
+        if let Some(dead_key) = dead_key {
+            let _ = self.to_unicode(dead_key, &mut buf);
+        }
+        let rc = self.to_unicode(stroke, &mut buf);
+        if rc < 0 {
+            // A dead key in the target layout: flush it so it does not leak into the next call.
+            let _ = self.to_unicode(stroke, &mut buf);
+            return None;
+        }
+        let text = String::from_utf16_lossy(&buf[..usize::try_from(rc).ok()?]);
+        (!text.is_empty() && !text.chars().any(char::is_control)).then_some(text)
+    }
+}
+
+/// Claims the autoconvert revert for a plain physical Backspace keydown.
+#[cfg(windows)]
+#[must_use]
+pub fn claim_backspace_revert(kb: &KBDLLHOOKSTRUCT, vk: u32) -> bool {
+    if vk != u32::from(VK_BACK.0) || kb.flags.contains(LLKHF_INJECTED) || mods_ctrl_or_alt_down() {
+        return false;
+    }
+    let claimed = request_autoconvert_revert();
+    if claimed {
+        record_event(&InputEvent::Backspace);
+    }
+    claimed
 }
 
+/// `dwExtraInfo` of the input this program injects, to tell it from input sent by others.
+#[cfg(windows)]
+pub const OWN_INPUT_TAG: usize = 0x5253_5743;
+
 #[cfg(windows)]
 pub fn record_keydown(kb: &KBDLLHOOKSTRUCT, vk: u32) -> Option<String> {
+    record_keydown_with(kb, vk, None)
+}
+
+/// Records a key-down the hook held back, as typed with the modifiers `held` at the time.
+#[cfg(windows)]
+pub fn record_held_keydown(kb: &KBDLLHOOKSTRUCT, vk: u32, held: HeldModifiers) -> Option<String> {
+    record_keydown_with(kb, vk, Some(held))
+}
+
+#[cfg(windows)]
+fn record_keydown_with(
+    kb: &KBDLLHOOKSTRUCT,
+    vk: u32,
+    held: Option<HeldModifiers>,
+) -> Option<String> {
     if kb.flags.contains(LLKHF_INJECTED) {
+        if kb.dwExtraInfo != OWN_INPUT_TAG {
+            with_journal_mut(InputJournal::forget_background_windows);
+        }
         return None;
     }
 
     let vk_u16 = u16::try_from(vk).ok()?;
     let vk = VIRTUAL_KEY(vk_u16);
-
-    enum JournalAction {
-        Clear,
-        Backspace,
-        PushText {
-            text: String,
-            layout: LayoutTag,
-            origin: RunOrigin,
-        },
+    let mods = held.unwrap_or_else(HeldModifiers::now);
+    // Alt+Tab only moves the focus, which `observe_window` follows on the next key.
+    if vk == VK_TAB && mods.alt() {
+        return None;
     }
 
-    let mut action: Option<JournalAction> = None;
+    let mut event: Option<InputEvent> = None;
     let mut output: Option<String> = None;
 
     match vk {
-        VK_ESCAPE | VK_DELETE | VK_INSERT | VK_LEFT | VK_RIGHT | VK_UP | VK_DOWN | VK_HOME
-        | VK_END | VK_PRIOR | VK_NEXT => action = Some(JournalAction::Clear),
-        VK_BACK => action = Some(JournalAction::Backspace),
+        // Shift+arrows select text that the next key replaces.
+        VK_LEFT | VK_RIGHT if mods.shift() => {
+            event = Some(InputEvent::Navigate(Navigation::Unknown))
+        }
+        VK_LEFT => event = Some(InputEvent::Navigate(Navigation::CaretLeft)),
+        VK_RIGHT => event = Some(InputEvent::Navigate(Navigation::CaretRight)),
+        VK_DELETE => event = Some(InputEvent::Delete),
+        VK_ESCAPE | VK_INSERT | VK_UP | VK_DOWN | VK_HOME | VK_END | VK_PRIOR | VK_NEXT => {
+            event = Some(InputEvent::Navigate(Navigation::Unknown));
+        }
+        VK_BACK => event = Some(InputEvent::Backspace),
         VK_RETURN => {
             let layout = current_foreground_layout_tag();
             output = Some("\n".to_string());
-            action = Some(JournalAction::PushText {
+            event = Some(InputEvent::Text {
                 text: "\n".to_string(),
                 layout,
+                key: CharKey::Unknown,
                 origin: RunOrigin::Physical,
             });
         }
         VK_TAB => {
             let layout = current_foreground_layout_tag();
             output = Some("\t".to_string());
-            action = Some(JournalAction::PushText {
+            event = Some(InputEvent::Text {
                 text: "\t".to_string(),
                 layout,
+                key: CharKey::Unknown,
                 origin: RunOrigin::Physical,
             });
         }
         _ => {}
     }
 
-    if mods_ctrl_or_alt_down() {
-        action = Some(JournalAction::Clear);
-    }
-
-    if action.is_none() {
-        let decoded = decode_typed_text(kb, vk)?;
-        output = Some(decoded.text.clone());
-        action = Some(JournalAction::PushText {
-            text: decoded.text,
-            layout: decoded.layout,
-            origin: RunOrigin::Physical,
+    if mods.ctrl() && !mods.alt() && matches!(vk, VK_BACK | VK_DELETE) {
+        event = Some(if vk == VK_BACK {
+            InputEvent::DeleteWordBackward
+        } else {
+            InputEvent::DeleteWordForward
         });
+    } else if (mods.ctrl() || mods.alt()) && !(mods.altgr() && event.is_none()) {
+        event = Some(InputEvent::Navigate(Navigation::Unknown));
     }
 
-    with_journal_mut(|j| {
-        j.invalidate_if_foreground_changed();
-        if let Some(action) = action {
-            match action {
-                JournalAction::Clear => j.clear(),
-                JournalAction::Backspace => j.backspace(),
-                JournalAction::PushText {
-                    text,
-                    layout,
-                    origin,
-                } => {
-                    if text.chars().any(char::is_alphanumeric) {
-                        j.last_token_autoconverted = false;
-                    }
-                    j.push_text_internal(&text, layout, origin);
+    let event = match event {
+        Some(event) => event,
+        None => match decode_typed_text(kb, vk, mods) {
+            Some(decoded) => {
+                output = Some(decoded.text.clone());
+                InputEvent::Text {
+                    text: decoded.text,
+                    layout: decoded.layout,
+                    key: decoded.key,
+                    origin: RunOrigin::Physical,
                 }
             }
-        }
+            // An AltGr chord that types nothing is a Ctrl+Alt shortcut, unless it was a dead key.
+            None if mods.altgr() && !dead_key_pending() => {
+                InputEvent::Navigate(Navigation::Unknown)
+            }
+            None => return None,
+        },
+    };
+
+    let (entered, suppressed) = with_journal_mut(|j| {
+        let entered = observe_focus(j);
+        let _ = j.expire_if_idle();
+        j.apply(&event);
+        (entered, j.is_suppressed())
     });
+    if let Some(focus) = entered {
+        record_event(&InputEvent::FocusChanged(focus));
+    }
+    if suppressed {
+        if let InputEvent::Text { mut text, .. } = event {
+            text.zeroize();
+        }
+        if let Some(mut output) = output {
+            output.zeroize();
+        }
+        return None;
+    }
+    record_event(&event);
 
     output
 }
 
+/// Receives the events the hook records, such as a session recorder.
+#[cfg(windows)]
+static EVENT_RECORDER: OnceLock<fn(&InputEvent)> = OnceLock::new();
+
+/// Sets where the events recorded from the hook go. Only the first call has an effect.
+#[cfg(windows)]
+pub fn set_event_recorder(recorder: fn(&InputEvent)) {
+    let _ = EVENT_RECORDER.set(recorder);
+}
+
+#[cfg(windows)]
+fn record_event(event: &InputEvent) {
+    if let Some(recorder) = EVENT_RECORDER.get() {
+        recorder(event);
+    }
+}
+
+/// Follows the focus of `journal` and returns the focus it entered, if it changed.
+#[cfg(windows)]
+fn observe_focus(journal: &mut InputJournal) -> Option<Option<Focus>> {
+    let before = journal.focus();
+    journal.observe_window();
+    (journal.focus() != before).then(|| journal.focus())
+}
+
+/// Observes the focused window, so that a change no key has reported yet is seen by the next
+/// pipeline step, see [`InputJournal::focus_visit`].
+#[cfg(windows)]
+pub fn observe_focus_change() {
+    if let Some(focus) = with_journal_mut(observe_focus) {
+        record_event(&InputEvent::FocusChanged(focus));
+    }
+}
+
+#[cfg(test)]
 #[must_use]
 pub fn take_last_layout_run_with_suffix() -> Option<(InputRun, Vec<InputRun>)> {
-    with_journal_mut(|j| j.take_last_layout_run_with_suffix())
+    with_journal_mut(InputJournal::take_last_layout_run_with_suffix)
+}
+
+/// Up to `limit` words at the end of the journal, oldest first, not crossing a line break.
+#[cfg(test)]
+#[must_use]
+pub fn recent_text_runs(limit: usize) -> Vec<InputRun> {
+    with_journal(|j| j.recent_text_runs(limit))
 }
 
+#[cfg(test)]
 #[must_use]
-pub fn take_last_layout_sequence_with_suffix() -> Option<(Vec<InputRun>, Vec<InputRun>)> {
-    with_journal_mut(|j| j.take_last_layout_sequence_with_suffix())
+pub fn take_last_phrase_with_suffix() -> Option<(Vec<InputRun>, Vec<InputRun>)> {
+    with_journal_mut(InputJournal::take_last_phrase_with_suffix)
 }
 
 #[cfg(test)]
 pub fn push_text(s: &str) {
-    with_journal_mut(|j| j.push_text_internal(s, LayoutTag::Unknown, RunOrigin::Programmatic));
+    with_journal_mut(|j| j.push_text(s, LayoutTag::Unknown, RunOrigin::Programmatic));
 }
 
+#[cfg(test)]
 pub fn push_run(run: InputRun) {
     with_journal_mut(|j| j.push_run(run));
 }
 
-pub fn push_runs(runs: impl IntoIterator<Item = InputRun>) {
-    with_journal_mut(|j| j.push_runs(runs));
-}
-
-#[cfg(any(test, windows))]
+#[cfg(test)]
 pub fn push_text_with_meta(text: &str, layout: LayoutTag, origin: RunOrigin) {
-    with_journal_mut(|j| j.push_text_internal(text, layout, origin));
+    with_journal_mut(|j| j.push_text(text, layout, origin));
 }
 
 #[cfg(test)]
 pub fn test_backspace() {
-    with_journal_mut(|j| j.backspace());
+    with_journal_mut(InputJournal::backspace);
 }
 
 #[cfg(test)]
@@ -583,31 +2444,60 @@ pub fn runs_snapshot() -> Vec<InputRun> {
     with_journal(|j| j.runs.iter().cloned().collect())
 }
 
-#[cfg(any(test, windows))]
+/// Removes `expected` from the end of the journal.
+///
+/// Returns `false` and leaves the journal untouched when it does not end with `expected`.
+#[cfg(test)]
+#[must_use]
+pub fn take_tail_if_matches(expected: &str) -> bool {
+    with_journal_mut(|j| j.take_tail_if_matches(expected))
+}
+
+#[cfg(test)]
 pub fn invalidate() {
-    with_journal_mut(|j| j.clear());
+    with_journal_mut(InputJournal::clear);
+}
+
+/// See [`InputJournal::untracked_input`].
+#[cfg(windows)]
+pub fn untracked_input() {
+    with_journal_mut(InputJournal::untracked_input);
+    record_event(&InputEvent::Untracked);
+}
+
+/// See [`InputJournal::set_blocked_processes`].
+#[cfg(any(test, windows))]
+pub fn set_blocked_processes(names: &[String]) {
+    with_journal_mut(|j| j.set_blocked_processes(names));
 }
 
+/// See [`InputJournal::recheck_privacy`].
+#[cfg(windows)]
+pub fn recheck_privacy() {
+    with_journal_mut(InputJournal::recheck_privacy);
+}
+
+/// Whether typed text is currently not recorded, see [`InputJournal::observe_window`].
 #[cfg(any(test, windows))]
 #[must_use]
-pub fn last_char_triggers_autoconvert() -> bool {
-    with_journal(|j| {
-        let Some(last) = j.last_char() else {
-            return false;
-        };
+pub fn input_suppressed() -> bool {
+    with_journal(InputJournal::is_suppressed)
+}
 
-        if matches!(last, '.' | ',' | '!' | '?' | ';' | ':') {
-            return j
-                .prev_char_before_last()
-                .is_some_and(|prev| !prev.is_whitespace());
-        }
+/// See [`InputJournal::set_idle_expiry`].
+#[cfg(any(test, windows))]
+pub fn set_idle_expiry(expiry: Duration) {
+    with_journal_mut(|j| j.set_idle_expiry(expiry));
+}
 
-        if last.is_whitespace() {
-            return j
-                .prev_char_before_last()
-                .is_some_and(|prev| !prev.is_whitespace());
-        }
+/// Sets the punctuation and symbols that trigger autoconvert; whitespace always does.
+#[cfg(any(test, windows))]
+pub fn set_autoconvert_trigger_chars(chars: &str) {
+    with_journal_mut(|j| j.set_trigger_chars(chars));
+}
 
-        false
-    })
+#[cfg(any(test, windows))]
+#[must_use]
+pub fn last_char_triggers_autoconvert() -> bool {
+    with_journal(InputJournal::last_char_triggers_autoconvert)
 }
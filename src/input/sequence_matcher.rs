@@ -0,0 +1,200 @@
+//! Recognition of hotkey sequences from chords and their timestamps.
+//!
+//! The matcher knows nothing about the platform: the keyboard hook feeds it each chord with how
+//! it was pressed and when, and turns completed sequences into actions.
+
+use crate::input::hotkey_sequence::{HotkeyChord, HotkeyPress, HotkeySequence, HotkeyStep};
+
+/// Whether `input` is the chord `template` describes.
+///
+/// A template without side-specific modifiers matches either side.
+#[must_use]
+pub fn chord_matches(template: HotkeyChord, input: HotkeyChord) -> bool {
+    if template.mods != input.mods {
+        return false;
+    }
+    if template.key != input.key {
+        return false;
+    }
+    if template.mods_vks == 0 {
+        return true;
+    }
+    template.mods_vks == input.mods_vks
+}
+
+/// Whether `chord`, pressed as `press`, completes `step`.
+///
+/// A `Hold` press carries how long the key has been held; it matches holds up to that length.
+#[must_use]
+pub fn step_matches(step: HotkeyStep, chord: HotkeyChord, press: HotkeyPress) -> bool {
+    use HotkeyPress::{Down, Hold, Tap};
+
+    let press_matches = match (step.press, press) {
+        (Down, Down) | (Tap, Tap) => true,
+        (Hold(min_ms), Hold(held_ms)) => held_ms >= min_ms,
+        _ => false,
+    };
+    press_matches && chord_matches(step.chord, chord)
+}
+
+/// What a chord did to the bound sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceEvent<A> {
+    /// No sequence took the chord.
+    Unmatched,
+    /// A sequence matched the chord and waits for its next step.
+    Advanced,
+    /// The sequence bound to the action matched its last step.
+    Completed(A),
+}
+
+impl<A> SequenceEvent<A> {
+    /// Whether a sequence took the chord, so it should not reach other applications.
+    #[must_use]
+    pub const fn is_consumed(&self) -> bool {
+        !matches!(self, Self::Unmatched)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Binding<A> {
+    action: A,
+    sequence: HotkeySequence,
+    /// For `n` steps matched, `fallback[n - 1]` is the longest shorter match they end with: the
+    /// longest proper prefix of the first `n` steps that is also their suffix.
+    fallback: Vec<usize>,
+    /// Steps matched so far.
+    matched: usize,
+    last_tick_ms: u64,
+}
+
+impl<A> Binding<A> {
+    fn new(action: A, sequence: HotkeySequence) -> Self {
+        Self {
+            fallback: fallback_table(&sequence),
+            action,
+            sequence,
+            matched: 0,
+            last_tick_ms: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.matched = 0;
+        self.last_tick_ms = 0;
+    }
+
+    /// Advances by `chord` and returns whether the sequence took it.
+    ///
+    /// A chord that breaks a partial match continues the longest shorter match the chords so
+    /// far end with, as in KMP string search, so `A, A, B` matches `A A A B`. When no such match
+    /// takes it, the progress resets; so does a pause longer than the gap allowed before the
+    /// next step.
+    fn feed(&mut self, chord: HotkeyChord, press: HotkeyPress, now_ms: u64) -> bool {
+        if self.matched > 0 {
+            let elapsed_ms = now_ms.saturating_sub(self.last_tick_ms);
+            if elapsed_ms > u64::from(self.sequence.gap_before_ms(self.matched)) {
+                self.reset();
+            }
+        }
+
+        let mut matched = self.matched;
+        loop {
+            let next = self.sequence.steps[matched];
+            if press.is_down() && !next.press.is_down() && chord_matches(next.chord, chord) {
+                // The key-down of a tap or hold step: its release or the hold decides.
+                return false;
+            }
+            if step_matches(next, chord, press) {
+                self.matched = matched + 1;
+                self.last_tick_ms = now_ms;
+                return true;
+            }
+            if matched == 0 {
+                break;
+            }
+            matched = self.fallback[matched - 1];
+        }
+
+        self.reset();
+        false
+    }
+
+    fn is_complete(&self) -> bool {
+        self.matched == self.sequence.steps.len()
+    }
+}
+
+/// KMP failure function of the steps of `sequence`, see [`Binding::fallback`].
+fn fallback_table(sequence: &HotkeySequence) -> Vec<usize> {
+    let steps = &sequence.steps;
+    let mut fallback = vec![0; steps.len()];
+    let mut len = 0;
+    for i in 1..steps.len() {
+        while len > 0 && steps[i] != steps[len] {
+            len = fallback[len - 1];
+        }
+        if steps[i] == steps[len] {
+            len += 1;
+        }
+        fallback[i] = len;
+    }
+    fallback
+}
+
+/// Tracks the progress of every bound sequence.
+///
+/// Bindings are tried in order and the first one that takes a chord stops the search, so a
+/// later binding keeps its progress through chords taken by an earlier one.
+#[derive(Debug, Clone)]
+pub struct SequenceMatcher<A> {
+    bindings: Vec<Binding<A>>,
+}
+
+impl<A> Default for SequenceMatcher<A> {
+    fn default() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+}
+
+impl<A: Copy> SequenceMatcher<A> {
+    /// Matcher for `bindings`, in the order they are tried.
+    pub fn new(bindings: impl IntoIterator<Item = (A, HotkeySequence)>) -> Self {
+        Self {
+            bindings: bindings
+                .into_iter()
+                .map(|(action, sequence)| Binding::new(action, sequence))
+                .collect(),
+        }
+    }
+
+    /// Bound sequences, in the order they are tried.
+    pub fn sequences(&self) -> impl Iterator<Item = &HotkeySequence> {
+        self.bindings.iter().map(|binding| &binding.sequence)
+    }
+
+    /// Feeds `chord`, pressed as `press` at `now_ms`.
+    pub fn feed(
+        &mut self,
+        chord: HotkeyChord,
+        press: HotkeyPress,
+        now_ms: u64,
+    ) -> SequenceEvent<A> {
+        let Some(binding) = self
+            .bindings
+            .iter_mut()
+            .find_map(|binding| binding.feed(chord, press, now_ms).then_some(binding))
+        else {
+            return SequenceEvent::Unmatched;
+        };
+
+        if binding.is_complete() {
+            binding.reset();
+            SequenceEvent::Completed(binding.action)
+        } else {
+            SequenceEvent::Advanced
+        }
+    }
+}
@@ -1,6 +1,5 @@
-#[cfg(test)]
-pub use crate::input::ring_buffer::push_text;
 pub use crate::input::ring_buffer::{
-    InputRun, LayoutTag, RunKind, RunOrigin, mark_last_token_autoconverted, push_run, push_runs,
-    push_text_with_meta, take_last_layout_run_with_suffix, take_last_layout_sequence_with_suffix,
+    DEFAULT_AUTOCONVERT_TRIGGER_CHARS, InputRun, LayoutTag, observe_focus_change,
+    set_autoconvert_trigger_chars, set_blocked_processes, set_idle_expiry, with_journal_detached,
+    with_journal_mut,
 };
@@ -9,7 +9,10 @@
 // If we compile the Windows app modules here, `cargo check` / `cargo clippy --all-targets`
 // will build the library target first and hit `dead_code` cascades under `-D warnings`.
 //
-// The only code that belongs in the shared library right now is `rust-switcher-core`.
+// The shared library holds the platform-independent parts: `rust-switcher-core`, the text
+// domain modules (decision engine, pipeline, sessions) and the input modules (journal, hotkey
+// model, key codes, sequence matcher). The Windows app includes the same files by path, so they
+// must not depend on Win32 outside `cfg(windows)` items.
 
 pub use rust_switcher_core as core;
 
@@ -20,16 +23,56 @@ pub mod domain {
         pub mod mapping {
             pub use rust_switcher_core::text::mapping::*;
         }
+
+        // Alternatives for repeated Convert presses are pure text transforms.
+        pub mod alternatives;
+
+        // The decision engine is platform independent and shared with the Windows app.
+        pub mod autoconvert;
+
+        // Undo history only depends on journal types, so it is shared and unit tested here.
+        pub mod conversion_history;
+
+        // Mixed-layout word repair works on journal runs only.
+        pub mod mixed_word;
+
+        // Phrase conversion runs on the runs the journal takes for a phrase.
+        pub mod phrase;
+
+        // The event reducer drives the journal and the decision engine without any platform API.
+        pub mod pipeline;
+
+        // Session recording and replay build on the pipeline, for bug reports and golden tests.
+        pub mod session;
+
+        // Corpus evaluation is a development tool and is not compiled into the Windows app.
+        pub mod autoconvert_eval;
     }
 }
 
 #[path = "input/ring_buffer.rs"]
 pub mod ring_buffer;
 
+// Events fed to the journal, kept below the text domain that also consumes them.
+#[path = "input/input_event.rs"]
+pub mod input_event;
+
+// The hotkey model and its text syntax are shared so the parser is tested on every platform.
+#[path = "input/hotkey_sequence.rs"]
+pub mod hotkey_sequence;
+
+// Key codes convert between Win32, evdev and X11, and name keys in the config.
+#[path = "input/key_code.rs"]
+pub mod key_code;
+
+// The sequence matcher works on chords and timestamps, so its timing rules are unit tested here.
+#[path = "input/sequence_matcher.rs"]
+pub mod sequence_matcher;
+
 // Compatibility shim for unit tests that still refer to
 // `crate::input::ring_buffer::*`.
 pub mod input {
-    pub use super::ring_buffer;
+    pub use super::{hotkey_sequence, input_event, key_code, ring_buffer, sequence_matcher};
 }
 
 #[cfg(test)]
@@ -360,6 +360,28 @@ fn create_hotkey_rows(
         w!("Switch keyboard layout:"),
         Some(ControlId::HotkeySwitchLayout.hmenu()),
     )?;
+    hy += 28;
+
+    state.hotkeys.undo_conversion = create_hotkey_row(
+        hwnd,
+        g.hx,
+        hy,
+        g.w_label,
+        g.w_edit,
+        w!("Undo conversion:"),
+        Some(ControlId::HotkeyUndoConversion.hmenu()),
+    )?;
+    hy += 28;
+
+    state.hotkeys.last_phrase = create_hotkey_row(
+        hwnd,
+        g.hx,
+        hy,
+        g.w_label,
+        g.w_edit,
+        w!("Convert last phrase:"),
+        Some(ControlId::HotkeyLastPhrase.hmenu()),
+    )?;
 
     Ok(())
 }
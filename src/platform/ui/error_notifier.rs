@@ -5,6 +5,9 @@ use windows::Win32::{
 
 pub const WM_APP_ERROR: u32 = WM_APP + 101;
 pub const WM_APP_AUTOCONVERT: u32 = WM_APP + 102;
+pub const WM_APP_AUTOCONVERT_REVERT: u32 = WM_APP + 104;
+/// `wparam` is 1 when recording of typed text is suppressed and 0 when it resumes.
+pub const WM_APP_PRIVACY: u32 = WM_APP + 105;
 
 use crate::app::{AppState, UiError};
 
@@ -17,7 +17,7 @@ mod tray_dispatch;
 mod visuals;
 mod window;
 mod winutil;
-use std::sync::OnceLock;
+use std::{sync::OnceLock, time::Duration};
 
 pub(crate) use hotkey_format::{format_hotkey, format_hotkey_sequence};
 use windows::{
@@ -50,7 +50,12 @@ use windows::Win32::UI::WindowsAndMessaging::{WM_CTLCOLOREDIT, WM_ERASEBKGND};
 use crate::{
     app::AppState,
     config,
-    domain::text::{last_word::autoconvert_last_word, switch_keyboard_layout},
+    domain::text::{
+        autoconvert::ExceptionRules,
+        last_word::{InstalledLayouts, autoconvert_word_end, revert_autoconvert, run_hotkey},
+        pipeline::{AutoconvertNotice, HotkeyCommand},
+        session, switch_keyboard_layout,
+    },
     input::hotkeys::{HotkeyAction, action_from_id},
     platform::{
         ui::{
@@ -121,33 +126,47 @@ fn apply_config_to_ui(state: &mut AppState, cfg: &config::Config) -> windows::co
     state.hotkey_sequence_values = crate::app::HotkeySequenceValues::from_config(cfg);
 
     let last_word_text = if cfg.hotkey_convert_last_word_sequence.is_some() {
-        format_hotkey_sequence(cfg.hotkey_convert_last_word_sequence)
+        format_hotkey_sequence(cfg.hotkey_convert_last_word_sequence.as_ref())
     } else {
         format_hotkey(cfg.hotkey_convert_last_word)
     };
     set_hwnd_text(state.hotkeys.last_word, &last_word_text)?;
 
     let pause_text = if cfg.hotkey_pause_sequence.is_some() {
-        format_hotkey_sequence(cfg.hotkey_pause_sequence)
+        format_hotkey_sequence(cfg.hotkey_pause_sequence.as_ref())
     } else {
         format_hotkey(cfg.hotkey_pause)
     };
     set_hwnd_text(state.hotkeys.pause, &pause_text)?;
 
     let selection_text = if cfg.hotkey_convert_selection_sequence.is_some() {
-        format_hotkey_sequence(cfg.hotkey_convert_selection_sequence)
+        format_hotkey_sequence(cfg.hotkey_convert_selection_sequence.as_ref())
     } else {
         format_hotkey(cfg.hotkey_convert_selection)
     };
     set_hwnd_text(state.hotkeys.selection, &selection_text)?;
 
     let switch_layout_text = if cfg.hotkey_switch_layout_sequence.is_some() {
-        format_hotkey_sequence(cfg.hotkey_switch_layout_sequence)
+        format_hotkey_sequence(cfg.hotkey_switch_layout_sequence.as_ref())
     } else {
         format_hotkey(cfg.hotkey_switch_layout)
     };
     set_hwnd_text(state.hotkeys.switch_layout, &switch_layout_text)?;
 
+    let undo_conversion_text = if cfg.hotkey_undo_conversion_sequence.is_some() {
+        format_hotkey_sequence(cfg.hotkey_undo_conversion_sequence.as_ref())
+    } else {
+        format_hotkey(cfg.hotkey_undo_conversion)
+    };
+    set_hwnd_text(state.hotkeys.undo_conversion, &undo_conversion_text)?;
+
+    let last_phrase_text = if cfg.hotkey_convert_last_phrase_sequence.is_some() {
+        format_hotkey_sequence(cfg.hotkey_convert_last_phrase_sequence.as_ref())
+    } else {
+        format_hotkey(cfg.hotkey_convert_last_phrase)
+    };
+    set_hwnd_text(state.hotkeys.last_phrase, &last_phrase_text)?;
+
     Ok(())
 }
 
@@ -157,31 +176,44 @@ fn read_ui_to_config(state: &AppState, mut cfg: config::Config) -> config::Confi
     cfg.start_minimized = helpers::get_checkbox(state.checkboxes.start_minimized);
     cfg.theme_dark = helpers::get_checkbox(state.checkboxes.theme_dark);
 
-    cfg.hotkey_convert_last_word_sequence = state.hotkey_sequence_values.last_word;
-    cfg.hotkey_pause_sequence = state.hotkey_sequence_values.pause;
-    cfg.hotkey_convert_selection_sequence = state.hotkey_sequence_values.selection;
-    cfg.hotkey_switch_layout_sequence = state.hotkey_sequence_values.switch_layout;
+    cfg.hotkey_convert_last_word_sequence = state.hotkey_sequence_values.last_word.clone();
+    cfg.hotkey_pause_sequence = state.hotkey_sequence_values.pause.clone();
+    cfg.hotkey_convert_selection_sequence = state.hotkey_sequence_values.selection.clone();
+    cfg.hotkey_switch_layout_sequence = state.hotkey_sequence_values.switch_layout.clone();
+    cfg.hotkey_undo_conversion_sequence = state.hotkey_sequence_values.undo_conversion.clone();
+    cfg.hotkey_convert_last_phrase_sequence = state.hotkey_sequence_values.last_phrase.clone();
 
     fn hk_or_none_if_double(
-        seq: Option<config::HotkeySequence>,
+        seq: Option<&config::HotkeySequence>,
         hk: Option<config::Hotkey>,
     ) -> Option<config::Hotkey> {
         match seq {
-            Some(s) if s.second.is_some() => None,
+            Some(s) if !s.is_single() => None,
             _ => hk,
         }
     }
 
     cfg.hotkey_convert_last_word = hk_or_none_if_double(
-        cfg.hotkey_convert_last_word_sequence,
+        cfg.hotkey_convert_last_word_sequence.as_ref(),
         state.hotkey_values.last_word,
     );
-    cfg.hotkey_pause = hk_or_none_if_double(cfg.hotkey_pause_sequence, state.hotkey_values.pause);
+    cfg.hotkey_pause = hk_or_none_if_double(
+        cfg.hotkey_pause_sequence.as_ref(),
+        state.hotkey_values.pause,
+    );
     cfg.hotkey_convert_selection = hk_or_none_if_double(
-        cfg.hotkey_convert_selection_sequence,
+        cfg.hotkey_convert_selection_sequence.as_ref(),
         state.hotkey_values.selection,
     );
-    cfg.hotkey_switch_layout = match cfg.hotkey_switch_layout_sequence {
+    cfg.hotkey_undo_conversion = hk_or_none_if_double(
+        cfg.hotkey_undo_conversion_sequence.as_ref(),
+        state.hotkey_values.undo_conversion,
+    );
+    cfg.hotkey_convert_last_phrase = hk_or_none_if_double(
+        cfg.hotkey_convert_last_phrase_sequence.as_ref(),
+        state.hotkey_values.last_phrase,
+    );
+    cfg.hotkey_switch_layout = match &cfg.hotkey_switch_layout_sequence {
         Some(_) => None,
         None => state.hotkey_values.switch_layout,
     };
@@ -194,16 +226,42 @@ fn apply_config_runtime(
     state: &mut AppState,
     cfg: &config::Config,
 ) -> windows::core::Result<()> {
-    state.autoconvert_enabled = false;
+    state.typing.set_autoconvert_enabled(false);
+
+    let settings = state.typing.settings_mut();
+    settings.exceptions =
+        ExceptionRules::compile(&cfg.autoconvert_exceptions).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "autoconvert exceptions ignored");
+            ExceptionRules::default()
+        });
+    settings.context_weight = cfg.autoconvert_context_weight;
+    settings.max_pause_in_word =
+        Duration::from_millis(u64::from(cfg.autoconvert_max_pause_in_word_ms));
+
+    state
+        .typing
+        .set_pause_after_reverts(cfg.autoconvert_pause_after_reverts);
+
+    crate::input_journal::set_autoconvert_trigger_chars(&cfg.autoconvert_trigger_chars);
+    crate::input_journal::set_idle_expiry(Duration::from_millis(u64::from(
+        cfg.journal_idle_expiry_ms,
+    )));
+    crate::input_journal::set_blocked_processes(&cfg.privacy_blocked_processes);
+    apply_session_recording(cfg);
+
+    state.typing.set_convert_repeat(
+        cfg.convert_repeat,
+        Duration::from_millis(u64::from(cfg.convert_repeat_window_ms)),
+    );
+    state.selection_repeat = None;
 
-    state.active_hotkey_sequences = crate::app::HotkeySequenceValues::from_config(cfg);
+    state.hotkey_matcher =
+        keyboard::sequence::hotkey_matcher(&crate::app::HotkeySequenceValues::from_config(cfg));
 
     state.runtime_chord_capture = crate::app::RuntimeChordCapture::default();
-    state.hotkey_sequence_progress = crate::app::HotkeySequenceProgress::default();
+    state.pending_key_press = None;
 
-    state.active_switch_layout_sequence = cfg.hotkey_switch_layout_sequence;
-    state.switch_layout_waiting_second = false;
-    state.switch_layout_first_tick_ms = 0;
+    state.active_switch_layout_sequence = cfg.hotkey_switch_layout_sequence.clone();
 
     ui_try!(
         hwnd,
@@ -292,15 +350,21 @@ fn load_config_or_default(hwnd: HWND, state: &mut AppState) -> config::Config {
             );
         })
         .ok()
-        .and_then(|cfg| match cfg.validate_hotkey_sequences() {
-            Ok(()) => Some(cfg),
-            Err(msg) => {
-                let user_text = msg.clone();
-                let source = io_to_win(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg));
-                crate::platform::ui::error_notifier::push(
-                    hwnd, state, T_CONFIG, &user_text, &source,
-                );
-                None
+        .and_then(|cfg| {
+            match cfg
+                .validate_hotkey_sequences()
+                .and_then(|()| cfg.validate_autoconvert_exceptions())
+            {
+                Ok(()) => Some(cfg),
+                Err(msg) => {
+                    let user_text = msg.clone();
+                    let source =
+                        io_to_win(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg));
+                    crate::platform::ui::error_notifier::push(
+                        hwnd, state, T_CONFIG, &user_text, &source,
+                    );
+                    None
+                }
             }
         })
         .unwrap_or_default()
@@ -308,6 +372,7 @@ fn load_config_or_default(hwnd: HWND, state: &mut AppState) -> config::Config {
 
 fn on_create(hwnd: HWND) -> LRESULT {
     let mut state = Box::new(AppState::default());
+    state.typing.set_layouts(InstalledLayouts);
 
     // ВАЖНО: state должен быть доступен через get_state/with_state_mut_do
     // уже во время создания контролов и их первого paint.
@@ -324,7 +389,8 @@ fn on_create(hwnd: HWND) -> LRESULT {
     let cfg = load_config_or_default(hwnd, state.as_mut());
 
     state.hotkey_values = crate::app::HotkeyValues::from_config(&cfg);
-    state.active_hotkey_sequences = crate::app::HotkeySequenceValues::from_config(&cfg);
+    state.hotkey_matcher =
+        keyboard::sequence::hotkey_matcher(&crate::app::HotkeySequenceValues::from_config(&cfg));
 
     startup_or_return0!(
         hwnd,
@@ -345,6 +411,7 @@ fn on_create(hwnd: HWND) -> LRESULT {
         apply_config_runtime(hwnd, state.as_mut(), &cfg)
     );
 
+    crate::domain::text::autoconvert::warm_up_language_detector();
     keyboard::install(hwnd, state.as_mut());
     mouse::install();
 
@@ -486,14 +553,29 @@ pub extern "system" fn wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
             }
 
             with_state_mut_do(hwnd, |state| {
-                if state.autoconvert_enabled {
-                    autoconvert_last_word(state);
+                if state.typing.autoconvert_enabled() {
+                    autoconvert_word_end(hwnd, state);
                 }
             });
 
             LRESULT(0)
         }
 
+        crate::platform::ui::error_notifier::WM_APP_AUTOCONVERT_REVERT => {
+            with_state_mut_do(hwnd, |state| revert_autoconvert(hwnd, state));
+
+            LRESULT(0)
+        }
+
+        crate::platform::ui::error_notifier::WM_APP_PRIVACY => {
+            if let Err(e) =
+                crate::platform::win::tray::set_private_input_indicator(hwnd, wparam.0 != 0)
+            {
+                tracing::warn!(error = ?e, "set_private_input_indicator failed");
+            }
+            LRESULT(0)
+        }
+
         WM_APP_TRAY => tray_dispatch::handle_tray_message(hwnd, wparam, lparam),
 
         _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
@@ -670,16 +752,31 @@ pub fn hotkey_id_from_wparam(wparam: WPARAM) -> i32 {
     wparam.0 as i32
 }
 
-fn handle_pause_toggle(hwnd: HWND, state: &mut AppState) {
-    let enabled = !state.autoconvert_enabled;
-    set_autoconvert_enabled_from_tray(hwnd, state, enabled, true);
+/// Starts or stops the session recording to match `cfg.record_session`.
+fn apply_session_recording(cfg: &config::Config) {
+    if !cfg.record_session {
+        session::stop_recording();
+        return;
+    }
+    let settings = session::SessionSettings {
+        context_weight: cfg.autoconvert_context_weight,
+        max_pause_in_word_ms: u64::from(cfg.autoconvert_max_pause_in_word_ms),
+        trigger_chars: cfg.autoconvert_trigger_chars.clone(),
+        exceptions: cfg.autoconvert_exceptions.clone(),
+        pause_after_reverts: cfg.autoconvert_pause_after_reverts,
+    };
+    let started =
+        config::session_path().and_then(|path| session::start_recording(&path, &settings));
+    if let Err(e) = started {
+        tracing::warn!(error = %e, "session recording not started");
+    }
 }
 
-fn handle_convert_smart(state: &mut AppState) {
+fn handle_convert_smart(hwnd: HWND, state: &mut AppState) {
     if crate::conversion::convert_selection_if_any(state) {
         return;
     }
-    crate::conversion::convert_last_sequence(state);
+    run_hotkey(hwnd, state, HotkeyCommand::ConvertLastWord);
 }
 
 #[cfg(test)]
@@ -707,13 +804,17 @@ fn on_hotkey(hwnd: HWND, wparam: WPARAM) -> LRESULT {
     with_state_mut(hwnd, |state| match action {
         HotkeyAction::PauseToggle => {
             tracing::warn!(msg = "autoconvert_toggle", source = "hotkey_pause_toggle");
-            handle_pause_toggle(hwnd, state)
+            run_hotkey(hwnd, state, HotkeyCommand::ToggleAutoconvert)
         }
-        HotkeyAction::ConvertLastWord => handle_convert_smart(state),
+        HotkeyAction::ConvertLastWord => handle_convert_smart(hwnd, state),
         HotkeyAction::ConvertSelection => crate::conversion::convert_selection(state),
         HotkeyAction::SwitchLayout => {
             let _ = switch_keyboard_layout();
         }
+        HotkeyAction::UndoConversion => run_hotkey(hwnd, state, HotkeyCommand::UndoConversion),
+        HotkeyAction::ConvertLastPhrase => {
+            run_hotkey(hwnd, state, HotkeyCommand::ConvertLastPhrase)
+        }
     });
 
     LRESULT(0)
@@ -732,6 +833,8 @@ fn on_app_error(hwnd: HWND) -> LRESULT {
 
 fn on_timer(hwnd: HWND, wparam: WPARAM, _lparam: LPARAM) -> LRESULT {
     let _ = handle_tray_timer(hwnd, wparam);
+    let _ = keyboard::hold::handle_hold_timer(hwnd, wparam.0);
+    let _ = keyboard::handle_privacy_timer(wparam.0);
     #[cfg(debug_assertions)]
     let _ = handle_timer(hwnd, wparam.0);
     LRESULT(0)
@@ -748,18 +851,85 @@ fn toggle_window_visibility_from_tray(hwnd: HWND) {
     }
 }
 
+/// Persists `word` in the never-convert list and refreshes the runtime rules.
+fn persist_never_convert_word(state: &mut AppState, word: &str) -> std::io::Result<()> {
+    let cfg = config::add_never_convert_word(word)?;
+    if let Ok(rules) = ExceptionRules::compile(&cfg.autoconvert_exceptions) {
+        state.typing.settings_mut().exceptions = rules;
+    }
+    Ok(())
+}
+
+/// Adds the last autoconverted word to the persisted never-convert list.
+///
+/// The runtime rules are refreshed from the saved config, so the word is excluded immediately.
+pub(crate) fn never_convert_last_autoconverted(hwnd: HWND, state: &mut AppState) {
+    let Some(word) = state.typing.last_autoconverted_word().map(str::to_owned) else {
+        return;
+    };
+
+    match persist_never_convert_word(state, &word) {
+        Ok(()) => {
+            state.typing.forget_last_autoconverted_word();
+            let body = format!("\"{word}\" will not be autoconverted.");
+            if let Err(e) = crate::platform::win::tray::balloon_info(hwnd, "Rust Switcher", &body) {
+                tracing::warn!(error = ?e, "tray balloon failed");
+            }
+        }
+        Err(e) => {
+            crate::platform::ui::error_notifier::push(
+                hwnd,
+                state,
+                T_CONFIG,
+                "Failed to save config",
+                &io_to_win(e),
+            );
+        }
+    }
+}
+
+/// Keeps a word reverted with Backspace from being autoconverted again.
+pub(crate) fn never_convert_reverted(hwnd: HWND, state: &mut AppState, word: &str) {
+    if let Err(e) = persist_never_convert_word(state, word) {
+        crate::platform::ui::error_notifier::push(
+            hwnd,
+            state,
+            T_CONFIG,
+            "Failed to save config",
+            &io_to_win(e),
+        );
+    }
+}
+
+/// Shows an autoconvert state change made by the typing pipeline.
+pub(crate) fn announce_autoconvert(hwnd: HWND, state: &AppState, notice: AutoconvertNotice) {
+    let enabled = match notice {
+        AutoconvertNotice::Enabled => true,
+        AutoconvertNotice::Disabled => false,
+        AutoconvertNotice::Paused => {
+            tracing::info!("autoconvert paused after consecutive reverts");
+            false
+        }
+    };
+    show_autoconvert_state(hwnd, state, enabled, true);
+}
+
 fn set_autoconvert_enabled_from_tray(
     hwnd: HWND,
     state: &mut crate::app::AppState,
     enabled: bool,
     show_balloon: bool,
 ) {
-    if state.autoconvert_enabled == enabled {
+    if state.typing.autoconvert_enabled() == enabled {
         return;
     }
 
-    state.autoconvert_enabled = enabled;
+    state.typing.set_autoconvert_enabled(enabled);
+    show_autoconvert_state(hwnd, state, enabled, show_balloon);
+}
 
+/// Updates the tray icon to the autoconvert state and optionally tells the user in a balloon.
+fn show_autoconvert_state(hwnd: HWND, state: &AppState, enabled: bool, show_balloon: bool) {
     if let Err(e) = crate::platform::win::tray::switch_tray_icon(hwnd, enabled) {
         tracing::warn!(error = ?e, "switch_tray_icon failed");
     }
@@ -769,7 +939,7 @@ fn set_autoconvert_enabled_from_tray(
     }
 
     let hotkey_text = if state.hotkey_sequence_values.pause.is_some() {
-        crate::platform::win::format_hotkey_sequence(state.hotkey_sequence_values.pause)
+        crate::platform::win::format_hotkey_sequence(state.hotkey_sequence_values.pause.as_ref())
     } else {
         crate::platform::win::format_hotkey(state.hotkey_values.pause)
     };
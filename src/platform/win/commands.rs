@@ -33,6 +33,8 @@ fn handle_hotkey_capture_focus(hwnd: HWND, id: i32, notif: u32) -> Option<LRESUL
         ControlId::HotkeyPause => crate::app::HotkeySlot::Pause,
         ControlId::HotkeySelection => crate::app::HotkeySlot::Selection,
         ControlId::HotkeySwitchLayout => crate::app::HotkeySlot::SwitchLayout,
+        ControlId::HotkeyUndoConversion => crate::app::HotkeySlot::UndoConversion,
+        ControlId::HotkeyLastPhrase => crate::app::HotkeySlot::LastPhrase,
         _ => return None,
     };
 
@@ -2,7 +2,13 @@ use windows::Win32::UI::Input::KeyboardAndMouse::{
     GetKeyNameTextW, MAPVK_VK_TO_VSC, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN, MapVirtualKeyW,
 };
 
-use crate::config;
+use crate::{
+    config,
+    input::hotkey_sequence::{
+        MODVK_LALT, MODVK_LCTRL, MODVK_LSHIFT, MODVK_LWIN, MODVK_RALT, MODVK_RCTRL, MODVK_RSHIFT,
+        MODVK_RWIN,
+    },
+};
 
 pub(crate) fn format_hotkey(hk: Option<config::Hotkey>) -> String {
     let Some(hk) = hk else {
@@ -14,20 +20,26 @@ pub(crate) fn format_hotkey(hk: Option<config::Hotkey>) -> String {
         // Legacy `RegisterHotKey` chords encode only generic modifier masks.
         // Side-specific modifier virtual-keys are sequence-only.
         mods_vks: 0,
-        vk: (hk.vk != 0).then_some(hk.vk),
+        key: hk.key,
     };
 
     format_hotkey_chord(chord)
 }
 
-pub(crate) fn format_hotkey_sequence(seq: Option<config::HotkeySequence>) -> String {
+pub(crate) fn format_hotkey_sequence(seq: Option<&config::HotkeySequence>) -> String {
     let Some(seq) = seq else {
         return "None".to_string();
     };
 
-    std::iter::once(seq.first)
-        .chain(seq.second)
-        .map(format_hotkey_chord)
+    seq.steps
+        .iter()
+        .map(|step| match step.press {
+            config::HotkeyPress::Down => format_hotkey_chord(step.chord),
+            config::HotkeyPress::Tap => format!("{} (tap)", format_hotkey_chord(step.chord)),
+            config::HotkeyPress::Hold(ms) => {
+                format!("{} (hold {ms} ms)", format_hotkey_chord(step.chord))
+            }
+        })
         .collect::<Vec<_>>()
         .join("; ")
 }
@@ -59,14 +71,14 @@ fn format_hotkey_chord(ch: config::HotkeyChord) -> String {
     }
 
     const MODS_VKS_ORDER: &[(u32, &str)] = &[
-        (config::MODVK_LCTRL, "LCtrl"),
-        (config::MODVK_RCTRL, "RCtrl"),
-        (config::MODVK_LALT, "LAlt"),
-        (config::MODVK_RALT, "RAlt"),
-        (config::MODVK_LSHIFT, "LShift"),
-        (config::MODVK_RSHIFT, "RShift"),
-        (config::MODVK_LWIN, "LWin"),
-        (config::MODVK_RWIN, "RWin"),
+        (MODVK_LCTRL, "LCtrl"),
+        (MODVK_RCTRL, "RCtrl"),
+        (MODVK_LALT, "LAlt"),
+        (MODVK_RALT, "RAlt"),
+        (MODVK_LSHIFT, "LShift"),
+        (MODVK_RSHIFT, "RShift"),
+        (MODVK_LWIN, "LWin"),
+        (MODVK_RWIN, "RWin"),
     ];
     const MODS_ORDER: &[(u32, &str)] = &[
         (MOD_CONTROL.0, "Ctrl"),
@@ -93,8 +105,8 @@ fn format_hotkey_chord(ch: config::HotkeyChord) -> String {
 
     let mut out = mods.join(" + ");
 
-    if let Some(vk) = ch.vk {
-        let key = vk_to_display(vk);
+    if let Some(key) = ch.key {
+        let key = vk_to_display(key.win32_vk());
         if !out.is_empty() {
             out.push_str(" + ");
         }
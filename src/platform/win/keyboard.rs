@@ -1,31 +1,44 @@
-mod capture;
+pub(crate) mod capture;
 #[cfg(debug_assertions)]
 pub(crate) mod debug_timers;
+pub(crate) mod hold;
 mod keydown;
 mod keyup;
 pub(crate) mod mods;
 pub(crate) mod sequence;
 pub(crate) mod vk;
 
-use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
 
 use windows::Win32::{
     Foundation::{HWND, LPARAM, LRESULT, WPARAM},
     System::SystemInformation::GetTickCount64,
     UI::WindowsAndMessaging::{
-        CallNextHookEx, HC_ACTION, HHOOK, KBDLLHOOKSTRUCT, PostMessageW, SetWindowsHookExW,
-        WH_KEYBOARD_LL,
+        CallNextHookEx, HC_ACTION, HHOOK, KBDLLHOOKSTRUCT, PostMessageW, SetTimer,
+        SetWindowsHookExW, WH_KEYBOARD_LL,
     },
 };
 
 use self::vk::{is_keydown_msg, is_keyup_msg, mod_bit_for_vk, normalize_vk};
 use crate::{
-    input,
+    input::{
+        self,
+        hotkey_sequence::{
+            MODVK_LALT, MODVK_LCTRL, MODVK_LSHIFT, MODVK_RALT, MODVK_RCTRL, MODVK_RSHIFT,
+        },
+        ring_buffer::HeldModifiers,
+    },
     platform::win::keyboard::{keydown::handle_keydown, keyup::handle_keyup},
 };
 
 static HOOK_HANDLE: AtomicIsize = AtomicIsize::new(0);
 static MAIN_HWND: AtomicIsize = AtomicIsize::new(0);
+/// Suppression state last reported to the UI thread.
+static PRIVACY_SHOWN: AtomicBool = AtomicBool::new(false);
+
+pub(crate) const PRIVACY_TIMER_ID: usize = 0x5157_0003;
+/// How often the focused field is checked for private input while the focus stays.
+const PRIVACY_CHECK_MS: u32 = 500;
 
 fn now_tick_ms() -> u64 {
     unsafe { GetTickCount64() }
@@ -62,6 +75,77 @@ fn report_hook_error(hwnd: HWND, state: &mut crate::app::AppState, e: &windows::
     );
 }
 
+/// Records a key-down that reaches the focused window in the input journal and asks for
+/// autoconvert when it ends a word.
+fn record_typed_key(kb: &KBDLLHOOKSTRUCT) {
+    report_typed(input::ring_buffer::record_keydown(kb, normalize_vk(kb)));
+}
+
+/// Records a key-down that was held back, as typed with the side-specific modifiers `mods_vks`
+/// that were down when it went down.
+fn record_held_key(kb: &KBDLLHOOKSTRUCT, mods_vks: u32) {
+    let held = HeldModifiers {
+        left_shift: mods_vks & MODVK_LSHIFT != 0,
+        right_shift: mods_vks & MODVK_RSHIFT != 0,
+        left_ctrl: mods_vks & MODVK_LCTRL != 0,
+        right_ctrl: mods_vks & MODVK_RCTRL != 0,
+        left_alt: mods_vks & MODVK_LALT != 0,
+        right_alt: mods_vks & MODVK_RALT != 0,
+    };
+    report_typed(input::ring_buffer::record_held_keydown(
+        kb,
+        normalize_vk(kb),
+        held,
+    ));
+}
+
+fn report_typed(typed: Option<String>) {
+    report_privacy_change();
+
+    if typed.is_some()
+        && crate::input::ring_buffer::last_char_triggers_autoconvert()
+        && let Some(hwnd) = main_hwnd()
+    {
+        let _ = unsafe {
+            PostMessageW(
+                Some(hwnd),
+                crate::platform::ui::error_notifier::WM_APP_AUTOCONVERT,
+                WPARAM(0),
+                LPARAM(0),
+            )
+        };
+    }
+}
+
+/// Checks again whether the focused field takes private input. Returns whether `id` was the
+/// privacy timer.
+pub(crate) fn handle_privacy_timer(id: usize) -> bool {
+    if id != PRIVACY_TIMER_ID {
+        return false;
+    }
+    input::ring_buffer::recheck_privacy();
+    report_privacy_change();
+    true
+}
+
+/// Tells the UI thread when recording of typed text stops or resumes.
+fn report_privacy_change() {
+    let suppressed = input::ring_buffer::input_suppressed();
+    if PRIVACY_SHOWN.swap(suppressed, Ordering::Relaxed) == suppressed {
+        return;
+    }
+    if let Some(hwnd) = main_hwnd() {
+        let _ = unsafe {
+            PostMessageW(
+                Some(hwnd),
+                crate::platform::ui::error_notifier::WM_APP_PRIVACY,
+                WPARAM(usize::from(suppressed)),
+                LPARAM(0),
+            )
+        };
+    }
+}
+
 extern "system" fn proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     if code != HC_ACTION.cast_signed() {
         let h = HOOK_HANDLE.load(Ordering::Relaxed);
@@ -84,29 +168,31 @@ extern "system" fn proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     let is_keyup = is_keyup_msg(msg);
 
     let decision = if is_keydown {
-        handle_keydown(vk, is_mod)
+        handle_keydown(kb, vk, is_mod)
     } else if is_keyup {
-        handle_keyup(vk, is_mod)
+        handle_keyup(kb, vk, is_mod)
     } else {
         Ok(HookDecision::Pass)
     };
 
+    if is_keydown
+        && matches!(decision.as_ref(), Ok(HookDecision::Pass))
+        && let Some(hwnd) = main_hwnd()
+        && input::ring_buffer::claim_backspace_revert(kb, vk)
+    {
+        let _ = unsafe {
+            PostMessageW(
+                Some(hwnd),
+                crate::platform::ui::error_notifier::WM_APP_AUTOCONVERT_REVERT,
+                WPARAM(0),
+                LPARAM(0),
+            )
+        };
+        return LRESULT(1);
+    }
+
     if is_keydown && matches!(decision.as_ref(), Ok(HookDecision::Pass)) {
-        let typed = input::ring_buffer::record_keydown(kb, vk);
-
-        if typed.is_some()
-            && crate::input::ring_buffer::last_char_triggers_autoconvert()
-            && let Some(hwnd) = main_hwnd()
-        {
-            let _ = unsafe {
-                PostMessageW(
-                    Some(hwnd),
-                    crate::platform::ui::error_notifier::WM_APP_AUTOCONVERT,
-                    WPARAM(0),
-                    LPARAM(0),
-                )
-            };
-        }
+        record_typed_key(kb);
     }
 
     match decision {
@@ -130,6 +216,8 @@ extern "system" fn proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
 /// release build observable even without logs.
 pub fn install(hwnd: HWND, state: &mut crate::app::AppState) {
     MAIN_HWND.store(hwnd.0 as isize, Ordering::Relaxed);
+    let _ = unsafe { SetTimer(Some(hwnd), PRIVACY_TIMER_ID, PRIVACY_CHECK_MS, None) };
+    input::ring_buffer::set_event_recorder(crate::domain::text::session::record_event);
 
     if HOOK_HANDLE.load(Ordering::Relaxed) != 0 {
         return;
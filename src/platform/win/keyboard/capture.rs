@@ -2,20 +2,25 @@ use windows::Win32::Foundation::HWND;
 
 use crate::{config, platform::win::format_hotkey_sequence, utils::helpers};
 
+/// Longest sequence the hotkey edits capture; longer ones can be written in the config.
+pub(crate) const MAX_CAPTURED_STEPS: usize = 3;
+
 pub(crate) fn chord_to_hotkey(ch: config::HotkeyChord) -> config::Hotkey {
     config::Hotkey {
-        vk: ch.vk.unwrap_or(0),
+        key: ch.key,
         mods: ch.mods,
     }
 }
 
+/// Appends `chord` to the sequence being captured, keeping the last [`MAX_CAPTURED_STEPS`].
+///
+/// A pause longer than `RESET_AFTER_MS` starts a new sequence.
 pub(crate) fn push_chord_capture(
-    existing: Option<config::HotkeySequence>,
+    existing: Option<&config::HotkeySequence>,
     chord: config::HotkeyChord,
     now_ms: u64,
     last_input_tick_ms: &mut u64,
 ) -> config::HotkeySequence {
-    const DEFAULT_GAP_MS: u32 = 1000;
     const RESET_AFTER_MS: u64 = 2000;
 
     let existing = match (*last_input_tick_ms, existing) {
@@ -25,22 +30,18 @@ pub(crate) fn push_chord_capture(
     };
 
     let seq = match existing {
-        None => config::HotkeySequence {
-            first: chord,
-            second: None,
-            max_gap_ms: DEFAULT_GAP_MS,
-        },
-        Some(mut s) => match s.second {
-            None => {
-                s.second = Some(chord);
-                s
-            }
-            Some(prev_second) => {
-                s.first = prev_second;
-                s.second = Some(chord);
-                s
-            }
-        },
+        None => config::HotkeySequence::of([chord], config::DEFAULT_MAX_GAP_MS),
+        Some(s) => {
+            let mut s = s.clone();
+            s.steps.push(config::HotkeyStep {
+                chord,
+                press: config::HotkeyPress::Down,
+                max_gap_ms: None,
+            });
+            let excess = s.steps.len().saturating_sub(MAX_CAPTURED_STEPS);
+            s.steps.drain(..excess);
+            s
+        }
     };
 
     *last_input_tick_ms = now_ms;
@@ -53,10 +54,10 @@ pub(crate) fn store_captured_hotkey(
     chord: config::HotkeyChord,
     seq: config::HotkeySequence,
 ) -> windows::core::Result<()> {
+    let text = format_hotkey_sequence(Some(&seq));
     state.hotkey_sequence_values.set(slot, Some(seq));
     state.hotkey_values.set(slot, Some(chord_to_hotkey(chord)));
 
-    let text = format_hotkey_sequence(Some(seq));
     let target = ui_hotkey_target(state, slot);
 
     helpers::set_edit_text(target, &text)?;
@@ -69,5 +70,7 @@ pub(crate) fn ui_hotkey_target(state: &crate::app::AppState, slot: crate::app::H
         crate::app::HotkeySlot::Pause => state.hotkeys.pause,
         crate::app::HotkeySlot::Selection => state.hotkeys.selection,
         crate::app::HotkeySlot::SwitchLayout => state.hotkeys.switch_layout,
+        crate::app::HotkeySlot::UndoConversion => state.hotkeys.undo_conversion,
+        crate::app::HotkeySlot::LastPhrase => state.hotkeys.last_phrase,
     }
 }
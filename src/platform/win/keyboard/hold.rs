@@ -0,0 +1,210 @@
+//! Tap and hold bindings.
+//!
+//! A key with such a binding is held back when it goes down. Its release or the hold timer then
+//! decides what the press was; a press that fires no binding is replayed as ordinary typing.
+
+use windows::Win32::{
+    Foundation::HWND,
+    UI::{
+        Input::KeyboardAndMouse::{MAPVK_VK_TO_VSC, MapVirtualKeyW},
+        WindowsAndMessaging::{
+            KBDLLHOOKSTRUCT, KBDLLHOOKSTRUCT_FLAGS, KillTimer, LLKHF_EXTENDED, LLKHF_UP, SetTimer,
+        },
+    },
+};
+
+use crate::{
+    app::{AppState, PendingKeyPress},
+    config::{self, DEFAULT_TAP_MAX_MS, HotkeyPress, KeyCode},
+    conversion::input::replay_hook_keys,
+    input::{hotkey_sequence::side_modifier_keys, sequence_matcher::chord_matches},
+    platform::win::{
+        keyboard::{
+            HookDecision, mods::mod_vks_now, now_tick_ms, record_held_key, record_typed_key,
+            report_hook_error, sequence::try_match_any_sequence,
+        },
+        with_state_mut,
+    },
+};
+
+pub(crate) const HOLD_TIMER_ID: usize = 0x5157_0002;
+
+/// Whether some sequence has a tap or hold step for `chord`.
+pub(crate) fn is_tap_or_hold_bound(state: &AppState, chord: config::HotkeyChord) -> bool {
+    state
+        .hotkey_matcher
+        .sequences()
+        .flat_map(|seq| &seq.steps)
+        .any(|step| !step.press.is_down() && chord_matches(step.chord, chord))
+}
+
+/// Shortest hold bound to `chord`; a shorter press is a tap.
+fn shortest_hold_ms(state: &AppState, chord: config::HotkeyChord) -> Option<u32> {
+    state
+        .hotkey_matcher
+        .sequences()
+        .flat_map(|seq| &seq.steps)
+        .filter(|step| chord_matches(step.chord, chord))
+        .filter_map(|step| match step.press {
+            HotkeyPress::Hold(ms) => Some(ms),
+            HotkeyPress::Down | HotkeyPress::Tap => None,
+        })
+        .min()
+}
+
+/// Holds back the key-down of a tap or hold bound key until the press is decided.
+pub(super) fn begin(
+    hwnd: HWND,
+    state: &mut AppState,
+    kb: KBDLLHOOKSTRUCT,
+    chord: config::HotkeyChord,
+    down_matched: bool,
+    now_ms: u64,
+) -> HookDecision {
+    state.pending_key_press = Some(PendingKeyPress {
+        chord,
+        down_tick_ms: now_ms,
+        kb,
+        mods_vks: mod_vks_now(),
+        down_matched,
+        hold_fired: false,
+    });
+    if let Some(hold_ms) = shortest_hold_ms(state, chord) {
+        let _ = unsafe { SetTimer(Some(hwnd), HOLD_TIMER_ID, hold_ms, None) };
+    }
+    HookDecision::Swallow
+}
+
+/// Another key went down while `pending` was held: the held key was typing, as in a fast roll
+/// from one key to the next.
+///
+/// The held key-down is replayed before the current key so that the text keeps its order, with
+/// the modifiers that were down when it went down.
+pub(super) fn interrupt(
+    hwnd: HWND,
+    pending: PendingKeyPress,
+    current: &KBDLLHOOKSTRUCT,
+    current_matched: bool,
+) -> HookDecision {
+    let _ = unsafe { KillTimer(Some(hwnd), HOLD_TIMER_ID) };
+    let current_decision = if current_matched {
+        HookDecision::Swallow
+    } else {
+        HookDecision::Pass
+    };
+    if pending.hold_fired || pending.down_matched {
+        return current_decision;
+    }
+
+    record_held_key(&pending.kb, pending.mods_vks);
+    if current_matched {
+        let _ = replay_hook_keys(&held_key_events(&pending, false));
+        return HookDecision::Swallow;
+    }
+    record_typed_key(current);
+    let mut keys = held_key_events(&pending, false);
+    keys.push(*current);
+    let _ = replay_hook_keys(&keys);
+    HookDecision::Swallow
+}
+
+/// Decides a press on the release of its key: a tap binding fires, or the press is replayed.
+pub(super) fn release(
+    hwnd: HWND,
+    state: &mut AppState,
+    pending: PendingKeyPress,
+    now_ms: u64,
+) -> windows::core::Result<HookDecision> {
+    let _ = unsafe { KillTimer(Some(hwnd), HOLD_TIMER_ID) };
+    if pending.hold_fired {
+        return Ok(HookDecision::Swallow);
+    }
+
+    let held_ms = now_ms.saturating_sub(pending.down_tick_ms);
+    let tap_max_ms = shortest_hold_ms(state, pending.chord).unwrap_or(DEFAULT_TAP_MAX_MS);
+    if held_ms < u64::from(tap_max_ms)
+        && try_match_any_sequence(hwnd, state, pending.chord, HotkeyPress::Tap, now_ms)?
+    {
+        return Ok(HookDecision::Swallow);
+    }
+    if pending.down_matched {
+        return Ok(HookDecision::Swallow);
+    }
+
+    record_held_key(&pending.kb, pending.mods_vks);
+    let _ = replay_hook_keys(&held_key_events(&pending, true));
+    Ok(HookDecision::Swallow)
+}
+
+/// Key events that type the held key-down, and its release when `released`, with the modifiers
+/// of its key-down. Modifiers pressed or released since are switched around it and back.
+fn held_key_events(pending: &PendingKeyPress, released: bool) -> Vec<KBDLLHOOKSTRUCT> {
+    let now = mod_vks_now();
+    let missing = pending.mods_vks & !now;
+    let extra = now & !pending.mods_vks;
+
+    let mut keys: Vec<_> = modifier_events(missing, false)
+        .chain(modifier_events(extra, true))
+        .collect();
+    keys.push(pending.kb);
+    if released {
+        keys.push(KBDLLHOOKSTRUCT {
+            flags: pending.kb.flags | LLKHF_UP,
+            ..pending.kb
+        });
+    }
+    keys.extend(modifier_events(missing, true).chain(modifier_events(extra, false)));
+    keys
+}
+
+/// Presses, or with `up` releases, the side-specific modifiers of `mods_vks`.
+fn modifier_events(mods_vks: u32, up: bool) -> impl Iterator<Item = KBDLLHOOKSTRUCT> {
+    side_modifier_keys(mods_vks).map(move |key| {
+        let vk = key.win32_vk();
+        let mut flags = if up {
+            LLKHF_UP
+        } else {
+            KBDLLHOOKSTRUCT_FLAGS::default()
+        };
+        if matches!(
+            key,
+            KeyCode::RCtrl | KeyCode::RAlt | KeyCode::LWin | KeyCode::RWin
+        ) {
+            flags |= LLKHF_EXTENDED;
+        }
+        KBDLLHOOKSTRUCT {
+            vkCode: vk,
+            scanCode: unsafe { MapVirtualKeyW(vk, MAPVK_VK_TO_VSC) },
+            flags,
+            ..Default::default()
+        }
+    })
+}
+
+/// Fires the hold binding of the key still held down. Returns whether `id` was the hold timer.
+pub(crate) fn handle_hold_timer(hwnd: HWND, id: usize) -> bool {
+    if id != HOLD_TIMER_ID {
+        return false;
+    }
+    let _ = unsafe { KillTimer(Some(hwnd), HOLD_TIMER_ID) };
+
+    let now_ms = now_tick_ms();
+    with_state_mut(hwnd, |state| {
+        let Some(pending) = state.pending_key_press.filter(|p| !p.hold_fired) else {
+            return;
+        };
+        let held_ms =
+            u32::try_from(now_ms.saturating_sub(pending.down_tick_ms)).unwrap_or(u32::MAX);
+        let hold = HotkeyPress::Hold(held_ms);
+        match try_match_any_sequence(hwnd, state, pending.chord, hold, now_ms) {
+            Ok(true) => {
+                if let Some(pending) = state.pending_key_press.as_mut() {
+                    pending.hold_fired = true;
+                }
+            }
+            Ok(false) => {}
+            Err(e) => report_hook_error(hwnd, state, &e),
+        }
+    });
+    true
+}
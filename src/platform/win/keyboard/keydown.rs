@@ -1,4 +1,7 @@
-use windows::Win32::Foundation::HWND;
+use windows::Win32::{
+    Foundation::HWND,
+    UI::WindowsAndMessaging::{KBDLLHOOKSTRUCT, LLKHF_INJECTED},
+};
 
 use crate::{
     config,
@@ -6,7 +9,7 @@ use crate::{
         keyboard::{
             HookDecision,
             capture::{push_chord_capture, store_captured_hotkey},
-            main_hwnd,
+            hold, main_hwnd,
             mods::{chord_from_vk, update_mods_down_press},
             now_tick_ms,
             sequence::try_match_any_sequence,
@@ -15,7 +18,11 @@ use crate::{
     },
 };
 
-pub(crate) fn handle_keydown(vk: u32, is_mod: bool) -> windows::core::Result<HookDecision> {
+pub(crate) fn handle_keydown(
+    kb: &KBDLLHOOKSTRUCT,
+    vk: u32,
+    is_mod: bool,
+) -> windows::core::Result<HookDecision> {
     update_mods_down_press(vk);
 
     let Some(hwnd) = main_hwnd() else {
@@ -25,7 +32,7 @@ pub(crate) fn handle_keydown(vk: u32, is_mod: bool) -> windows::core::Result<Hoo
     let now_ms = now_tick_ms();
 
     with_state_mut(hwnd, |state| {
-        handle_keydown_in_state(hwnd, state, vk, is_mod, now_ms)
+        handle_keydown_in_state(hwnd, state, kb, vk, is_mod, now_ms)
     })
     .unwrap_or(Ok(HookDecision::Pass))
 }
@@ -33,6 +40,7 @@ pub(crate) fn handle_keydown(vk: u32, is_mod: bool) -> windows::core::Result<Hoo
 pub(crate) fn handle_keydown_in_state(
     hwnd: HWND,
     state: &mut crate::app::AppState,
+    kb: &KBDLLHOOKSTRUCT,
     vk: u32,
     is_mod: bool,
     now_ms: u64,
@@ -43,7 +51,7 @@ pub(crate) fn handle_keydown_in_state(
         return handle_keydown_capture(state, chord, is_mod, now_ms);
     }
 
-    handle_keydown_runtime(hwnd, state, chord, is_mod, now_ms)
+    handle_keydown_runtime(hwnd, state, kb, chord, is_mod, now_ms)
 }
 
 pub(crate) fn handle_keydown_capture(
@@ -82,6 +90,7 @@ pub(crate) fn handle_keydown_capture(
 pub(crate) fn handle_keydown_runtime(
     hwnd: HWND,
     state: &mut crate::app::AppState,
+    kb: &KBDLLHOOKSTRUCT,
     chord: config::HotkeyChord,
     is_mod: bool,
     now_ms: u64,
@@ -97,10 +106,40 @@ pub(crate) fn handle_keydown_runtime(
     state.runtime_chord_capture.saw_non_mod = true;
     state.runtime_chord_capture.pending_mods_valid = false;
 
-    let matched = try_match_any_sequence(hwnd, state, chord, now_ms)?;
-    Ok(if matched {
+    let injected = kb.flags.contains(LLKHF_INJECTED);
+    // Auto-repeat of a key held back for its tap or hold binding.
+    if !injected
+        && state
+            .pending_key_press
+            .is_some_and(|pending| pending.chord.key == chord.key)
+    {
+        return Ok(HookDecision::Swallow);
+    }
+
+    let matched = try_match_any_sequence(hwnd, state, chord, config::HotkeyPress::Down, now_ms)?;
+    if injected {
+        return Ok(decision_for(matched));
+    }
+
+    let interrupted = state.pending_key_press.take();
+    if hold::is_tap_or_hold_bound(state, chord) {
+        if let Some(pending) = interrupted {
+            // The current key is held back in turn, so only the interrupted one is replayed.
+            let _ = hold::interrupt(hwnd, pending, kb, true);
+        }
+        return Ok(hold::begin(hwnd, state, *kb, chord, matched, now_ms));
+    }
+    if let Some(pending) = interrupted {
+        return Ok(hold::interrupt(hwnd, pending, kb, matched));
+    }
+
+    Ok(decision_for(matched))
+}
+
+const fn decision_for(matched: bool) -> HookDecision {
+    if matched {
         HookDecision::Swallow
     } else {
         HookDecision::Pass
-    })
+    }
 }
@@ -1,4 +1,7 @@
-use windows::Win32::Foundation::HWND;
+use windows::Win32::{
+    Foundation::HWND,
+    UI::WindowsAndMessaging::{KBDLLHOOKSTRUCT, LLKHF_INJECTED},
+};
 
 use crate::{
     config,
@@ -6,7 +9,7 @@ use crate::{
         keyboard::{
             HookDecision,
             capture::{push_chord_capture, store_captured_hotkey},
-            main_hwnd,
+            hold, main_hwnd,
             mods::{mods_now, update_mods_down_release},
             now_tick_ms,
             sequence::try_match_any_sequence,
@@ -15,7 +18,11 @@ use crate::{
     },
 };
 
-pub(crate) fn handle_keyup(vk: u32, is_mod: bool) -> windows::core::Result<HookDecision> {
+pub(crate) fn handle_keyup(
+    kb: &KBDLLHOOKSTRUCT,
+    vk: u32,
+    is_mod: bool,
+) -> windows::core::Result<HookDecision> {
     update_mods_down_release(vk);
 
     let Some(hwnd) = main_hwnd() else {
@@ -25,7 +32,7 @@ pub(crate) fn handle_keyup(vk: u32, is_mod: bool) -> windows::core::Result<HookD
     let now_ms = now_tick_ms();
 
     with_state_mut(hwnd, |state| {
-        handle_keyup_in_state(hwnd, state, vk, is_mod, now_ms)
+        handle_keyup_in_state(hwnd, state, kb, vk, is_mod, now_ms)
     })
     .unwrap_or(Ok(HookDecision::Pass))
 }
@@ -33,7 +40,8 @@ pub(crate) fn handle_keyup(vk: u32, is_mod: bool) -> windows::core::Result<HookD
 pub(crate) fn handle_keyup_in_state(
     hwnd: HWND,
     state: &mut crate::app::AppState,
-    _vk: u32,
+    kb: &KBDLLHOOKSTRUCT,
+    vk: u32,
     is_mod: bool,
     now_ms: u64,
 ) -> windows::core::Result<HookDecision> {
@@ -41,6 +49,14 @@ pub(crate) fn handle_keyup_in_state(
         return handle_keyup_capture(state, is_mod, now_ms);
     }
 
+    if !kb.flags.contains(LLKHF_INJECTED)
+        && let Some(pending) = state.pending_key_press
+        && config::KeyCode::from_win32_vk(vk) == pending.chord.key
+    {
+        state.pending_key_press = None;
+        return hold::release(hwnd, state, pending, now_ms);
+    }
+
     handle_keyup_runtime(hwnd, state, is_mod, now_ms)
 }
 
@@ -72,7 +88,7 @@ pub(crate) fn handle_keyup_capture(
     let chord = config::HotkeyChord {
         mods: state.hotkey_capture.pending_mods,
         mods_vks: state.hotkey_capture.pending_mods_vks,
-        vk: None,
+        key: None,
     };
 
     let prev = state.hotkey_sequence_values.get(slot);
@@ -116,12 +132,12 @@ pub(crate) fn handle_keyup_runtime(
     let chord = config::HotkeyChord {
         mods: state.runtime_chord_capture.pending_mods,
         mods_vks: state.runtime_chord_capture.pending_mods_vks,
-        vk: None,
+        key: None,
     };
 
     state.runtime_chord_capture = crate::app::RuntimeChordCapture::default();
 
-    let matched = try_match_any_sequence(hwnd, state, chord, now_ms)?;
+    let matched = try_match_any_sequence(hwnd, state, chord, config::HotkeyPress::Down, now_ms)?;
     Ok(if matched {
         HookDecision::Swallow
     } else {
@@ -38,10 +38,15 @@ pub(crate) fn chord_from_vk(vk: u32) -> config::HotkeyChord {
     config::HotkeyChord {
         mods,
         mods_vks,
-        vk: Some(vk),
+        key: config::KeyCode::from_win32_vk(vk),
     }
 }
 
 pub(crate) fn mods_now() -> u32 {
     MODS_DOWN.load(Ordering::Relaxed)
 }
+
+/// Side-specific modifiers down now, as raw `MODVK_*` bits.
+pub(crate) fn mod_vks_now() -> u32 {
+    MODVKS_DOWN.load(Ordering::Relaxed)
+}
@@ -21,7 +21,7 @@ extern "system" fn proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     let msg = u32::try_from(wparam.0);
     let _ms = unsafe { &*(lparam.0 as *const MSLLHOOKSTRUCT) };
 
-    let should_invalidate = matches!(
+    let untracked = matches!(
         msg,
         Ok(WM_LBUTTONDOWN
             | WM_LBUTTONDBLCLK
@@ -33,8 +33,8 @@ extern "system" fn proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
             | WM_MOUSEHWHEEL)
     );
 
-    if should_invalidate {
-        crate::input::ring_buffer::invalidate();
+    if untracked {
+        crate::input::ring_buffer::untracked_input();
     }
 
     let h = HOOK_HANDLE.load(Ordering::Relaxed);
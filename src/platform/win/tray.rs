@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use windows::{
     Win32::{
         Foundation::{HINSTANCE, HWND, POINT},
@@ -9,10 +11,10 @@ use windows::{
             },
             WindowsAndMessaging::{
                 AppendMenuW, CreatePopupMenu, DestroyMenu, GWLP_HINSTANCE, GetCursorPos,
-                GetWindowLongPtrW, HICON, HMENU, IMAGE_ICON, LR_SHARED, LoadImageW, MF_SEPARATOR,
-                SW_HIDE, SW_RESTORE, SetForegroundWindow, ShowWindow, TPM_BOTTOMALIGN,
-                TPM_NOANIMATION, TPM_RETURNCMD, TPM_RIGHTALIGN, TPM_RIGHTBUTTON, TrackPopupMenu,
-                WM_APP,
+                GetWindowLongPtrW, HICON, HMENU, IDI_SHIELD, IMAGE_ICON, LR_SHARED, LoadIconW,
+                LoadImageW, MF_SEPARATOR, SW_HIDE, SW_RESTORE, SetForegroundWindow, ShowWindow,
+                TPM_BOTTOMALIGN, TPM_NOANIMATION, TPM_RETURNCMD, TPM_RIGHTALIGN, TPM_RIGHTBUTTON,
+                TrackPopupMenu, WM_APP,
             },
         },
     },
@@ -24,6 +26,7 @@ use super::winutil::make_int_resource;
 pub enum TrayMenuAction {
     None,
     ToggleAutoConvert,
+    NeverConvertLastWord,
 }
 
 pub const WM_APP_TRAY: u32 = WM_APP + 3;
@@ -32,7 +35,22 @@ const ID_EXIT: u32 = 1001;
 const ID_SHOW_HIDE: u32 = 1002;
 const ID_AUTOCONVERT_TOGGLE: u32 = 1003;
 const ID_CHANGE_THEME: u32 = 1004;
+const ID_NEVER_CONVERT_LAST: u32 = 1005;
 const TRAY_TOOLTIP: &str = "Rust Switcher";
+const TRAY_TOOLTIP_PRIVATE: &str = "Rust Switcher: not recording (private input)";
+
+/// Whether the icon and the tooltip say that typed text is not recorded.
+static PRIVATE_INPUT: AtomicBool = AtomicBool::new(false);
+/// Whether the icon shows autoconvert as enabled, once typed text is recorded again.
+static GREEN_ICON: AtomicBool = AtomicBool::new(false);
+
+fn tooltip() -> &'static str {
+    if PRIVATE_INPUT.load(Ordering::Relaxed) {
+        TRAY_TOOLTIP_PRIVATE
+    } else {
+        TRAY_TOOLTIP
+    }
+}
 
 unsafe fn show_popup_menu_at_cursor(hwnd: HWND, hmenu: HMENU) -> u32 {
     let mut pt = POINT { x: 0, y: 0 };
@@ -127,8 +145,8 @@ unsafe fn apply_tray_identity(nid: &mut NOTIFYICONDATAW, hwnd: HWND) -> windows:
     nid.uCallbackMessage = WM_APP_TRAY;
     nid.uFlags = NIF_MESSAGE | NIF_ICON | NIF_TIP | NIF_SHOWTIP;
 
-    nid.hIcon = unsafe { default_icon(hwnd) }?;
-    fill_wide(&mut nid.szTip, TRAY_TOOLTIP);
+    nid.hIcon = unsafe { current_icon(hwnd) }?;
+    fill_wide(&mut nid.szTip, tooltip());
 
     Ok(())
 }
@@ -267,12 +285,9 @@ pub fn balloon_info(hwnd: HWND, title: &str, text: &str) -> windows::core::Resul
 }
 
 pub fn switch_tray_icon(hwnd: HWND, use_green: bool) -> windows::core::Result<()> {
+    GREEN_ICON.store(use_green, Ordering::Relaxed);
     unsafe {
-        let icon = if use_green {
-            green_icon(hwnd)?
-        } else {
-            default_icon(hwnd)?
-        };
+        let icon = current_icon(hwnd)?;
 
         let mut nid = NOTIFYICONDATAW {
             cbSize: u32::try_from(core::mem::size_of::<NOTIFYICONDATAW>())?,
@@ -284,17 +299,42 @@ pub fn switch_tray_icon(hwnd: HWND, use_green: bool) -> windows::core::Result<()
         nid.uFlags = NIF_ICON | NIF_MESSAGE | NIF_TIP | NIF_SHOWTIP;
         nid.uCallbackMessage = WM_APP_TRAY;
         nid.hIcon = icon;
-        fill_wide(&mut nid.szTip, TRAY_TOOLTIP);
+        fill_wide(&mut nid.szTip, tooltip());
 
         shell_notify(NIM_MODIFY, &nid, "switch_tray_icon")
     }
 }
 
+/// Shows with the tray icon and its tooltip whether typed text is being recorded.
+pub fn set_private_input_indicator(hwnd: HWND, private: bool) -> windows::core::Result<()> {
+    PRIVATE_INPUT.store(private, Ordering::Relaxed);
+    unsafe {
+        let mut nid = base_tray_nid(hwnd)?;
+        nid.uFlags = NIF_ICON | NIF_TIP | NIF_SHOWTIP;
+        nid.hIcon = current_icon(hwnd)?;
+        fill_wide(&mut nid.szTip, tooltip());
+
+        shell_notify(NIM_MODIFY, &nid, "set_private_input_indicator")
+    }
+}
+
 unsafe fn window_hinstance(hwnd: HWND) -> HINSTANCE {
     let raw = unsafe { GetWindowLongPtrW(hwnd, GWLP_HINSTANCE) };
     HINSTANCE(raw as *mut core::ffi::c_void)
 }
 
+/// Icon for the current state: the shield while typed text is not recorded, otherwise green
+/// while autoconvert is enabled.
+unsafe fn current_icon(hwnd: HWND) -> windows::core::Result<HICON> {
+    if PRIVATE_INPUT.load(Ordering::Relaxed) {
+        unsafe { LoadIconW(None, IDI_SHIELD) }
+    } else if GREEN_ICON.load(Ordering::Relaxed) {
+        unsafe { green_icon(hwnd) }
+    } else {
+        unsafe { default_icon(hwnd) }
+    }
+}
+
 unsafe fn green_icon(hwnd: HWND) -> windows::core::Result<HICON> {
     let hinst = unsafe { window_hinstance(hwnd) };
 
@@ -347,11 +387,17 @@ pub fn show_tray_context_menu(
     window_visible: bool,
     autoconvert_enabled: bool,
     current_theme_dark: bool,
+    last_autoconverted_word: Option<&str>,
 ) -> Result<TrayMenuAction> {
     unsafe {
         crate::platform::win::menu_theme::set_tray_menu_preferred_theme(current_theme_dark);
 
-        let hmenu = build_tray_menu(window_visible, autoconvert_enabled, current_theme_dark)?;
+        let hmenu = build_tray_menu(
+            window_visible,
+            autoconvert_enabled,
+            current_theme_dark,
+            last_autoconverted_word,
+        )?;
 
         crate::platform::win::menu_theme::flush_tray_menu_theme();
 
@@ -371,10 +417,14 @@ fn build_tray_menu(
     window_visible: bool,
     autoconvert_enabled: bool,
     current_theme_dark: bool,
+    last_autoconverted_word: Option<&str>,
 ) -> Result<HMENU> {
     let hmenu = unsafe { CreatePopupMenu() }?;
 
     unsafe { append_autoconvert_toggle_item(hmenu, autoconvert_enabled) }?;
+    if let Some(word) = last_autoconverted_word {
+        unsafe { append_never_convert_item(hmenu, word) }?;
+    }
     unsafe { AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null()) }?;
 
     unsafe { append_show_hide_item(hmenu, window_visible) }?;
@@ -414,6 +464,24 @@ unsafe fn append_autoconvert_toggle_item(hmenu: HMENU, autoconvert_enabled: bool
     Ok(())
 }
 
+unsafe fn append_never_convert_item(hmenu: HMENU, word: &str) -> Result<()> {
+    use windows::Win32::UI::WindowsAndMessaging::{AppendMenuW, MF_STRING};
+
+    let text = format!("Never convert \"{word}\"\0");
+    let wide: Vec<u16> = text.encode_utf16().collect();
+
+    (unsafe {
+        AppendMenuW(
+            hmenu,
+            MF_STRING,
+            ID_NEVER_CONVERT_LAST as usize,
+            PCWSTR(wide.as_ptr()),
+        )
+    })?;
+
+    Ok(())
+}
+
 unsafe fn append_change_theme_item(hmenu: HMENU, current_theme_dark: bool) -> Result<()> {
     use windows::Win32::UI::WindowsAndMessaging::{AppendMenuW, MF_STRING};
 
@@ -474,6 +542,7 @@ unsafe fn handle_tray_menu_cmd(
 ) -> Result<TrayMenuAction> {
     match cmd {
         ID_AUTOCONVERT_TOGGLE => Ok(TrayMenuAction::ToggleAutoConvert),
+        ID_NEVER_CONVERT_LAST => Ok(TrayMenuAction::NeverConvertLastWord),
 
         ID_SHOW_HIDE => {
             unsafe { toggle_window_visibility(hwnd, window_visible) };
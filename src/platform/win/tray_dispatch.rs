@@ -185,7 +185,7 @@ pub fn handle_tray_message(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) -> LRESUL
             let _ = unsafe { KillTimer(Some(hwnd), TRAY_SINGLE_CLICK_TIMER_ID) };
 
             with_state_mut_do(hwnd, |state| {
-                let next = !state.autoconvert_enabled;
+                let next = !state.typing.autoconvert_enabled();
                 super::set_autoconvert_enabled_from_tray(hwnd, state, next, false);
             });
 
@@ -203,15 +203,19 @@ pub fn handle_tray_message(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) -> LRESUL
                 match super::tray::show_tray_context_menu(
                     hwnd,
                     window_visible,
-                    state.autoconvert_enabled,
+                    state.typing.autoconvert_enabled(),
                     state.current_theme_dark,
+                    state.typing.last_autoconverted_word(),
                 ) {
                     Ok(action) => match action {
                         super::tray::TrayMenuAction::None => {}
                         super::tray::TrayMenuAction::ToggleAutoConvert => {
-                            let next = !state.autoconvert_enabled;
+                            let next = !state.typing.autoconvert_enabled();
                             super::set_autoconvert_enabled_from_tray(hwnd, state, next, false);
                         }
+                        super::tray::TrayMenuAction::NeverConvertLastWord => {
+                            super::never_convert_last_autoconverted(hwnd, state);
+                        }
                     },
                     Err(e) => tracing::warn!(error = ?e, "tray menu failed"),
                 }
@@ -0,0 +1,41 @@
+use crate::domain::text::alternatives::{Alternative, ConversionCycle, fix_case, transliterate};
+
+#[test]
+fn transliterate_cyrillic_to_latin_keeps_case() {
+    assert_eq!(transliterate("Привет, щука"), "Privet, shchuka");
+    assert_eq!(transliterate("Жёлтый"), "Zhyoltyy");
+}
+
+#[test]
+fn transliterate_latin_to_cyrillic_prefers_digraphs() {
+    assert_eq!(transliterate("privet"), "привет");
+    assert_eq!(transliterate("Shchuka zhuk"), "Щука жук");
+}
+
+#[test]
+fn fix_case_inverts_capslock_words_only() {
+    assert_eq!(fix_case("hELLO World"), "Hello World");
+    assert_eq!(fix_case("ПРИВЕТ мир"), "привет мир");
+}
+
+#[test]
+fn cycle_visits_alternatives_in_order_and_wraps() {
+    let mut cycle = ConversionCycle::new("pRIVET", "зКШМУЕ");
+    assert_eq!(cycle.current(), "зКШМУЕ");
+    assert_eq!(cycle.advance(), "pRIVET");
+    assert_eq!(cycle.current_alternative(), Alternative::Original);
+    assert_eq!(cycle.advance(), "пРИВЕТ");
+    assert_eq!(cycle.current_alternative(), Alternative::Transliteration);
+    assert_eq!(cycle.advance(), "Privet");
+    assert_eq!(cycle.current_alternative(), Alternative::CaseFixed);
+    assert_eq!(cycle.advance(), "зКШМУЕ");
+}
+
+#[test]
+fn cycle_skips_duplicate_alternatives() {
+    // Lowercase original: the case-fixed variant equals the original and is skipped.
+    let mut cycle = ConversionCycle::new("ghbdtn", "привет");
+    assert_eq!(cycle.advance(), "ghbdtn");
+    assert_eq!(cycle.advance(), "гхбдтн");
+    assert_eq!(cycle.advance(), "привет");
+}
@@ -0,0 +1,98 @@
+use lingua::Language;
+
+use crate::domain::text::autoconvert::{
+    ContextPrior, ExceptionRules, SentenceContext, SkipReason, language_detector,
+    should_autoconvert_word, should_autoconvert_word_with_prior,
+};
+
+fn russian_context() -> SentenceContext {
+    let mut ctx = SentenceContext::default();
+    for word in ["я", "сегодня", "пойду"] {
+        ctx.observe(word, None, false);
+    }
+    ctx
+}
+
+#[test]
+fn lean_counts_converted_words_twice() {
+    let mut ctx = SentenceContext::default();
+    ctx.observe("hello", None, false);
+    ctx.observe("привет", None, true);
+    assert!((ctx.lean(Language::Russian) - 1.0 / 3.0).abs() < 1e-9);
+    assert!((ctx.lean(Language::English) + 1.0 / 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn layout_decides_words_without_letters() {
+    let mut ctx = SentenceContext::default();
+    ctx.observe("42", Some(Language::Russian), false);
+    ctx.observe("...", None, false);
+    assert_eq!(ctx.russian_words, 1);
+    assert_eq!(ctx.english_words, 0);
+}
+
+#[test]
+fn weight_is_clamped_and_zero_disables_the_prior() {
+    let ctx = russian_context();
+    assert_eq!(ContextPrior::new(ctx, 0.0).bias(Language::Russian), 0.0);
+    assert_eq!(ContextPrior::new(ctx, 5.0).bias(Language::Russian), 1.0);
+    assert_eq!(
+        ContextPrior::new(ctx, f64::NAN).bias(Language::Russian),
+        0.0
+    );
+}
+
+#[test]
+fn russian_context_lets_short_words_convert() {
+    let rules = ExceptionRules::default();
+    let detector = language_detector();
+
+    assert_eq!(
+        should_autoconvert_word(detector, &rules, "lf", "да"),
+        Err(SkipReason::TooShort)
+    );
+
+    let prior = ContextPrior::new(russian_context(), 1.0);
+    assert_eq!(
+        should_autoconvert_word_with_prior(detector, &rules, "lf", "да", &prior),
+        Ok(())
+    );
+}
+
+#[test]
+fn english_context_does_not_relax_short_words() {
+    let mut ctx = SentenceContext::default();
+    for word in ["see", "you", "later"] {
+        ctx.observe(word, None, false);
+    }
+    let prior = ContextPrior::new(ctx, 1.0);
+    assert_eq!(
+        should_autoconvert_word_with_prior(
+            language_detector(),
+            &ExceptionRules::default(),
+            "lf",
+            "да",
+            &prior
+        ),
+        Err(SkipReason::TooShort)
+    );
+}
+
+#[test]
+fn russian_context_keeps_english_words() {
+    let prior = ContextPrior::new(russian_context(), 1.0);
+    for (word, converted) in [("merge", "ьукпу"), ("docker", "вщслук"), ("test", "еуые")]
+    {
+        assert_eq!(
+            should_autoconvert_word_with_prior(
+                language_detector(),
+                &ExceptionRules::default(),
+                word,
+                converted,
+                &prior
+            ),
+            Err(SkipReason::AlreadyCorrect),
+            "{word}"
+        );
+    }
+}
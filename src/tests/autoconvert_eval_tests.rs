@@ -0,0 +1,91 @@
+use crate::domain::text::{
+    autoconvert::language_detector,
+    autoconvert_eval::{
+        Baseline, ExpectedAction, Metrics, evaluate, parse_baseline, parse_corpus, regressions,
+        render_report,
+    },
+    mapping::ConversionDirection,
+};
+
+const CORPUS: &str = include_str!("data/autoconvert_corpus.tsv");
+const BASELINE: &str = include_str!("data/autoconvert_baseline.txt");
+
+#[test]
+fn parse_corpus_reads_columns_and_skips_comments() {
+    let entries =
+        parse_corpus("# header\n\nghbdtn\tconvert\ten\nпривет\tkeep\nlf\tconvert\t\tя сегодня\n")
+            .unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].line, 3);
+    assert_eq!(entries[0].text, "ghbdtn");
+    assert_eq!(entries[0].expected, ExpectedAction::Convert);
+    assert_eq!(entries[0].direction, Some(ConversionDirection::EnToRu));
+    assert_eq!(entries[1].expected, ExpectedAction::Keep);
+    assert_eq!(entries[1].direction, None);
+    assert!(entries[1].context.is_empty());
+    assert_eq!(entries[2].direction, None);
+    assert_eq!(entries[2].context, ["я", "сегодня"]);
+}
+
+#[test]
+fn parse_corpus_reports_line_of_bad_action() {
+    let err = parse_corpus("ghbdtn\tconvert\nhello\tmaybe\n").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(err.message.contains("maybe"));
+}
+
+#[test]
+fn metrics_precision_and_recall() {
+    let m = Metrics {
+        true_converts: 3,
+        false_converts: 1,
+        true_keeps: 5,
+        false_keeps: 3,
+    };
+    assert!((m.precision() - 0.75).abs() < f64::EPSILON);
+    assert!((m.recall() - 0.5).abs() < f64::EPSILON);
+
+    let empty = Metrics::default();
+    assert!((empty.precision() - 1.0).abs() < f64::EPSILON);
+    assert!((empty.recall() - 1.0).abs() < f64::EPSILON);
+}
+
+#[test]
+fn baseline_parse_and_regressions() {
+    let baseline =
+        parse_baseline("min_precision = 0.9\nmin_recall=0.5\nmax_false_converts = 0\n").unwrap();
+    assert_eq!(
+        baseline,
+        Baseline {
+            min_precision: 0.9,
+            min_recall: 0.5,
+            max_false_converts: Some(0),
+        }
+    );
+
+    let m = Metrics {
+        true_converts: 1,
+        false_converts: 1,
+        true_keeps: 0,
+        false_keeps: 0,
+    };
+    let failed = regressions(&m, &baseline);
+    assert_eq!(failed.len(), 2, "{failed:?}");
+
+    assert!(parse_baseline("min_precision = high\n").is_err());
+    assert!(parse_baseline("min_f1 = 0.5\n").is_err());
+}
+
+#[test]
+fn checked_in_corpus_meets_baseline() {
+    let entries = parse_corpus(CORPUS).unwrap();
+    let baseline = parse_baseline(BASELINE).unwrap();
+    let report = evaluate(language_detector(), &entries);
+
+    let failed = regressions(&report.metrics, &baseline);
+    assert!(
+        failed.is_empty(),
+        "autoconvert metrics regressed: {failed:?}\n\n{}",
+        render_report(&report)
+    );
+}
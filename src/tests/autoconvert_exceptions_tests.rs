@@ -0,0 +1,81 @@
+use crate::domain::text::autoconvert::{
+    AutoconvertExceptions, ExceptionRules, ExceptionVerdict, SkipReason, language_detector,
+    should_autoconvert_word,
+};
+
+fn rules(src: &AutoconvertExceptions) -> ExceptionRules {
+    ExceptionRules::compile(src).unwrap()
+}
+
+#[test]
+fn never_convert_word_blocks_conversion() {
+    let src = AutoconvertExceptions {
+        never_convert: vec!["Ghbdtn".to_string()],
+        ..AutoconvertExceptions::default()
+    };
+    let decision = should_autoconvert_word(language_detector(), &rules(&src), "ghbdtn", "привет");
+    assert_eq!(decision, Err(SkipReason::UserNeverConvert));
+}
+
+#[test]
+fn always_convert_word_bypasses_heuristics() {
+    // Short words are normally rejected as too short.
+    let src = AutoconvertExceptions {
+        always_convert: vec!["lf".to_string()],
+        ..AutoconvertExceptions::default()
+    };
+    let decision = should_autoconvert_word(language_detector(), &rules(&src), "lf", "да");
+    assert_eq!(decision, Ok(()));
+}
+
+#[test]
+fn patterns_match_prefix_and_regex() {
+    let src = AutoconvertExceptions {
+        never_convert_patterns: vec!["prefix:git".to_string(), r"regex:^v\d+$".to_string()],
+        ..AutoconvertExceptions::default()
+    };
+    let rules = rules(&src);
+    assert_eq!(
+        rules.verdict("GitHub"),
+        Some(ExceptionVerdict::NeverConvert)
+    );
+    assert_eq!(rules.verdict("v12"), Some(ExceptionVerdict::NeverConvert));
+    assert_eq!(rules.verdict("vx12"), None);
+}
+
+#[test]
+fn never_convert_wins_over_always_convert() {
+    let src = AutoconvertExceptions {
+        never_convert: vec!["word".to_string()],
+        always_convert_patterns: vec!["prefix:wo".to_string()],
+        ..AutoconvertExceptions::default()
+    };
+    assert_eq!(
+        rules(&src).verdict("word"),
+        Some(ExceptionVerdict::NeverConvert)
+    );
+}
+
+#[test]
+fn compile_rejects_malformed_patterns() {
+    for bad in ["regex:(", "prefix:", "word"] {
+        let src = AutoconvertExceptions {
+            always_convert_patterns: vec![bad.to_string()],
+            ..AutoconvertExceptions::default()
+        };
+        assert!(ExceptionRules::compile(&src).is_err(), "{bad} accepted");
+    }
+}
+
+#[test]
+fn add_never_convert_dedupes_and_moves_from_always() {
+    let mut src = AutoconvertExceptions {
+        always_convert: vec!["Rust".to_string()],
+        ..AutoconvertExceptions::default()
+    };
+    assert!(src.add_never_convert("rust"));
+    assert!(!src.add_never_convert("RUST"));
+    assert!(!src.add_never_convert("  "));
+    assert_eq!(src.never_convert, vec!["rust".to_string()]);
+    assert!(src.always_convert.is_empty());
+}
@@ -0,0 +1,44 @@
+use crate::domain::text::autoconvert::AutoconvertLearning;
+
+#[test]
+fn revert_reports_word_without_trailing_punctuation() {
+    let mut learning = AutoconvertLearning::new(3);
+    learning.record_autoconvert();
+    let outcome = learning.record_revert("ghbdtn,");
+    assert_eq!(outcome.word, "ghbdtn");
+    assert!(!outcome.pause_autoconvert);
+}
+
+#[test]
+fn consecutive_reverts_pause_autoconvert() {
+    let mut learning = AutoconvertLearning::new(2);
+    learning.record_autoconvert();
+    assert!(!learning.record_revert("one").pause_autoconvert);
+    learning.record_autoconvert();
+    assert!(learning.record_revert("two").pause_autoconvert);
+    // The run starts over after a pause.
+    learning.record_autoconvert();
+    assert!(!learning.record_revert("three").pause_autoconvert);
+}
+
+#[test]
+fn accepted_autoconvert_breaks_the_run() {
+    let mut learning = AutoconvertLearning::new(2);
+    learning.record_autoconvert();
+    let _ = learning.record_revert("one");
+    // Two conversions in a row: the first one was kept by the user.
+    learning.record_autoconvert();
+    learning.record_autoconvert();
+    assert!(!learning.record_revert("two").pause_autoconvert);
+    learning.record_autoconvert();
+    assert!(learning.record_revert("three").pause_autoconvert);
+}
+
+#[test]
+fn zero_threshold_never_pauses() {
+    let mut learning = AutoconvertLearning::new(0);
+    for _ in 0..10 {
+        learning.record_autoconvert();
+        assert!(!learning.record_revert("word").pause_autoconvert);
+    }
+}
@@ -0,0 +1,32 @@
+use std::time::{Duration, Instant};
+
+use crate::domain::text::autoconvert::{SkipReason, TypingTiming, check_typing_rhythm};
+
+fn typed_at(start: Instant, offsets_ms: &[u64]) -> Vec<Instant> {
+    offsets_ms
+        .iter()
+        .map(|ms| start + Duration::from_millis(*ms))
+        .collect()
+}
+
+#[test]
+fn timing_reports_longest_pause() {
+    let timing = TypingTiming::of(typed_at(Instant::now(), &[0, 80, 150, 1400, 1500]));
+    assert_eq!(timing.longest_pause, Duration::from_millis(1250));
+
+    assert_eq!(TypingTiming::of([]), TypingTiming::default());
+}
+
+#[test]
+fn long_pause_inside_a_word_skips_it() {
+    let max_pause = Duration::from_millis(1000);
+    let burst = TypingTiming::of(typed_at(Instant::now(), &[0, 90, 200, 310]));
+    let paused = TypingTiming::of(typed_at(Instant::now(), &[0, 90, 2500, 2600]));
+
+    assert_eq!(check_typing_rhythm(&burst, max_pause), Ok(()));
+    assert_eq!(
+        check_typing_rhythm(&paused, max_pause),
+        Err(SkipReason::PausedMidWord)
+    );
+    assert_eq!(check_typing_rhythm(&paused, Duration::ZERO), Ok(()));
+}
@@ -24,15 +24,14 @@ fn unique_temp_dir(prefix: &str) -> PathBuf {
 }
 
 fn seq_ctrl_a() -> HotkeySequence {
-    HotkeySequence {
-        first: HotkeyChord {
+    HotkeySequence::of(
+        [HotkeyChord {
             mods: MOD_CONTROL.0,
             mods_vks: 0,
-            vk: Some(u32::from(b'A')),
-        },
-        second: None,
-        max_gap_ms: 1000,
-    }
+            key: Some(config::KeyCode::A),
+        }],
+        1000,
+    )
 }
 
 struct AppDataOverride {
@@ -97,3 +96,119 @@ fn config_save_rejects_invalid_sequences() {
     assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
     assert!(err.to_string().contains("unique hotkey sequence"));
 }
+
+#[test]
+fn add_never_convert_word_persists_to_config() {
+    let _env = AppDataOverride::new("appdata-never-convert");
+
+    config::save(&Config::default()).unwrap();
+    let cfg = config::add_never_convert_word("ghbdtn").unwrap();
+    assert_eq!(cfg.autoconvert_exceptions.never_convert, vec!["ghbdtn"]);
+
+    let loaded = config::load().unwrap();
+    assert_eq!(loaded.autoconvert_exceptions, cfg.autoconvert_exceptions);
+}
+
+#[test]
+fn add_never_convert_word_keeps_an_unreadable_config() {
+    let _env = AppDataOverride::new("appdata-never-convert-broken");
+
+    let path = config::config_path().unwrap();
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "{ not json").unwrap();
+
+    assert!(config::add_never_convert_word("ghbdtn").is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "{ not json");
+}
+
+#[test]
+fn config_save_rejects_invalid_exception_patterns() {
+    let _env = AppDataOverride::new("appdata-bad-pattern");
+
+    let mut cfg = Config::default();
+    cfg.autoconvert_exceptions.never_convert_patterns = vec!["regex:(".to_string()];
+
+    let err = config::save(&cfg).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn convert_repeat_mode_roundtrips_via_appdata() {
+    let _env = AppDataOverride::new("appdata-convert-repeat");
+
+    let cfg = Config {
+        convert_repeat: config::ConvertRepeatMode::Extend,
+        convert_repeat_window_ms: 900,
+        ..Default::default()
+    };
+
+    config::save(&cfg).unwrap();
+    let loaded = config::load().unwrap();
+
+    assert_eq!(loaded.convert_repeat, config::ConvertRepeatMode::Extend);
+    assert_eq!(loaded.convert_repeat_window_ms, 900);
+}
+
+#[test]
+fn legacy_two_chord_sequences_still_load() {
+    let json = r#"{"first":{"mods":4,"mods_vks":4,"vk":null},"second":{"mods":4,"mods_vks":4,"vk":null},"max_gap_ms":700}"#;
+    let seq: HotkeySequence = serde_json::from_str(json).unwrap();
+
+    let lshift = HotkeyChord {
+        mods: 4,
+        mods_vks: 4,
+        key: None,
+    };
+    assert_eq!(seq, HotkeySequence::of([lshift; 2], 700));
+
+    let single = r#"{"first":{"mods":0,"vk":20},"second":null,"max_gap_ms":1000}"#;
+    let seq: HotkeySequence = serde_json::from_str(single).unwrap();
+    assert!(seq.is_single());
+}
+
+#[test]
+fn sequences_of_any_length_roundtrip_with_step_gaps() {
+    let json = r#"{"steps":[{"mods":2,"mods_vks":0,"vk":75},{"mods":2,"mods_vks":0,"vk":67,"max_gap_ms":300},{"mods":0,"mods_vks":0,"vk":13}],"max_gap_ms":800}"#;
+    let seq: HotkeySequence = serde_json::from_str(json).unwrap();
+
+    assert_eq!(seq.steps.len(), 3);
+    assert_eq!(seq.gap_before_ms(1), 300);
+    assert_eq!(seq.gap_before_ms(2), 800);
+
+    let saved = serde_json::to_string(&seq).unwrap();
+    assert_eq!(
+        saved,
+        r#"{"steps":[{"mods":2,"mods_vks":0,"key":"K"},{"mods":2,"mods_vks":0,"key":"C","max_gap_ms":300},{"mods":0,"mods_vks":0,"key":"Enter"}],"max_gap_ms":800}"#
+    );
+    assert_eq!(serde_json::from_str::<HotkeySequence>(&saved).unwrap(), seq);
+
+    let text = r#""Ctrl+K, Ctrl+C (within 300 ms), Enter; gap 800 ms""#;
+    assert_eq!(serde_json::from_str::<HotkeySequence>(text).unwrap(), seq);
+}
+
+#[test]
+fn sequences_without_steps_are_rejected() {
+    let json = r#"{"steps":[],"max_gap_ms":800}"#;
+    assert!(serde_json::from_str::<HotkeySequence>(json).is_err());
+}
+
+#[test]
+fn tap_and_hold_steps_roundtrip() {
+    let json =
+        r#"{"steps":[{"mods":0,"mods_vks":0,"vk":20,"press":{"hold":400}}],"max_gap_ms":1000}"#;
+    let hold: HotkeySequence = serde_json::from_str(json).unwrap();
+    assert_eq!(hold.steps[0].press, config::HotkeyPress::Hold(400));
+    let saved = serde_json::to_string(&hold).unwrap();
+    assert_eq!(
+        saved,
+        r#"{"steps":[{"mods":0,"mods_vks":0,"key":"CapsLock","press":{"hold":400}}],"max_gap_ms":1000}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<HotkeySequence>(&saved).unwrap(),
+        hold
+    );
+
+    let tap = r#"{"steps":[{"mods":0,"vk":20,"press":"tap"}],"max_gap_ms":1000}"#;
+    let tap: HotkeySequence = serde_json::from_str(tap).unwrap();
+    assert_eq!(tap.steps[0].press, config::HotkeyPress::Tap);
+}
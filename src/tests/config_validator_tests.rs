@@ -1,7 +1,7 @@
 use windows::Win32::UI::Input::KeyboardAndMouse::{MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN};
 
 use crate::config::{
-    Config, HotkeyChord, HotkeySequence,
+    Config, HotkeyChord, HotkeyPress, HotkeySequence, KeyCode,
     constants::{CONVERT_LAST_WORD, CONVERT_SELECTION, PAUSE, SWITCH_LAYOUT},
 };
 
@@ -9,40 +9,24 @@ fn chord(mods: u32, mods_vks: u32, vk: u32) -> HotkeyChord {
     HotkeyChord {
         mods,
         mods_vks,
-        vk: Some(vk),
+        key: KeyCode::from_win32_vk(vk),
     }
 }
 
 fn seq1(mods: u32, vk: u32) -> HotkeySequence {
-    HotkeySequence {
-        first: chord(mods, 0, vk),
-        second: None,
-        max_gap_ms: 250,
-    }
+    HotkeySequence::of([chord(mods, 0, vk)], 250)
 }
 
 fn seq1_gap(mods: u32, vk: u32, max_gap_ms: u32) -> HotkeySequence {
-    HotkeySequence {
-        first: chord(mods, 0, vk),
-        second: None,
-        max_gap_ms,
-    }
+    HotkeySequence::of([chord(mods, 0, vk)], max_gap_ms)
 }
 
 fn seq1_modsvks(mods: u32, mods_vks: u32, vk: u32) -> HotkeySequence {
-    HotkeySequence {
-        first: chord(mods, mods_vks, vk),
-        second: None,
-        max_gap_ms: 250,
-    }
+    HotkeySequence::of([chord(mods, mods_vks, vk)], 250)
 }
 
 fn seq2(mods1: u32, vk1: u32, mods2: u32, vk2: u32, max_gap_ms: u32) -> HotkeySequence {
-    HotkeySequence {
-        first: chord(mods1, 0, vk1),
-        second: Some(chord(mods2, 0, vk2)),
-        max_gap_ms,
-    }
+    HotkeySequence::of([chord(mods1, 0, vk1), chord(mods2, 0, vk2)], max_gap_ms)
 }
 
 fn mk_cfg(
@@ -343,3 +327,24 @@ fn error_message_includes_only_unique_pairs_once() {
         "expected exactly 2 bullet lines, got {bullets:?}\n{err}"
     );
 }
+
+#[test]
+fn tap_or_hold_on_modifiers_alone_err() {
+    for press in [HotkeyPress::Tap, HotkeyPress::Hold(300)] {
+        let mut seq = seq1(MOD_SHIFT.0, 0);
+        seq.steps[0].press = press;
+
+        let err = assert_err(mk_cfg(None, Some(seq), None, None));
+
+        assert!(err.starts_with("Tap and hold steps need a key:"), "{err}");
+        assert!(err.contains(PAUSE), "{err}");
+    }
+}
+
+#[test]
+fn plain_press_on_modifiers_alone_and_tap_with_a_key_ok() {
+    let mut tap = seq1(MOD_CONTROL.0, u32::from(b'K'));
+    tap.steps[0].press = HotkeyPress::Tap;
+
+    assert_ok(mk_cfg(Some(seq1(MOD_SHIFT.0, 0)), None, Some(tap), None));
+}
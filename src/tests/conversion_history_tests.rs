@@ -0,0 +1,104 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicIsize, Ordering},
+};
+
+use crate::{
+    domain::text::conversion_history::{ConversionHistory, ConversionRecord},
+    input::ring_buffer::{
+        InputJournal, InputRun, LayoutTag, RunKind, RunOrigin, SystemClock, WindowId,
+    },
+};
+
+fn record(original: &str, converted: &str) -> ConversionRecord {
+    ConversionRecord {
+        original_runs: vec![InputRun {
+            text: original.to_string(),
+            layout: LayoutTag::En,
+            origin: RunOrigin::Physical,
+            kind: RunKind::Word,
+            typed: Vec::new(),
+        }],
+        converted: converted.to_string(),
+        suffix_runs: vec![InputRun {
+            text: " ".to_string(),
+            layout: LayoutTag::En,
+            origin: RunOrigin::Physical,
+            kind: RunKind::Whitespace,
+            typed: Vec::new(),
+        }],
+        layout_before: LayoutTag::En,
+        layout_after: LayoutTag::Ru,
+    }
+}
+
+#[test]
+fn pop_returns_most_recent_first() {
+    let mut history = ConversionHistory::new(4);
+    history.push(1, record("ghbdtn", "привет"));
+    history.push(1, record("vbh", "мир"));
+
+    assert_eq!(history.pop(1).unwrap().converted, "мир");
+    assert_eq!(history.pop(1).unwrap().converted, "привет");
+    assert!(history.pop(1).is_none());
+}
+
+#[test]
+fn capacity_drops_oldest_entries() {
+    let mut history = ConversionHistory::new(2);
+    history.push(1, record("a", "ф"));
+    history.push(1, record("b", "и"));
+    history.push(1, record("c", "с"));
+
+    assert_eq!(history.pop(1).unwrap().original_text(), "c");
+    assert_eq!(history.pop(1).unwrap().original_text(), "b");
+    assert!(history.pop(1).is_none());
+}
+
+#[test]
+fn focus_change_invalidates_history() {
+    let mut history = ConversionHistory::new(4);
+    history.push(1, record("ghbdtn", "привет"));
+
+    assert!(history.pop(2).is_none());
+    // Coming back does not resurrect entries.
+    assert!(history.pop(1).is_none());
+
+    history.push(1, record("ghbdtn", "привет"));
+    history.push(2, record("vbh", "мир"));
+    assert_eq!(history.pop(2).unwrap().converted, "мир");
+    assert!(history.pop(2).is_none());
+}
+
+#[test]
+fn leaving_a_window_and_coming_back_drops_its_entries() {
+    let focused = Arc::new(AtomicIsize::new(1));
+    let source = Arc::clone(&focused);
+    let mut journal = InputJournal::with_sources(100, SystemClock, move || {
+        Some(WindowId(source.load(Ordering::Relaxed)))
+    });
+    let mut history = ConversionHistory::new(4);
+
+    journal.observe_window();
+    history.push(journal.focus_visit(), record("ghbdtn", "привет"));
+    journal.observe_window();
+    assert_eq!(
+        history.pop(journal.focus_visit()).unwrap().converted,
+        "привет",
+        "staying in the window keeps the visit"
+    );
+
+    history.push(journal.focus_visit(), record("vbh", "мир"));
+    focused.store(2, Ordering::Relaxed);
+    journal.observe_window();
+    focused.store(1, Ordering::Relaxed);
+    journal.observe_window();
+    assert!(history.pop(journal.focus_visit()).is_none());
+}
+
+#[test]
+fn record_exposes_joined_texts() {
+    let r = record("ghbdtn", "привет");
+    assert_eq!(r.original_text(), "ghbdtn");
+    assert_eq!(r.suffix_text(), " ");
+}
@@ -2,7 +2,10 @@
 
 use windows::Win32::UI::Input::KeyboardAndMouse::{MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN};
 
-use crate::{config, platform::win::hotkey_format::format_hotkey};
+use crate::{
+    config,
+    platform::win::hotkey_format::{format_hotkey, format_hotkey_sequence},
+};
 
 #[test]
 fn format_hotkey_none() {
@@ -13,7 +16,7 @@ fn format_hotkey_none() {
 fn format_hotkey_letter_fast_path() {
     let hk = config::Hotkey {
         mods: MOD_CONTROL.0,
-        vk: u32::from(b'A'),
+        key: Some(config::KeyCode::A),
     };
     let s = format_hotkey(Some(hk));
     assert!(s.contains("Ctrl"));
@@ -24,7 +27,7 @@ fn format_hotkey_letter_fast_path() {
 fn format_hotkey_multiple_mods_fast_path() {
     let hk = config::Hotkey {
         mods: MOD_CONTROL.0 | MOD_SHIFT.0 | MOD_ALT.0 | MOD_WIN.0,
-        vk: u32::from(b'9'),
+        key: Some(config::KeyCode::Digit9),
     };
     let s = format_hotkey(Some(hk));
     assert!(s.contains("Ctrl"));
@@ -33,3 +36,17 @@ fn format_hotkey_multiple_mods_fast_path() {
     assert!(s.contains("Win"));
     assert!(s.contains("9"));
 }
+
+#[test]
+fn format_hotkey_sequence_joins_steps() {
+    let defaults = config::Config::default();
+    assert_eq!(
+        format_hotkey_sequence(defaults.hotkey_pause_sequence.as_ref()),
+        "LShift + RShift"
+    );
+    assert_eq!(
+        format_hotkey_sequence(defaults.hotkey_convert_last_word_sequence.as_ref()),
+        "LShift; LShift"
+    );
+    assert_eq!(format_hotkey_sequence(None), "None");
+}
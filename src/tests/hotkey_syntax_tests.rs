@@ -0,0 +1,189 @@
+use crate::input::{
+    hotkey_sequence::{
+        DEFAULT_MAX_GAP_MS, HotkeyChord, HotkeyPress, HotkeySequence, HotkeyStep,
+        HotkeySyntaxError, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MODVK_LSHIFT, MODVK_RALT, MODVK_RSHIFT,
+    },
+    key_code::KeyCode,
+};
+
+const LSHIFT: HotkeyChord = HotkeyChord {
+    mods: MOD_SHIFT,
+    mods_vks: MODVK_LSHIFT,
+    key: None,
+};
+
+fn parse(text: &str) -> HotkeySequence {
+    text.parse()
+        .unwrap_or_else(|e| panic!("{text:?} does not parse: {e}"))
+}
+
+#[test]
+fn parses_the_documented_examples() {
+    assert_eq!(
+        parse("LShift, LShift"),
+        HotkeySequence::of([LSHIFT; 2], DEFAULT_MAX_GAP_MS)
+    );
+
+    let ctrl_alt_k = parse("Ctrl+Alt+K");
+    assert_eq!(
+        ctrl_alt_k.steps[0].chord,
+        HotkeyChord {
+            mods: MOD_CONTROL | MOD_ALT,
+            mods_vks: 0,
+            key: Some(KeyCode::K),
+        }
+    );
+
+    assert_eq!(
+        parse("LShift+RShift").steps[0].chord,
+        HotkeyChord {
+            mods: MOD_SHIFT,
+            mods_vks: MODVK_LSHIFT | MODVK_RSHIFT,
+            key: None,
+        }
+    );
+
+    assert_eq!(
+        parse("CapsLock").steps[0].chord.key,
+        Some(KeyCode::CapsLock)
+    );
+}
+
+#[test]
+fn canonical_text_roundtrips() {
+    for text in [
+        "LShift, LShift",
+        "Ctrl+Alt+K",
+        "LShift+RShift",
+        "CapsLock",
+        "Ctrl+K, Ctrl+C (within 300 ms), Enter; gap 800 ms",
+        "CapsLock (tap)",
+        "RCtrl (hold 400 ms), RCtrl (within 250 ms)",
+        "Shift+Win+F13",
+        "LCtrl+LAlt+0xE9",
+        "Alt+Comma, Space",
+    ] {
+        assert_eq!(parse(text).to_string(), text);
+    }
+}
+
+#[test]
+fn sequences_roundtrip_through_text() {
+    let sequences = [
+        HotkeySequence::of([LSHIFT; 3], 650),
+        HotkeySequence {
+            steps: vec![
+                HotkeyStep {
+                    chord: HotkeyChord {
+                        mods: 0,
+                        mods_vks: 0,
+                        key: Some(KeyCode::CapsLock),
+                    },
+                    press: HotkeyPress::Hold(400),
+                    max_gap_ms: None,
+                },
+                HotkeyStep {
+                    chord: HotkeyChord {
+                        mods: MOD_CONTROL | MOD_SHIFT,
+                        mods_vks: 0,
+                        key: Some(KeyCode::Slash),
+                    },
+                    press: HotkeyPress::Tap,
+                    max_gap_ms: Some(300),
+                },
+            ],
+            max_gap_ms: DEFAULT_MAX_GAP_MS,
+        },
+    ];
+
+    for seq in sequences {
+        assert_eq!(parse(&seq.to_string()), seq);
+    }
+}
+
+#[test]
+fn generic_modifiers_beside_side_specific_ones_roundtrip() {
+    // AltGr: Windows reports Ctrl, but only the right Alt has a side.
+    let altgr_q = HotkeyChord {
+        mods: MOD_CONTROL | MOD_ALT,
+        mods_vks: MODVK_RALT,
+        key: Some(KeyCode::Q),
+    };
+    assert_eq!(altgr_q.to_string(), "Ctrl+RAlt+Q");
+    assert_eq!("Ctrl+RAlt+Q".parse::<HotkeyChord>(), Ok(altgr_q));
+
+    let seq = HotkeySequence::of([altgr_q, LSHIFT], DEFAULT_MAX_GAP_MS);
+    assert_eq!(parse(&seq.to_string()), seq);
+}
+
+#[test]
+fn empty_chord_roundtrips() {
+    let empty = HotkeyChord {
+        mods: 0,
+        mods_vks: 0,
+        key: None,
+    };
+    assert_eq!(empty.to_string(), "None");
+    assert_eq!("none".parse::<HotkeyChord>(), Ok(empty));
+
+    let seq = HotkeySequence::of([LSHIFT, empty], 700);
+    assert_eq!(seq.to_string(), "LShift, None; gap 700 ms");
+    assert_eq!(parse(&seq.to_string()), seq);
+}
+
+#[test]
+fn accepts_loose_spelling() {
+    assert_eq!(
+        parse("control + alt + k"),
+        parse("Ctrl+Alt+K"),
+        "case and spaces around + do not matter"
+    );
+    assert_eq!(parse("lshift,lshift;gap 1000ms"), parse("LShift, LShift"));
+    assert_eq!(parse("Caps (HOLD 400ms)"), parse("CapsLock (hold 400 ms)"));
+}
+
+#[test]
+fn rejects_malformed_text() {
+    let err = |text: &str| text.parse::<HotkeySequence>().unwrap_err();
+
+    assert_eq!(err(""), HotkeySyntaxError::Empty);
+    assert_eq!(err("Ctrl+, K"), HotkeySyntaxError::Empty);
+    assert_eq!(
+        err("Ctrl+Banana"),
+        HotkeySyntaxError::UnknownKey("Banana".to_string())
+    );
+    assert_eq!(
+        err("K+Ctrl"),
+        HotkeySyntaxError::KeyNotLast("K".to_string())
+    );
+    assert_eq!(err("Shift+LShift+K"), HotkeySyntaxError::MixedModifiers);
+    assert_eq!(
+        err("K (twice)"),
+        HotkeySyntaxError::BadOption("(twice)".to_string())
+    );
+    assert_eq!(
+        err("K; gap soon"),
+        HotkeySyntaxError::BadOption("gap soon".to_string())
+    );
+}
+
+#[test]
+fn config_reads_text_and_numeric_forms_alike() {
+    let text: HotkeySequence = serde_json::from_str(r#""LShift, LShift; gap 700 ms""#).unwrap();
+    let legacy: HotkeySequence = serde_json::from_str(
+        r#"{"first":{"mods":4,"mods_vks":4,"vk":null},"second":{"mods":4,"mods_vks":4,"vk":null},"max_gap_ms":700}"#,
+    )
+    .unwrap();
+
+    assert_eq!(text, legacy);
+    let saved = serde_json::to_string(&text).unwrap();
+    assert_eq!(
+        saved,
+        r#"{"steps":[{"mods":4,"mods_vks":4,"key":null},{"mods":4,"mods_vks":4,"key":null}],"max_gap_ms":700}"#
+    );
+    assert_eq!(
+        serde_json::from_str::<HotkeySequence>(&saved).unwrap(),
+        text
+    );
+    assert!(serde_json::from_str::<HotkeySequence>(r#""Ctrl+""#).is_err());
+}
@@ -0,0 +1,726 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicIsize, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    domain::text::pipeline::InputEvent,
+    input::ring_buffer::{
+        CharKey, InputJournal, InputRun, KeyDecoder, KeyStroke, LayoutTag, ProcessName, RunKind,
+        RunOrigin, SingleWindow, SpanGrowth, SystemClock, WindowId, WindowSource,
+    },
+};
+
+fn type_text(journal: &mut InputJournal, text: &str) {
+    journal.push_text(text, LayoutTag::En, RunOrigin::Physical);
+}
+
+#[test]
+fn capacity_drops_the_oldest_chars() {
+    let mut journal = InputJournal::new(5);
+    assert_eq!(journal.capacity(), 5);
+
+    type_text(&mut journal, "abc def");
+    assert_eq!(journal.text(), "c def");
+}
+
+#[test]
+fn debug_output_leaves_out_the_text() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "hunter2");
+
+    let shown = format!("{journal:?}");
+    assert!(!shown.contains("hunter"), "{shown}");
+    assert!(shown.contains("chars: 7"), "{shown}");
+}
+
+#[test]
+fn large_insert_keeps_only_its_end() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "ghbdtn ");
+    let word = "ф".repeat(50_000);
+    journal.push_runs([
+        InputRun {
+            text: format!("{word} "),
+            layout: LayoutTag::Ru,
+            origin: RunOrigin::Programmatic,
+            kind: RunKind::Word,
+            typed: Vec::new(),
+        },
+        InputRun {
+            text: "vbh".to_string(),
+            layout: LayoutTag::En,
+            origin: RunOrigin::Physical,
+            kind: RunKind::Word,
+            typed: Vec::new(),
+        },
+    ]);
+
+    let text = journal.text();
+    assert_eq!(text.chars().count(), 100);
+    assert!(text.ends_with("фф vbh"));
+    let (run, _) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "vbh");
+    assert_eq!(run.typed.len(), 3);
+}
+
+#[test]
+fn journals_are_independent() {
+    let mut first = InputJournal::new(100);
+    let mut second = InputJournal::new(100);
+    type_text(&mut first, "ghbdtn ");
+    type_text(&mut second, "vbh ");
+
+    let (run, _) = first.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "ghbdtn");
+    assert_eq!(second.text(), "vbh ");
+}
+
+#[test]
+fn input_time_comes_from_the_injected_clock() {
+    let start = Instant::now();
+    let elapsed_ms = Arc::new(AtomicU64::new(0));
+    let clock_ms = Arc::clone(&elapsed_ms);
+    let clock = move || start + Duration::from_millis(clock_ms.load(Ordering::Relaxed));
+    let mut journal = InputJournal::with_sources(100, clock, || Some(WindowId(1)));
+    assert_eq!(journal.last_input_at(), None);
+
+    elapsed_ms.store(250, Ordering::Relaxed);
+    type_text(&mut journal, "a");
+    assert_eq!(
+        journal.last_input_at(),
+        Some(start + Duration::from_millis(250))
+    );
+
+    elapsed_ms.store(900, Ordering::Relaxed);
+    journal.backspace();
+    assert_eq!(
+        journal.last_input_at(),
+        Some(start + Duration::from_millis(900))
+    );
+}
+
+fn manual_clock(start: Instant) -> (Arc<AtomicU64>, impl Fn() -> Instant + Send + 'static) {
+    let elapsed_ms = Arc::new(AtomicU64::new(0));
+    let clock_ms = Arc::clone(&elapsed_ms);
+    let clock = move || start + Duration::from_millis(clock_ms.load(Ordering::Relaxed));
+    (elapsed_ms, clock)
+}
+
+#[test]
+fn char_times_survive_tokenising_and_caret_moves() {
+    let start = Instant::now();
+    let (elapsed_ms, clock) = manual_clock(start);
+    let mut journal = InputJournal::with_sources(100, clock, SingleWindow);
+    for (ms, ch) in [(0, "h"), (100, "e"), (200, "l"), (300, "o")] {
+        elapsed_ms.store(ms, Ordering::Relaxed);
+        type_text(&mut journal, ch);
+    }
+    press(&mut journal, InputJournal::caret_left, 1);
+    elapsed_ms.store(5000, Ordering::Relaxed);
+    type_text(&mut journal, "l");
+    press(&mut journal, InputJournal::caret_right, 1);
+
+    let (run, _) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "hello");
+    let offsets: Vec<u128> = run
+        .typed_at()
+        .map(|at| at.duration_since(start).as_millis())
+        .collect();
+    assert_eq!(offsets, [0, 100, 200, 5000, 300]);
+}
+
+#[test]
+fn pause_in_typing_ends_the_phrase() {
+    let (elapsed_ms, clock) = manual_clock(Instant::now());
+    let mut journal = InputJournal::with_sources(100, clock, SingleWindow);
+    for (ms, text) in [
+        (0, "ok"),
+        (100, " "),
+        (5000, "ghbdtn"),
+        (5100, " "),
+        (5200, "vbh"),
+    ] {
+        elapsed_ms.store(ms, Ordering::Relaxed);
+        type_text(&mut journal, text);
+    }
+
+    let (phrase, _) = journal.take_last_phrase_with_suffix().expect("phrase");
+    let phrase: String = phrase.iter().map(|run| run.text.as_str()).collect();
+    assert_eq!(phrase, "ghbdtn vbh");
+    assert_eq!(
+        journal.text(),
+        "ok ",
+        "the earlier burst stays in the journal"
+    );
+}
+
+#[test]
+fn idle_journal_is_forgotten() {
+    let (elapsed_ms, clock) = manual_clock(Instant::now());
+    let mut journal = InputJournal::with_sources(100, clock, SingleWindow);
+    journal.set_idle_expiry(Duration::from_secs(10));
+    type_text(&mut journal, "ghbdtn ");
+
+    elapsed_ms.store(9_000, Ordering::Relaxed);
+    assert!(!journal.expire_if_idle());
+    type_text(&mut journal, "vbh ");
+
+    elapsed_ms.store(20_000, Ordering::Relaxed);
+    assert!(journal.take_last_layout_run_with_suffix().is_none());
+    assert_eq!(journal.last_input_at(), None);
+
+    journal.set_idle_expiry(Duration::ZERO);
+    type_text(&mut journal, "ok ");
+    elapsed_ms.store(1_000_000, Ordering::Relaxed);
+    assert!(!journal.expire_if_idle());
+    assert_eq!(journal.text(), "ok ");
+}
+
+fn switchable_window() -> (
+    Arc<AtomicIsize>,
+    impl Fn() -> Option<WindowId> + Send + 'static,
+) {
+    let focused = Arc::new(AtomicIsize::new(1));
+    let source = Arc::clone(&focused);
+    let window = move || match source.load(Ordering::Relaxed) {
+        0 => None,
+        raw => Some(WindowId(raw)),
+    };
+    (focused, window)
+}
+
+#[test]
+fn focus_change_puts_the_text_aside_until_the_window_returns() {
+    let (focused, window) = switchable_window();
+    let mut journal = InputJournal::with_sources(100, SystemClock, window);
+
+    journal.observe_window();
+    type_text(&mut journal, "abc");
+    journal.observe_window();
+    assert_eq!(journal.text(), "abc");
+
+    focused.store(2, Ordering::Relaxed);
+    journal.observe_window();
+    assert_eq!(journal.text(), "");
+    type_text(&mut journal, "def");
+
+    focused.store(1, Ordering::Relaxed);
+    journal.observe_window();
+    assert_eq!(journal.text(), "abc");
+
+    focused.store(0, Ordering::Relaxed);
+    journal.observe_window();
+    assert_eq!(journal.text(), "");
+    focused.store(2, Ordering::Relaxed);
+    journal.observe_window();
+    assert_eq!(journal.text(), "def");
+}
+
+#[test]
+fn untracked_input_stops_trusting_background_windows() {
+    let (focused, window) = switchable_window();
+    let mut journal = InputJournal::with_sources(100, SystemClock, window);
+    journal.observe_window();
+    type_text(&mut journal, "abc");
+
+    focused.store(2, Ordering::Relaxed);
+    journal.observe_window();
+    journal.untracked_input();
+    assert_eq!(journal.saved_windows(), 0);
+
+    focused.store(1, Ordering::Relaxed);
+    journal.observe_window();
+    assert_eq!(journal.text(), "");
+}
+
+#[test]
+fn only_the_most_recent_windows_are_kept() {
+    let (focused, window) = switchable_window();
+    let mut journal = InputJournal::with_sources(100, SystemClock, window);
+    journal.set_window_slots(2);
+    journal.observe_window();
+    for id in 1..=3 {
+        focused.store(id, Ordering::Relaxed);
+        journal.observe_window();
+        type_text(&mut journal, &id.to_string());
+    }
+    focused.store(4, Ordering::Relaxed);
+    journal.observe_window();
+    assert_eq!(journal.saved_windows(), 2);
+
+    focused.store(1, Ordering::Relaxed);
+    journal.observe_window();
+    assert_eq!(journal.text(), "");
+    focused.store(3, Ordering::Relaxed);
+    journal.observe_window();
+    assert_eq!(journal.text(), "3");
+}
+
+fn press(journal: &mut InputJournal, key: fn(&mut InputJournal), times: usize) {
+    for _ in 0..times {
+        key(journal);
+    }
+}
+
+#[test]
+fn typing_after_caret_left_inserts_before_known_text() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "helo");
+    press(&mut journal, InputJournal::caret_left, 1);
+    type_text(&mut journal, "l");
+
+    assert_eq!(journal.text(), "hello");
+    assert_eq!(journal.chars_after_caret(), 1);
+
+    press(&mut journal, InputJournal::caret_right, 1);
+    assert_eq!(journal.chars_after_caret(), 0);
+    let (run, _) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "hello");
+}
+
+#[test]
+fn caret_leaving_known_text_clears_the_journal() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "ab");
+    press(&mut journal, InputJournal::caret_left, 3);
+    assert_eq!(journal.text(), "");
+
+    type_text(&mut journal, "ab");
+    press(&mut journal, InputJournal::caret_right, 1);
+    assert_eq!(journal.text(), "");
+}
+
+#[test]
+fn delete_removes_the_char_after_the_caret() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "abxc");
+    press(&mut journal, InputJournal::caret_left, 2);
+    journal.delete_forward();
+    assert_eq!(journal.text(), "abc");
+
+    press(&mut journal, InputJournal::caret_right, 1);
+    journal.delete_forward();
+    assert_eq!(journal.text(), "abc");
+}
+
+#[test]
+fn word_at_caret_is_taken_whole() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "ghbdn vbh");
+    press(&mut journal, InputJournal::caret_left, 5);
+    type_text(&mut journal, "t");
+
+    assert_eq!(journal.move_caret_to_word_end(), 1);
+    let (run, suffix) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "ghbdtn");
+    assert!(suffix.is_empty());
+
+    journal.push_text("привет", LayoutTag::Ru, RunOrigin::Programmatic);
+    assert_eq!(journal.text(), "привет vbh");
+    assert_eq!(journal.move_caret_to_word_end(), 0);
+}
+
+#[test]
+fn capacity_counts_text_after_the_caret() {
+    let mut journal = InputJournal::new(4);
+    type_text(&mut journal, "abcd");
+    press(&mut journal, InputJournal::caret_left, 4);
+    type_text(&mut journal, "x");
+    assert_eq!(journal.text(), "xabc");
+}
+
+#[test]
+fn ctrl_backspace_erases_a_mixed_layout_word_and_the_space_after_it() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "one ghb");
+    journal.push_text("вет  ", LayoutTag::Ru, RunOrigin::Physical);
+
+    journal.delete_word_backward();
+    assert_eq!(journal.text(), "one ");
+    journal.delete_word_backward();
+    assert_eq!(journal.text(), "");
+}
+
+#[test]
+fn ctrl_backspace_stops_at_punctuation() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "word, it's");
+
+    journal.delete_word_backward();
+    assert_eq!(journal.text(), "word, ");
+    journal.delete_word_backward();
+    assert_eq!(journal.text(), "word");
+
+    type_text(&mut journal, " vbh");
+    let (run, _) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "vbh");
+}
+
+#[test]
+fn ctrl_delete_erases_the_word_after_the_caret() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "one ");
+    journal.push_text("два", LayoutTag::Ru, RunOrigin::Physical);
+    type_text(&mut journal, "  three");
+
+    journal.delete_word_forward();
+    assert_eq!(journal.text(), "one два  three");
+
+    press(&mut journal, InputJournal::caret_left, 10);
+    journal.delete_word_forward();
+    assert_eq!(journal.text(), "one three");
+    assert_eq!(journal.chars_after_caret(), 5);
+}
+
+const fn stroke(vk: u16, shift: bool) -> KeyStroke {
+    KeyStroke {
+        vk,
+        scan_code: vk,
+        shift,
+        altgr: false,
+        caps_lock: false,
+    }
+}
+
+fn press_key(journal: &mut InputJournal, text: &str, stroke: KeyStroke) {
+    let key = CharKey::Pressed {
+        stroke,
+        dead_key: None,
+    };
+    journal.push_key_press(text, LayoutTag::En, key);
+}
+
+/// Russian ЙЦУКЕН for the keys the tests press, with `Shift+7` on a dead key and the rouble
+/// sign on `AltGr+8`.
+struct RussianKeys;
+
+impl KeyDecoder for RussianKeys {
+    fn decode(&self, stroke: KeyStroke, dead_key: Option<KeyStroke>) -> Option<String> {
+        if stroke.altgr {
+            return (stroke.vk == 0x38).then(|| "₽".to_string());
+        }
+        let base = match stroke.vk {
+            0x47 => 'п',
+            0x48 => 'р',
+            0x42 => 'и',
+            0xBC => 'б',
+            _ => return None,
+        };
+        let ch = if stroke.shift {
+            base.to_uppercase().next()?
+        } else {
+            base
+        };
+        Some(match dead_key {
+            Some(_) => format!("{ch}\u{301}"),
+            None => ch.to_string(),
+        })
+    }
+}
+
+#[test]
+fn key_presses_are_decoded_again_in_another_layout() {
+    let mut journal = InputJournal::new(100);
+    press_key(&mut journal, "G", stroke(0x47, true));
+    press_key(&mut journal, "h", stroke(0x48, false));
+    press_key(&mut journal, "b", stroke(0x42, false));
+    press_key(&mut journal, ",", stroke(0xBC, false));
+    press_key(&mut journal, " ", stroke(0x20, false));
+
+    let (run, _) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "Ghb,");
+    assert_eq!(run.redecode(&RussianKeys).as_deref(), Some("Приб"));
+
+    let dead = KeyStroke {
+        altgr: true,
+        ..stroke(0xDE, false)
+    };
+    journal.push_key_press(
+        "h",
+        LayoutTag::En,
+        CharKey::Pressed {
+            stroke: stroke(0x48, false),
+            dead_key: Some(dead),
+        },
+    );
+    let (run, _) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.redecode(&RussianKeys).as_deref(), Some("р\u{301}"));
+}
+
+#[test]
+fn altgr_symbols_are_decoded_again() {
+    let mut journal = InputJournal::new(100);
+    press_key(&mut journal, "g", stroke(0x47, false));
+    let altgr_8 = KeyStroke {
+        altgr: true,
+        ..stroke(0x38, false)
+    };
+    press_key(&mut journal, "∞", altgr_8);
+
+    let (run, _) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "g∞");
+    assert_eq!(run.redecode(&RussianKeys).as_deref(), Some("п₽"));
+}
+
+#[test]
+fn inserted_text_is_not_decoded_again() {
+    let mut journal = InputJournal::new(100);
+    press_key(&mut journal, "g", stroke(0x47, false));
+    journal.push_text("h", LayoutTag::En, RunOrigin::Physical);
+
+    let (run, _) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "gh");
+    assert_eq!(run.redecode(&RussianKeys), None);
+}
+
+#[test]
+fn one_press_typing_several_chars_is_decoded_once() {
+    let mut journal = InputJournal::new(100);
+    press_key(&mut journal, "gh", stroke(0x47, false));
+    press_key(&mut journal, "b", stroke(0x42, false));
+    journal.backspace();
+    press_key(&mut journal, "b", stroke(0x42, false));
+
+    let (run, _) = journal.take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "ghb");
+    assert_eq!(run.redecode(&RussianKeys).as_deref(), Some("пи"));
+}
+
+/// Window 3 is a password field, window 4 belongs to KeePass and the process of window 5 is
+/// being looked up.
+struct PrivateWindows(Arc<AtomicIsize>);
+
+impl WindowSource for PrivateWindows {
+    fn current_window(&self) -> Option<WindowId> {
+        Some(WindowId(self.0.load(Ordering::Relaxed)))
+    }
+
+    fn is_secure_input(&self) -> bool {
+        self.0.load(Ordering::Relaxed) == 3
+    }
+
+    fn process_name(&self) -> ProcessName {
+        let name = match self.0.load(Ordering::Relaxed) {
+            4 => "KeePass.exe",
+            5 => return ProcessName::Pending,
+            _ => "notepad.exe",
+        };
+        ProcessName::Known(name.to_string())
+    }
+}
+
+fn private_windows_journal() -> (Arc<AtomicIsize>, InputJournal) {
+    let focused = Arc::new(AtomicIsize::new(1));
+    let mut journal =
+        InputJournal::with_sources(100, SystemClock, PrivateWindows(Arc::clone(&focused)));
+    journal.set_blocked_processes(&["keepass.exe".to_string()]);
+    journal.observe_window();
+    (focused, journal)
+}
+
+#[test]
+fn password_fields_are_not_journalled() {
+    let (focused, mut journal) = private_windows_journal();
+    type_text(&mut journal, "login ");
+
+    focused.store(3, Ordering::Relaxed);
+    journal.observe_window();
+    assert!(journal.is_suppressed());
+    type_text(&mut journal, "hunter2");
+    journal.apply(&InputEvent::Text {
+        text: "x".to_string(),
+        layout: LayoutTag::En,
+        key: CharKey::Unknown,
+        origin: RunOrigin::Physical,
+    });
+    assert_eq!(journal.text(), "");
+
+    focused.store(1, Ordering::Relaxed);
+    journal.observe_window();
+    assert!(!journal.is_suppressed());
+    assert_eq!(journal.text(), "login ");
+    type_text(&mut journal, "ok");
+    assert_eq!(journal.text(), "login ok");
+}
+
+#[test]
+fn blocked_processes_are_not_journalled() {
+    let (focused, mut journal) = private_windows_journal();
+
+    focused.store(4, Ordering::Relaxed);
+    journal.observe_window();
+    type_text(&mut journal, "secret");
+    assert!(journal.is_suppressed());
+    assert_eq!(journal.text(), "");
+
+    focused.store(2, Ordering::Relaxed);
+    journal.observe_window();
+    type_text(&mut journal, "plain");
+    assert_eq!(journal.text(), "plain");
+
+    journal.set_blocked_processes(&[]);
+    focused.store(4, Ordering::Relaxed);
+    journal.observe_window();
+    assert!(!journal.is_suppressed());
+}
+
+#[test]
+fn windows_are_blocked_until_their_process_is_known() {
+    let (focused, mut journal) = private_windows_journal();
+
+    focused.store(5, Ordering::Relaxed);
+    journal.observe_window();
+    assert!(journal.is_suppressed());
+
+    journal.set_blocked_processes(&[]);
+    journal.recheck_privacy();
+    assert!(!journal.is_suppressed());
+}
+
+/// Window 1, whose field is a password field while the flag is set.
+struct ChangingField(Arc<AtomicBool>);
+
+impl WindowSource for ChangingField {
+    fn current_window(&self) -> Option<WindowId> {
+        Some(WindowId(1))
+    }
+
+    fn is_secure_input(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[test]
+fn privacy_is_rechecked_while_the_focus_stays() {
+    let secret = Arc::new(AtomicBool::new(false));
+    let mut journal =
+        InputJournal::with_sources(100, SystemClock, ChangingField(Arc::clone(&secret)));
+    journal.observe_window();
+    type_text(&mut journal, "login ");
+
+    secret.store(true, Ordering::Relaxed);
+    journal.observe_window();
+    assert!(!journal.is_suppressed(), "the focus did not change");
+    journal.recheck_privacy();
+    assert!(journal.is_suppressed());
+    assert_eq!(journal.text(), "");
+
+    secret.store(false, Ordering::Relaxed);
+    journal.recheck_privacy();
+    assert!(!journal.is_suppressed());
+}
+
+/// Journal holding `typed`, then `shown` as put there by a Convert press, then a space.
+fn journal_after_conversion(typed: &str, shown: &str) -> InputJournal {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, typed);
+    journal.push_text(shown, LayoutTag::Ru, RunOrigin::Programmatic);
+    type_text(&mut journal, " ");
+    journal
+}
+
+fn grown_word(growth: SpanGrowth) -> (String, String) {
+    let SpanGrowth::Grown { word, gap } = growth else {
+        panic!("expected a word before the span, got {growth:?}");
+    };
+    (
+        word.text.clone(),
+        gap.iter().map(|run| run.text.as_str()).collect(),
+    )
+}
+
+#[test]
+fn repeated_extend_takes_one_word_per_press() {
+    let mut journal = journal_after_conversion("ytn ghbdtn ", "мир");
+
+    let taken = grown_word(journal.take_word_before_span("мир", " "));
+    assert_eq!(taken, ("ghbdtn".to_string(), " ".to_string()));
+    assert_eq!(journal.text(), "ytn ");
+    journal.push_text("привет мир", LayoutTag::Ru, RunOrigin::Programmatic);
+    type_text(&mut journal, " ");
+
+    let taken = grown_word(journal.take_word_before_span("привет мир", " "));
+    assert_eq!(taken, ("ytn".to_string(), " ".to_string()));
+    assert_eq!(journal.text(), "");
+    journal.push_text("нет привет мир", LayoutTag::Ru, RunOrigin::Programmatic);
+    type_text(&mut journal, " ");
+
+    assert_eq!(
+        journal.take_word_before_span("нет привет мир", " "),
+        SpanGrowth::Boundary,
+        "nothing is left before the span"
+    );
+    assert_eq!(journal.text(), "нет привет мир ");
+}
+
+#[test]
+fn extend_stops_at_a_line_break() {
+    let mut journal = journal_after_conversion("ghbdtn\n", "мир");
+
+    assert_eq!(
+        journal.take_word_before_span("мир", " "),
+        SpanGrowth::Boundary
+    );
+    assert_eq!(
+        journal.text(),
+        "ghbdtn\nмир ",
+        "the journal is left as it was"
+    );
+}
+
+#[test]
+fn detached_copy_changes_the_journal_only_when_reattached() {
+    let mut journal = InputJournal::new(100);
+    type_text(&mut journal, "ghbdtn x");
+    press(&mut journal, InputJournal::caret_left, 1);
+
+    let mut detached = journal.detach();
+    assert!(detached.take_tail_if_matches("ghbdtn "));
+    detached.push_text("привет ", LayoutTag::Ru, RunOrigin::Programmatic);
+    assert_eq!(journal.text(), "ghbdtn x");
+
+    journal.reattach(detached);
+    assert_eq!(journal.text(), "привет x");
+    assert_eq!(journal.chars_after_caret(), 1);
+}
+
+#[test]
+fn failed_extend_leaves_the_caret_and_idle_time_alone() {
+    let start = Instant::now();
+    let (elapsed_ms, clock) = manual_clock(start);
+    let mut journal = InputJournal::with_sources(100, clock, SingleWindow);
+    journal.set_idle_expiry(Duration::from_secs(10));
+    type_text(&mut journal, "ghbdtn\n");
+    journal.push_text("мир", LayoutTag::Ru, RunOrigin::Programmatic);
+    type_text(&mut journal, " x");
+    press(&mut journal, InputJournal::caret_left, 1);
+    elapsed_ms.store(5000, Ordering::Relaxed);
+
+    assert_eq!(
+        journal.take_word_before_span("мир", " "),
+        SpanGrowth::Boundary
+    );
+    assert_eq!(journal.text(), "ghbdtn\nмир x");
+    assert_eq!(journal.chars_after_caret(), 1);
+    elapsed_ms.store(12_000, Ordering::Relaxed);
+    assert!(
+        journal.expire_if_idle(),
+        "the failed extend is not input and does not delay the expiry"
+    );
+}
+
+#[test]
+fn typing_after_the_conversion_resets_extend() {
+    let mut journal = journal_after_conversion("ghbdtn ", "мир");
+    type_text(&mut journal, "f");
+
+    assert_eq!(
+        journal.take_word_before_span("мир", " "),
+        SpanGrowth::Changed
+    );
+    assert_eq!(journal.text(), "ghbdtn мир f");
+}
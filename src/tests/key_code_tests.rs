@@ -0,0 +1,80 @@
+use crate::input::{hotkey_sequence::HotkeyChord, key_code::KeyCode};
+
+#[test]
+fn every_win32_virtual_key_roundtrips() {
+    for vk in 1..=0xFE {
+        let key = KeyCode::from_win32_vk(vk).unwrap();
+        assert_eq!(key.win32_vk(), vk, "vk 0x{vk:02X}");
+        assert_eq!(key.to_string().parse(), Ok(key), "vk 0x{vk:02X}");
+    }
+    assert_eq!(KeyCode::from_win32_vk(0), None);
+    assert_eq!(KeyCode::from_win32_vk(0xFF), None);
+}
+
+#[test]
+fn named_keys_convert_to_evdev_and_x11() {
+    for vk in 1..=0xFE {
+        let key = KeyCode::from_win32_vk(vk).unwrap();
+        if let KeyCode::Other(_) = key {
+            assert_eq!(key.evdev(), None);
+            assert_eq!(key.x11_keysym(), None);
+            continue;
+        }
+        let evdev = key.evdev().unwrap();
+        let keysym = key.x11_keysym().unwrap();
+        assert_eq!(KeyCode::from_evdev(evdev), Some(key), "{key}");
+        assert_eq!(KeyCode::from_x11_keysym(keysym), Some(key), "{key}");
+    }
+}
+
+#[test]
+fn conversions_use_the_platform_codes() {
+    // KEY_A, KEY_CAPSLOCK and KEY_LEFTSHIFT in `input-event-codes.h`.
+    assert_eq!(KeyCode::A.evdev(), Some(30));
+    assert_eq!(KeyCode::CapsLock.evdev(), Some(58));
+    assert_eq!(KeyCode::from_evdev(42), Some(KeyCode::LShift));
+
+    // XK_a, XK_Caps_Lock and XK_F1.
+    assert_eq!(KeyCode::A.x11_keysym(), Some(0x61));
+    assert_eq!(KeyCode::from_x11_keysym(0x41), Some(KeyCode::A));
+    assert_eq!(KeyCode::from_x11_keysym(0xFFE5), Some(KeyCode::CapsLock));
+    assert_eq!(KeyCode::F1.x11_keysym(), Some(0xFFBE));
+
+    assert_eq!(KeyCode::from_win32_vk(0x14), Some(KeyCode::CapsLock));
+    assert_eq!(KeyCode::Slash.win32_vk(), 0xBF);
+}
+
+#[test]
+fn keys_are_saved_by_name_and_read_from_numbers() {
+    assert_eq!(
+        serde_json::to_string(&KeyCode::CapsLock).unwrap(),
+        r#""CapsLock""#
+    );
+    assert_eq!(
+        serde_json::to_string(&KeyCode::Other(0xE9)).unwrap(),
+        r#""0xE9""#
+    );
+    assert_eq!(
+        serde_json::from_str::<KeyCode>(r#""capslock""#).unwrap(),
+        KeyCode::CapsLock
+    );
+    assert_eq!(
+        serde_json::from_str::<KeyCode>("20").unwrap(),
+        KeyCode::CapsLock
+    );
+    assert!(serde_json::from_str::<KeyCode>("0").is_err());
+    assert!(serde_json::from_str::<KeyCode>(r#""Banana""#).is_err());
+}
+
+#[test]
+fn numeric_chords_migrate_to_key_codes() {
+    let old: HotkeyChord = serde_json::from_str(r#"{"mods":2,"mods_vks":0,"vk":75}"#).unwrap();
+    let new: HotkeyChord = serde_json::from_str(r#"{"mods":2,"mods_vks":0,"key":"K"}"#).unwrap();
+    assert_eq!(old, new);
+    assert_eq!(old.key, Some(KeyCode::K));
+
+    let modifier_only: HotkeyChord = serde_json::from_str(r#"{"mods":4,"vk":null}"#).unwrap();
+    assert_eq!(modifier_only.key, None);
+    let zero: HotkeyChord = serde_json::from_str(r#"{"mods":4,"vk":0}"#).unwrap();
+    assert_eq!(zero.key, None);
+}
@@ -1,9 +1,13 @@
 #![cfg(windows)]
 
-use crate::{config, platform::win::keyboard::sequence::chord_matches};
+use crate::{config, input::sequence_matcher::chord_matches};
 
 fn ch(mods: u32, mods_vks: u32, vk: Option<u32>) -> config::HotkeyChord {
-    config::HotkeyChord { mods, mods_vks, vk }
+    config::HotkeyChord {
+        mods,
+        mods_vks,
+        key: vk.and_then(config::KeyCode::from_win32_vk),
+    }
 }
 
 #[test]
@@ -37,3 +41,59 @@ fn chord_matches_vk_some_requires_exact() {
     let i = ch(1, 0, Some(66));
     assert!(!chord_matches(t, i));
 }
+
+#[test]
+fn capture_keeps_the_last_chords_pressed_in_a_row() {
+    use crate::platform::win::keyboard::capture::{MAX_CAPTURED_STEPS, push_chord_capture};
+
+    let mut last_tick = 0;
+    let mut seq = push_chord_capture(None, ch(0, 0, Some(65)), 1000, &mut last_tick);
+    for (i, vk) in (66..70).enumerate() {
+        let now = 1100 + 100 * i as u64;
+        seq = push_chord_capture(Some(&seq), ch(0, 0, Some(vk)), now, &mut last_tick);
+    }
+    let keys: Vec<_> = seq.steps.iter().map(|step| step.chord.key).collect();
+    assert_eq!(keys.len(), MAX_CAPTURED_STEPS);
+    assert_eq!(keys.last(), Some(&Some(config::KeyCode::E)));
+
+    let restarted = push_chord_capture(Some(&seq), ch(0, 0, Some(70)), 10_000, &mut last_tick);
+    assert!(restarted.is_single());
+}
+
+#[test]
+fn step_matches_requires_the_same_kind_of_press() {
+    use crate::input::sequence_matcher::step_matches;
+
+    let caps = ch(0, 0, Some(20));
+    let step = |press| config::HotkeyStep {
+        chord: caps,
+        press,
+        max_gap_ms: None,
+    };
+
+    assert!(step_matches(
+        step(config::HotkeyPress::Down),
+        caps,
+        config::HotkeyPress::Down
+    ));
+    assert!(!step_matches(
+        step(config::HotkeyPress::Tap),
+        caps,
+        config::HotkeyPress::Down
+    ));
+    assert!(step_matches(
+        step(config::HotkeyPress::Tap),
+        caps,
+        config::HotkeyPress::Tap
+    ));
+    assert!(!step_matches(
+        step(config::HotkeyPress::Hold(400)),
+        caps,
+        config::HotkeyPress::Hold(399)
+    ));
+    assert!(step_matches(
+        step(config::HotkeyPress::Hold(400)),
+        caps,
+        config::HotkeyPress::Hold(450)
+    ));
+}
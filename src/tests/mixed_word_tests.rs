@@ -0,0 +1,52 @@
+use crate::{
+    domain::text::mixed_word::repair_mixed_word,
+    input::ring_buffer::{InputRun, LayoutTag, RunKind, RunOrigin},
+};
+
+fn run(text: &str, layout: LayoutTag) -> InputRun {
+    InputRun {
+        text: text.to_string(),
+        layout,
+        origin: RunOrigin::Physical,
+        kind: RunKind::Word,
+        typed: Vec::new(),
+    }
+}
+
+#[test]
+fn converts_only_the_prefix_typed_before_the_switch() {
+    let repair =
+        repair_mixed_word(&[run("ghb", LayoutTag::En), run("вет", LayoutTag::Ru)]).expect("repair");
+    assert_eq!(repair.typed, "ghbвет");
+    assert_eq!(repair.repaired, "привет");
+    assert_eq!(repair.as_typed_in_prefix_layout, "ghbdtn");
+    assert_eq!(repair.layout, LayoutTag::Ru);
+
+    let repair =
+        repair_mixed_word(&[run("руд", LayoutTag::Ru), run("lo", LayoutTag::En)]).expect("repair");
+    assert_eq!(repair.repaired, "hello");
+}
+
+#[test]
+fn single_layout_and_punctuation_tails_are_not_mixed_words() {
+    assert_eq!(repair_mixed_word(&[run("ghbdtn", LayoutTag::En)]), None);
+    assert_eq!(
+        repair_mixed_word(&[run("hello", LayoutTag::En), run(",", LayoutTag::Ru)]),
+        None
+    );
+    assert_eq!(
+        repair_mixed_word(&[run("ghb", LayoutTag::Unknown), run("вет", LayoutTag::Ru)]),
+        None
+    );
+}
+
+#[test]
+fn prefix_with_several_layouts_is_not_repaired() {
+    let runs = [
+        run("ab", LayoutTag::En),
+        run("вг", LayoutTag::Ru),
+        run("cd", LayoutTag::En),
+        run("еж", LayoutTag::Ru),
+    ];
+    assert_eq!(repair_mixed_word(&runs), None);
+}
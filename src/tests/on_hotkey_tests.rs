@@ -2,8 +2,9 @@ use windows::Win32::Foundation::WPARAM;
 
 use crate::{
     input::hotkeys::{
-        HK_CONVERT_LAST_WORD_ID, HK_CONVERT_SELECTION_ID, HK_PAUSE_TOGGLE_ID, HK_SWITCH_LAYOUT_ID,
-        HotkeyAction, action_from_id,
+        HK_CONVERT_LAST_PHRASE_ID, HK_CONVERT_LAST_WORD_ID, HK_CONVERT_SELECTION_ID,
+        HK_PAUSE_TOGGLE_ID, HK_SWITCH_LAYOUT_ID, HK_UNDO_CONVERSION_ID, HotkeyAction,
+        action_from_id,
     },
     platform::win::{hotkey_action_from_wparam, hotkey_id_from_wparam},
 };
@@ -46,6 +47,14 @@ fn action_from_id_known_values() {
         action_from_id(HK_SWITCH_LAYOUT_ID),
         Some(HotkeyAction::SwitchLayout)
     );
+    assert_eq!(
+        action_from_id(HK_UNDO_CONVERSION_ID),
+        Some(HotkeyAction::UndoConversion)
+    );
+    assert_eq!(
+        action_from_id(HK_CONVERT_LAST_PHRASE_ID),
+        Some(HotkeyAction::ConvertLastPhrase)
+    );
 }
 
 #[test]
@@ -0,0 +1,53 @@
+use crate::{
+    domain::text::{
+        autoconvert::SkipReason,
+        mapping::{ConversionDirection, convert_ru_en_with_direction},
+        phrase::convert_phrase,
+    },
+    input::ring_buffer::phrase_start,
+};
+
+fn to_ru(_: usize, text: &str) -> String {
+    convert_ru_en_with_direction(text, ConversionDirection::EnToRu)
+}
+
+#[test]
+fn phrase_starts_after_line_break_and_sentence_end() {
+    assert_eq!(phrase_start("ghbdtn vbh"), 0);
+    assert_eq!(phrase_start("first line\n  ghbdtn"), 13);
+    assert_eq!(phrase_start("Done. Ghbdtn vbh"), 6);
+    assert_eq!(phrase_start("Done!\tNext"), 6);
+}
+
+#[test]
+fn wrong_layout_punctuation_does_not_end_the_phrase() {
+    // `ltkf.` is `делаю` typed on the English layout.
+    assert_eq!(phrase_start("ghbdtn ltkf. rfr"), 0);
+    assert_eq!(phrase_start("ok?"), 0);
+}
+
+#[test]
+fn convert_phrase_converts_only_rejected_tokens_and_keeps_whitespace() {
+    let out = convert_phrase("hello  ghbdtn\tvbh", to_ru, |word, _| {
+        if word == "hello" {
+            Err(SkipReason::AlreadyCorrect)
+        } else {
+            Ok(())
+        }
+    });
+    assert_eq!(out.as_deref(), Some("hello  привет\tмир"));
+}
+
+#[test]
+fn short_tokens_follow_their_neighbours() {
+    let decide = |word: &str, _: &str| match word {
+        "z" => Err(SkipReason::TooShort),
+        "ljvf" => Ok(()),
+        _ => Err(SkipReason::AlreadyCorrect),
+    };
+    assert_eq!(
+        convert_phrase("z ljvf", to_ru, decide).as_deref(),
+        Some("я дома")
+    );
+    assert_eq!(convert_phrase("z hello", to_ru, decide), None);
+}
@@ -1,4 +1,6 @@
-use crate::input::ring_buffer::{self, InputRun, LayoutTag, RunKind, RunOrigin};
+use crate::input::ring_buffer::{
+    self, AutoconvertRevert, InputJournal, InputRun, LayoutTag, RunKind, RunOrigin,
+};
 
 #[test]
 fn run_journal_merges_contiguous_same_metadata() {
@@ -7,13 +9,15 @@ fn run_journal_merges_contiguous_same_metadata() {
         text: "ab".to_string(),
         layout: LayoutTag::En,
         origin: RunOrigin::Physical,
-        kind: RunKind::Text,
+        kind: RunKind::Word,
+        typed: Vec::new(),
     });
     ring_buffer::push_run(InputRun {
         text: "cd".to_string(),
         layout: LayoutTag::En,
         origin: RunOrigin::Physical,
-        kind: RunKind::Text,
+        kind: RunKind::Word,
+        typed: Vec::new(),
     });
 
     let runs = ring_buffer::runs_snapshot();
@@ -28,13 +32,15 @@ fn run_journal_splits_on_layout_change() {
         text: "ABC".to_string(),
         layout: LayoutTag::En,
         origin: RunOrigin::Physical,
-        kind: RunKind::Text,
+        kind: RunKind::Word,
+        typed: Vec::new(),
     });
     ring_buffer::push_run(InputRun {
         text: ",".to_string(),
         layout: LayoutTag::Ru,
         origin: RunOrigin::Physical,
-        kind: RunKind::Text,
+        kind: RunKind::Punctuation,
+        typed: Vec::new(),
     });
 
     let runs = ring_buffer::runs_snapshot();
@@ -50,13 +56,15 @@ fn take_last_layout_run_with_suffix_basic() {
         text: "hello".to_string(),
         layout: LayoutTag::En,
         origin: RunOrigin::Physical,
-        kind: RunKind::Text,
+        kind: RunKind::Word,
+        typed: Vec::new(),
     });
     ring_buffer::push_run(InputRun {
         text: "   ".to_string(),
         layout: LayoutTag::En,
         origin: RunOrigin::Physical,
         kind: RunKind::Whitespace,
+        typed: Vec::new(),
     });
 
     let (run, suffix) = ring_buffer::take_last_layout_run_with_suffix().expect("payload");
@@ -92,13 +100,15 @@ fn last_char_triggers_autoconvert_still_works_across_runs() {
         text: "abc".to_string(),
         layout: LayoutTag::En,
         origin: RunOrigin::Physical,
-        kind: RunKind::Text,
+        kind: RunKind::Word,
+        typed: Vec::new(),
     });
     ring_buffer::push_run(InputRun {
         text: ".".to_string(),
         layout: LayoutTag::En,
         origin: RunOrigin::Physical,
-        kind: RunKind::Text,
+        kind: RunKind::Punctuation,
+        typed: Vec::new(),
     });
     assert!(ring_buffer::last_char_triggers_autoconvert());
 
@@ -107,6 +117,7 @@ fn last_char_triggers_autoconvert_still_works_across_runs() {
         layout: LayoutTag::En,
         origin: RunOrigin::Physical,
         kind: RunKind::Whitespace,
+        typed: Vec::new(),
     });
     assert!(ring_buffer::last_char_triggers_autoconvert());
 }
@@ -123,10 +134,180 @@ fn backspace_removes_from_last_run_and_drops_empty_run() {
 fn foreground_invalidation_state_reset_via_invalidate() {
     ring_buffer::invalidate();
     ring_buffer::push_text("abc");
-    ring_buffer::mark_last_token_autoconverted();
+    ring_buffer::mark_last_token_autoconverted(sample_revert());
     assert!(ring_buffer::last_token_autoconverted());
 
     ring_buffer::invalidate();
     assert!(!ring_buffer::last_token_autoconverted());
+    assert!(!ring_buffer::request_autoconvert_revert());
     assert!(ring_buffer::take_last_layout_run_with_suffix().is_none());
 }
+
+fn sample_revert() -> AutoconvertRevert {
+    AutoconvertRevert {
+        original: InputRun {
+            text: "ghbdtn".to_string(),
+            layout: LayoutTag::En,
+            origin: RunOrigin::Physical,
+            kind: RunKind::Word,
+            typed: Vec::new(),
+        },
+        converted: InputRun {
+            text: "привет".to_string(),
+            layout: LayoutTag::Ru,
+            origin: RunOrigin::Programmatic,
+            kind: RunKind::Word,
+            typed: Vec::new(),
+        },
+        suffix_runs: vec![InputRun {
+            text: " ".to_string(),
+            layout: LayoutTag::En,
+            origin: RunOrigin::Physical,
+            kind: RunKind::Whitespace,
+            typed: Vec::new(),
+        }],
+        switched_layout: true,
+    }
+}
+
+#[test]
+fn autoconvert_revert_is_claimed_once_and_handed_over() {
+    ring_buffer::invalidate();
+    ring_buffer::mark_last_token_autoconverted(sample_revert());
+
+    assert!(ring_buffer::request_autoconvert_revert());
+    assert!(!ring_buffer::request_autoconvert_revert());
+    assert_eq!(
+        ring_buffer::take_requested_autoconvert_revert(),
+        Some(sample_revert())
+    );
+    assert_eq!(ring_buffer::take_requested_autoconvert_revert(), None);
+}
+
+#[test]
+fn take_tail_if_matches_spans_runs_and_keeps_mismatch() {
+    ring_buffer::invalidate();
+    ring_buffer::push_text("one привет ");
+
+    assert!(!ring_buffer::take_tail_if_matches("пока "));
+    assert!(ring_buffer::take_tail_if_matches("привет "));
+
+    let runs = ring_buffer::runs_snapshot();
+    let text: String = runs.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(text, "one ");
+}
+
+#[test]
+fn take_last_phrase_stops_at_sentence_end() {
+    ring_buffer::invalidate();
+    ring_buffer::push_text_with_meta("Ok. ", LayoutTag::En, RunOrigin::Physical);
+    ring_buffer::push_text_with_meta("Ghbdtn vbh ", LayoutTag::En, RunOrigin::Physical);
+
+    let (runs, suffix) = ring_buffer::take_last_phrase_with_suffix().expect("payload");
+    let text: String = runs.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(text, "Ghbdtn vbh");
+    assert_eq!(suffix.len(), 1);
+    assert_eq!(suffix[0].text, " ");
+
+    let rest: String = ring_buffer::runs_snapshot()
+        .iter()
+        .map(|r| r.text.as_str())
+        .collect();
+    assert_eq!(rest, "Ok. ");
+}
+
+#[test]
+fn recent_text_runs_stop_at_line_break() {
+    ring_buffer::invalidate();
+    ring_buffer::push_text_with_meta("old\n", LayoutTag::En, RunOrigin::Physical);
+    ring_buffer::push_text_with_meta("я ", LayoutTag::Ru, RunOrigin::Physical);
+    ring_buffer::push_text_with_meta("дома ", LayoutTag::Ru, RunOrigin::Programmatic);
+
+    let runs = ring_buffer::recent_text_runs(4);
+    let words: Vec<&str> = runs.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(words, ["я", "дома"]);
+    assert_eq!(ring_buffer::recent_text_runs(1)[0].text, "дома");
+}
+
+fn tokens() -> Vec<(String, RunKind)> {
+    ring_buffer::runs_snapshot()
+        .into_iter()
+        .map(|r| (r.text.clone(), r.kind))
+        .collect()
+}
+
+fn type_keys(text: &str) {
+    for ch in text.chars() {
+        ring_buffer::push_text_with_meta(&ch.to_string(), LayoutTag::En, RunOrigin::Physical);
+    }
+}
+
+#[test]
+fn journal_tokens_follow_word_boundaries() {
+    ring_buffer::invalidate();
+    type_keys("it's 42 word, a+b");
+
+    let expected = [
+        ("it's", RunKind::Word),
+        (" ", RunKind::Whitespace),
+        ("42", RunKind::Digit),
+        (" ", RunKind::Whitespace),
+        ("word", RunKind::Word),
+        (",", RunKind::Punctuation),
+        (" ", RunKind::Whitespace),
+        ("a", RunKind::Word),
+        ("+", RunKind::Symbol),
+        ("b", RunKind::Word),
+    ];
+    let expected: Vec<(String, RunKind)> = expected
+        .iter()
+        .map(|(text, kind)| ((*text).to_string(), *kind))
+        .collect();
+    assert_eq!(tokens(), expected);
+}
+
+#[test]
+fn backspace_resegments_the_last_word() {
+    ring_buffer::invalidate();
+    type_keys("it's");
+    ring_buffer::test_backspace();
+
+    assert_eq!(
+        tokens(),
+        [
+            ("it".to_string(), RunKind::Word),
+            ("'".to_string(), RunKind::Punctuation)
+        ]
+    );
+}
+
+#[test]
+fn last_word_spans_hyphen_and_wrong_layout_punctuation() {
+    ring_buffer::invalidate();
+    type_keys("ckjdj-ckjdj ghbdtn, ");
+
+    let (run, suffix) = ring_buffer::take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "ghbdtn,");
+    assert_eq!(run.kind, RunKind::Word);
+    assert_eq!(suffix[0].text, " ");
+
+    ring_buffer::test_backspace();
+    let (run, _) = ring_buffer::take_last_layout_run_with_suffix().expect("payload");
+    assert_eq!(run.text, "ckjdj-ckjdj");
+}
+
+#[test]
+fn autoconvert_trigger_chars_are_configurable() {
+    let mut journal = InputJournal::new(ring_buffer::DEFAULT_CAPACITY);
+    journal.set_trigger_chars("-");
+    journal.push_text("ghbdtn;", LayoutTag::En, RunOrigin::Physical);
+    assert!(!journal.last_char_triggers_autoconvert());
+
+    journal.clear();
+    journal.push_text("ghbdtn-", LayoutTag::En, RunOrigin::Physical);
+    assert!(journal.last_char_triggers_autoconvert());
+    journal.push_text("x", LayoutTag::En, RunOrigin::Physical);
+    assert!(!journal.last_char_triggers_autoconvert());
+    journal.push_text(" ", LayoutTag::En, RunOrigin::Physical);
+    assert!(journal.last_char_triggers_autoconvert());
+}
@@ -0,0 +1,204 @@
+use SequenceEvent::{Advanced, Completed, Unmatched};
+
+use crate::input::{
+    hotkey_sequence::{HotkeyChord, HotkeyPress, HotkeySequence},
+    sequence_matcher::{SequenceEvent, SequenceMatcher},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Convert,
+    Switch,
+}
+
+fn chord(text: &str) -> HotkeyChord {
+    text.parse().unwrap()
+}
+
+fn seq(text: &str) -> HotkeySequence {
+    text.parse().unwrap()
+}
+
+/// Feeds key-down chords at the given times and collects what each one did.
+fn feed_all(
+    matcher: &mut SequenceMatcher<Action>,
+    presses: &[(&str, u64)],
+) -> Vec<SequenceEvent<Action>> {
+    presses
+        .iter()
+        .map(|(text, ms)| matcher.feed(chord(text), HotkeyPress::Down, *ms))
+        .collect()
+}
+
+#[test]
+fn sequence_completes_within_the_gap() {
+    let mut matcher = SequenceMatcher::new([(Action::Convert, seq("Ctrl+K, Ctrl+C"))]);
+
+    assert_eq!(
+        feed_all(&mut matcher, &[("Ctrl+K", 0), ("Ctrl+C", 1000)]),
+        [Advanced, Completed(Action::Convert)]
+    );
+    assert_eq!(
+        feed_all(&mut matcher, &[("Ctrl+C", 1100)]),
+        [Unmatched],
+        "a completed sequence starts over"
+    );
+}
+
+#[test]
+fn pause_longer_than_the_gap_resets_progress() {
+    let mut matcher = SequenceMatcher::new([(Action::Convert, seq("LShift, LShift; gap 300 ms"))]);
+
+    assert_eq!(
+        feed_all(&mut matcher, &[("LShift", 0), ("LShift", 301)]),
+        [Advanced, Advanced],
+        "the late press starts the sequence again"
+    );
+    assert_eq!(
+        feed_all(&mut matcher, &[("LShift", 600)]),
+        [Completed(Action::Convert)]
+    );
+}
+
+#[test]
+fn step_gap_overrides_the_sequence_gap() {
+    let mut matcher = SequenceMatcher::new([(
+        Action::Convert,
+        seq("Ctrl+K, Ctrl+C (within 200 ms), Enter"),
+    )]);
+
+    assert_eq!(
+        feed_all(
+            &mut matcher,
+            &[("Ctrl+K", 0), ("Ctrl+C", 250), ("Enter", 300)]
+        ),
+        [Advanced, Unmatched, Unmatched]
+    );
+    assert_eq!(
+        feed_all(
+            &mut matcher,
+            &[("Ctrl+K", 1000), ("Ctrl+C", 1200), ("Enter", 2100)]
+        ),
+        [Advanced, Advanced, Completed(Action::Convert)]
+    );
+}
+
+#[test]
+fn repeated_first_chord_restarts_the_match() {
+    let mut matcher = SequenceMatcher::new([(Action::Convert, seq("Ctrl+K, Ctrl+C"))]);
+
+    assert_eq!(
+        feed_all(
+            &mut matcher,
+            &[("Ctrl+K", 0), ("Ctrl+K", 100), ("Ctrl+C", 200)]
+        ),
+        [Advanced, Advanced, Completed(Action::Convert)]
+    );
+}
+
+#[test]
+fn broken_match_falls_back_to_the_repeated_prefix() {
+    let mut matcher = SequenceMatcher::new([(Action::Convert, seq("A, A, B"))]);
+
+    assert_eq!(
+        feed_all(
+            &mut matcher,
+            &[("A", 0), ("A", 100), ("A", 200), ("B", 300)]
+        ),
+        [Advanced, Advanced, Advanced, Completed(Action::Convert)]
+    );
+}
+
+#[test]
+fn broken_tap_match_falls_back_to_the_repeated_prefix() {
+    let mut matcher = SequenceMatcher::new([(Action::Switch, seq("A (tap), A (tap), B (tap)"))]);
+    let (a, b) = (chord("A"), chord("B"));
+
+    for (i, ms) in [0, 100, 200].into_iter().enumerate() {
+        assert_eq!(matcher.feed(a, HotkeyPress::Down, ms), Unmatched);
+        assert_eq!(
+            matcher.feed(a, HotkeyPress::Tap, ms + 50),
+            Advanced,
+            "tap {i}"
+        );
+    }
+    assert_eq!(matcher.feed(b, HotkeyPress::Down, 300), Unmatched);
+    assert_eq!(
+        matcher.feed(b, HotkeyPress::Tap, 350),
+        Completed(Action::Switch)
+    );
+}
+
+#[test]
+fn other_chord_resets_a_partial_match() {
+    let mut matcher = SequenceMatcher::new([(Action::Convert, seq("Ctrl+K, Ctrl+C"))]);
+
+    assert_eq!(
+        feed_all(&mut matcher, &[("Ctrl+K", 0), ("A", 100), ("Ctrl+C", 200)]),
+        [Advanced, Unmatched, Unmatched]
+    );
+}
+
+#[test]
+fn slots_keep_their_progress_through_chords_taken_by_others() {
+    let mut matcher = SequenceMatcher::new([
+        (Action::Switch, seq("LShift, LShift")),
+        (Action::Convert, seq("Ctrl+K, Ctrl+C")),
+    ]);
+
+    assert_eq!(
+        feed_all(
+            &mut matcher,
+            &[
+                ("Ctrl+K", 0),
+                ("LShift", 100),
+                ("Ctrl+C", 200),
+                ("LShift", 300),
+            ]
+        ),
+        [Advanced, Advanced, Completed(Action::Convert), Advanced],
+        "Ctrl+C resets the LShift sequence before the Ctrl+K one takes it"
+    );
+}
+
+#[test]
+fn earlier_binding_wins_a_shared_chord() {
+    let mut matcher = SequenceMatcher::new([
+        (Action::Switch, seq("CapsLock")),
+        (Action::Convert, seq("CapsLock, CapsLock")),
+    ]);
+
+    assert_eq!(
+        feed_all(&mut matcher, &[("CapsLock", 0), ("CapsLock", 100)]),
+        [Completed(Action::Switch), Completed(Action::Switch)]
+    );
+}
+
+#[test]
+fn key_down_of_a_tap_step_waits_for_the_release() {
+    let mut matcher = SequenceMatcher::new([(Action::Convert, seq("Ctrl+K, CapsLock (tap)"))]);
+    let caps = chord("CapsLock");
+
+    assert_eq!(
+        matcher.feed(chord("Ctrl+K"), HotkeyPress::Down, 0),
+        Advanced
+    );
+    assert_eq!(matcher.feed(caps, HotkeyPress::Down, 100), Unmatched);
+    assert_eq!(
+        matcher.feed(caps, HotkeyPress::Tap, 150),
+        Completed(Action::Convert)
+    );
+}
+
+#[test]
+fn hold_matches_once_held_long_enough() {
+    let mut matcher = SequenceMatcher::new([(Action::Convert, seq("CapsLock (hold 400 ms)"))]);
+    let caps = chord("CapsLock");
+
+    assert_eq!(matcher.feed(caps, HotkeyPress::Hold(399), 399), Unmatched);
+    assert_eq!(
+        matcher.feed(caps, HotkeyPress::Hold(400), 400),
+        Completed(Action::Convert)
+    );
+    assert!(!matcher.feed(caps, HotkeyPress::Tap, 500).is_consumed());
+}
@@ -0,0 +1,185 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    domain::text::{
+        autoconvert::{AutoconvertExceptions, ExceptionRules},
+        pipeline::{AutoconvertSettings, Effect, HotkeyCommand, InputEvent, TypingPipeline},
+        session::{SessionEntry, SessionRecorder, SessionSettings, parse_session, replay},
+    },
+    input::ring_buffer::{CharKey, Focus, InputJournal, LayoutTag, RunOrigin, WindowId},
+};
+
+const TYPING_SESSION: &str = include_str!("data/sessions/typing_session.jsonl");
+
+#[test]
+fn recorded_sessions_replay_to_the_same_decisions() {
+    let lines = parse_session(TYPING_SESSION).expect("session should parse");
+    let replay = replay(&lines);
+
+    assert_eq!(replay.recorded.len(), 5);
+    assert!(
+        replay.matches_recording(),
+        "recorded: {:?}\nreplayed: {:?}",
+        replay.recorded,
+        replay.replayed
+    );
+}
+
+fn focus(window: isize) -> InputEvent {
+    InputEvent::FocusChanged(Some(Focus {
+        window: WindowId(window),
+        control: None,
+    }))
+}
+
+fn typed(text: &str, origin: RunOrigin) -> Vec<InputEvent> {
+    let event = |text: String| InputEvent::Text {
+        text,
+        layout: LayoutTag::En,
+        key: CharKey::Unknown,
+        origin,
+    };
+    match origin {
+        RunOrigin::Physical => text.chars().map(|ch| event(ch.to_string())).collect(),
+        RunOrigin::Programmatic => vec![event(text.to_string())],
+    }
+}
+
+#[test]
+fn recorder_output_replays_to_the_recorded_decisions() {
+    let started = Instant::now();
+    let mut recorder = SessionRecorder::start(Vec::new(), started, &SessionSettings::default())
+        .expect("writing to memory");
+    let mut pipeline = TypingPipeline::new(InputJournal::new(100), AutoconvertSettings::default());
+
+    let mut events = vec![focus(0x5000)];
+    events.extend(typed("ghbdtn ", RunOrigin::Physical));
+    events.extend([InputEvent::Backspace, focus(0x7000), focus(0x5000)]);
+
+    for (i, event) in events.iter().enumerate() {
+        let at = started + Duration::from_millis(100 * i as u64);
+        recorder.record_event(event, at).expect("writing to memory");
+        let _ = pipeline.handle(event);
+        if let Some(decision) = pipeline.take_decision() {
+            recorder
+                .record_decision(&decision, at)
+                .expect("writing to memory");
+        }
+    }
+
+    let src = String::from_utf8(recorder.into_inner()).expect("utf-8");
+    let lines = parse_session(&src).expect("recorded session should parse");
+    let windows: Vec<_> = lines
+        .iter()
+        .filter_map(|(_, line)| match line.entry {
+            SessionEntry::FocusChanged { window } => Some(window),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(windows, [Some(0), Some(1), Some(0)]);
+
+    let replay = replay(&lines);
+    assert_eq!(replay.recorded.len(), 1);
+    assert_eq!(replay.recorded[0].verdict, "convert");
+    assert!(replay.matches_recording());
+}
+
+#[test]
+fn recorded_settings_and_origins_replay_to_the_same_decisions() {
+    let started = Instant::now();
+    let mut session = SessionSettings {
+        pause_after_reverts: 1,
+        ..SessionSettings::default()
+    };
+    session.exceptions.never_convert.push("ntrcn".to_string());
+    let mut recorder =
+        SessionRecorder::start(Vec::new(), started, &session).expect("writing to memory");
+    let mut exceptions = session.exceptions.clone();
+    let settings = AutoconvertSettings {
+        exceptions: ExceptionRules::compile(&exceptions).expect("valid lists"),
+        ..AutoconvertSettings::default()
+    };
+    let mut pipeline = TypingPipeline::new(InputJournal::new(100), settings);
+    pipeline.set_pause_after_reverts(session.pause_after_reverts);
+
+    let mut events = vec![focus(0x5000)];
+    events.extend(typed("Ok. ", RunOrigin::Programmatic));
+    events.extend(typed("ntrcn ghbdtn ", RunOrigin::Physical));
+    // The revert pauses autoconvert after one revert, and the hotkey turns it back on.
+    events.extend([
+        InputEvent::Backspace,
+        InputEvent::Hotkey(HotkeyCommand::ToggleAutoconvert),
+    ]);
+    events.extend(typed(" ghbdtn ", RunOrigin::Physical));
+
+    for (i, event) in events.iter().enumerate() {
+        let at = started + Duration::from_millis(100 * i as u64);
+        recorder.record_event(event, at).expect("writing to memory");
+        for effect in pipeline.handle(event) {
+            if let Effect::NeverConvert(word) = &effect
+                && exceptions.add_never_convert(word)
+            {
+                pipeline.settings_mut().exceptions =
+                    ExceptionRules::compile(&exceptions).expect("valid lists");
+            }
+        }
+        if let Some(decision) = pipeline.take_decision() {
+            recorder
+                .record_decision(&decision, at)
+                .expect("writing to memory");
+        }
+    }
+
+    let src = String::from_utf8(recorder.into_inner()).expect("utf-8");
+    let lines = parse_session(&src).expect("recorded session should parse");
+    assert_eq!(lines[0].1.entry, SessionEntry::Start(session));
+    assert!(lines.iter().any(|(_, line)| matches!(
+        line.entry,
+        SessionEntry::Text {
+            origin: RunOrigin::Programmatic,
+            ..
+        }
+    )));
+
+    let replay = replay(&lines);
+    let verdicts: Vec<_> = replay
+        .recorded
+        .iter()
+        .map(|decision| (decision.word.as_str(), decision.verdict.as_str()))
+        .collect();
+    assert_eq!(
+        verdicts,
+        [
+            ("ntrcn", "user_never_convert"),
+            ("ghbdtn", "convert"),
+            ("ghbdtn", "user_never_convert"),
+        ]
+    );
+    assert!(
+        replay.matches_recording(),
+        "recorded: {:?}\nreplayed: {:?}",
+        replay.recorded,
+        replay.replayed
+    );
+}
+
+#[test]
+fn sessions_without_exception_lists_still_parse() {
+    let src = "{\"ms\":0,\"type\":\"start\",\"context_weight\":0.5,\"max_pause_in_word_ms\":0,\"trigger_chars\":\".\"}\n";
+    let lines = parse_session(src).expect("older start line");
+    let SessionEntry::Start(settings) = &lines[0].1.entry else {
+        panic!("start line expected");
+    };
+    assert_eq!(settings.exceptions, AutoconvertExceptions::default());
+    assert_eq!(
+        settings.pause_after_reverts,
+        SessionSettings::default().pause_after_reverts
+    );
+}
+
+#[test]
+fn parse_errors_name_the_line() {
+    let src = "{\"ms\":0,\"type\":\"backspace\"}\n\n{\"ms\":5,\"type\":\"warp\"}\n";
+    let err = parse_session(src).expect_err("unknown type");
+    assert_eq!(err.line, 3);
+}
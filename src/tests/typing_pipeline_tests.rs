@@ -0,0 +1,507 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use lingua::{Language, LanguageDetectorBuilder};
+
+use crate::{
+    domain::text::{
+        autoconvert::{ExceptionRules, should_autoconvert_word},
+        pipeline::{
+            AutoconvertNotice, AutoconvertSettings, ConvertRepeatMode, Effect, HotkeyCommand,
+            InputEvent, LayoutConverter, Navigation, TypingPipeline, TypingState, convert_typed,
+        },
+    },
+    input::ring_buffer::{
+        CharKey, Focus, InputJournal, InputRun, LayoutTag, RunOrigin, SingleWindow, WindowId,
+    },
+};
+
+fn pipeline() -> TypingPipeline {
+    TypingPipeline::new(InputJournal::new(100), AutoconvertSettings::default())
+}
+
+fn key(text: &str, layout: LayoutTag) -> InputEvent {
+    InputEvent::Text {
+        text: text.to_string(),
+        layout,
+        key: CharKey::Unknown,
+        origin: RunOrigin::Physical,
+    }
+}
+
+/// Types `text` char by char and returns all effects.
+fn type_text(pipeline: &mut TypingPipeline, text: &str, layout: LayoutTag) -> Vec<Effect> {
+    text.chars()
+        .flat_map(|ch| pipeline.handle(&key(&ch.to_string(), layout)))
+        .collect()
+}
+
+/// Pipeline with autoconvert off, for the hotkey commands.
+fn manual_pipeline() -> TypingPipeline {
+    let mut pipeline = pipeline();
+    pipeline.handle(&InputEvent::Hotkey(HotkeyCommand::ToggleAutoconvert));
+    pipeline
+}
+
+fn hotkey(pipeline: &mut TypingPipeline, command: HotkeyCommand) -> Vec<Effect> {
+    pipeline.handle(&InputEvent::Hotkey(command))
+}
+
+fn detector_ru_en() -> lingua::LanguageDetector {
+    LanguageDetectorBuilder::from_languages(&[Language::Russian, Language::English])
+        .with_minimum_relative_distance(0.20)
+        .build()
+}
+
+fn replace(span_len: usize, suffix: &str, replacement: &str) -> Effect {
+    Effect::ReplaceSpan {
+        span_len,
+        suffix: suffix.to_string(),
+        replacement: replacement.to_string(),
+    }
+}
+
+#[test]
+fn wrong_layout_word_is_replaced_when_it_ends() {
+    let mut pipeline = pipeline();
+
+    assert_eq!(type_text(&mut pipeline, "ghbdtn", LayoutTag::En), []);
+    assert_eq!(
+        type_text(&mut pipeline, " ", LayoutTag::En),
+        [
+            replace(6, " ", "привет"),
+            Effect::SwitchLayout(LayoutTag::Ru)
+        ]
+    );
+    assert_eq!(pipeline.journal().text(), "привет ");
+
+    assert_eq!(type_text(&mut pipeline, "мир ", LayoutTag::Ru), []);
+    assert_eq!(pipeline.journal().text(), "привет мир ");
+}
+
+#[test]
+fn backspace_right_after_autoconvert_reverts_it() {
+    let mut pipeline = pipeline();
+    type_text(&mut pipeline, "ghbdtn ", LayoutTag::En);
+
+    assert_eq!(
+        pipeline.handle(&InputEvent::Backspace),
+        [
+            replace(6, " ", "ghbdtn"),
+            Effect::SwitchLayout(LayoutTag::En),
+            Effect::NeverConvert("ghbdtn".to_string()),
+        ]
+    );
+    assert_eq!(pipeline.journal().text(), "ghbdtn ");
+
+    // The revert is claimed once; the next Backspace edits the text.
+    assert_eq!(pipeline.handle(&InputEvent::Backspace), []);
+    assert_eq!(pipeline.journal().text(), "ghbdtn");
+}
+
+#[test]
+fn revert_is_only_offered_for_the_next_key() {
+    let mut pipeline = pipeline();
+    type_text(&mut pipeline, "ghbdtn ", LayoutTag::En);
+    pipeline.handle(&InputEvent::Navigate(Navigation::CaretLeft));
+    pipeline.handle(&InputEvent::Navigate(Navigation::CaretRight));
+
+    assert_eq!(pipeline.handle(&InputEvent::Backspace), []);
+    assert_eq!(pipeline.journal().text(), "привет");
+}
+
+#[test]
+fn tick_before_the_backspace_keeps_the_revert() {
+    let mut pipeline = pipeline();
+    type_text(&mut pipeline, "ghbdtn ", LayoutTag::En);
+    pipeline.handle(&InputEvent::Tick);
+
+    assert_eq!(
+        pipeline.handle(&InputEvent::Backspace),
+        [
+            replace(6, " ", "ghbdtn"),
+            Effect::SwitchLayout(LayoutTag::En),
+            Effect::NeverConvert("ghbdtn".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn consecutive_reverts_pause_autoconvert() {
+    let mut pipeline = pipeline();
+    pipeline.set_pause_after_reverts(2);
+
+    type_text(&mut pipeline, "ghbdtn ", LayoutTag::En);
+    let first = pipeline.handle(&InputEvent::Backspace);
+    assert!(!first.contains(&Effect::Notify(AutoconvertNotice::Paused)));
+
+    type_text(&mut pipeline, "ghbdtn ", LayoutTag::En);
+    let second = pipeline.handle(&InputEvent::Backspace);
+    assert_eq!(
+        second.last(),
+        Some(&Effect::Notify(AutoconvertNotice::Paused))
+    );
+    assert!(!pipeline.autoconvert_enabled());
+
+    assert_eq!(type_text(&mut pipeline, "ghbdtn ", LayoutTag::En), []);
+}
+
+#[test]
+fn toggle_hotkey_switches_autoconvert() {
+    let mut pipeline = pipeline();
+
+    assert_eq!(
+        pipeline.handle(&InputEvent::Hotkey(HotkeyCommand::ToggleAutoconvert)),
+        [Effect::Notify(AutoconvertNotice::Disabled)]
+    );
+    assert_eq!(type_text(&mut pipeline, "ghbdtn ", LayoutTag::En), []);
+
+    assert_eq!(
+        pipeline.handle(&InputEvent::Hotkey(HotkeyCommand::ToggleAutoconvert)),
+        [Effect::Notify(AutoconvertNotice::Enabled)]
+    );
+    assert_eq!(type_text(&mut pipeline, "ghbdtn ", LayoutTag::En).len(), 2);
+}
+
+#[test]
+fn convert_hotkey_converts_without_a_decision() {
+    let mut pipeline = pipeline();
+    pipeline.handle(&InputEvent::Hotkey(HotkeyCommand::ToggleAutoconvert));
+    type_text(&mut pipeline, "hello", LayoutTag::En);
+
+    assert_eq!(
+        pipeline.handle(&InputEvent::Hotkey(HotkeyCommand::ConvertLastWord)),
+        [replace(5, "", "руддщ"), Effect::SwitchLayout(LayoutTag::Ru)]
+    );
+    assert_eq!(pipeline.journal().text(), "руддщ");
+}
+
+#[test]
+fn mixed_layout_word_keeps_the_layout() {
+    let mut pipeline = pipeline();
+    type_text(&mut pipeline, "ghb", LayoutTag::En);
+    type_text(&mut pipeline, "вет", LayoutTag::Ru);
+
+    assert_eq!(
+        type_text(&mut pipeline, " ", LayoutTag::Ru),
+        [replace(6, " ", "привет")]
+    );
+    assert_eq!(pipeline.journal().text(), "привет ");
+}
+
+#[test]
+fn pause_inside_a_word_skips_autoconvert() {
+    let start = Instant::now();
+    let elapsed_ms = Arc::new(AtomicU64::new(0));
+    let clock_ms = Arc::clone(&elapsed_ms);
+    let clock = move || start + Duration::from_millis(clock_ms.load(Ordering::Relaxed));
+    let settings = AutoconvertSettings {
+        max_pause_in_word: Duration::from_millis(1000),
+        ..AutoconvertSettings::default()
+    };
+    let mut pipeline = TypingPipeline::new(
+        InputJournal::with_sources(100, clock, SingleWindow),
+        settings,
+    );
+
+    type_text(&mut pipeline, "ghb", LayoutTag::En);
+    elapsed_ms.store(5000, Ordering::Relaxed);
+    assert_eq!(type_text(&mut pipeline, "dtn ", LayoutTag::En), []);
+    assert_eq!(pipeline.journal().text(), "ghbdtn ");
+}
+
+#[test]
+fn line_break_after_the_word_skips_autoconvert() {
+    let mut pipeline = pipeline();
+    type_text(&mut pipeline, "ghbdtn", LayoutTag::En);
+
+    assert_eq!(type_text(&mut pipeline, "\n", LayoutTag::En), []);
+    assert_eq!(pipeline.journal().text(), "ghbdtn\n");
+}
+
+#[test]
+fn revert_that_cannot_run_keeps_the_backspace() {
+    let mut journal = InputJournal::new(100);
+    let mut state = TypingState::default();
+    for ch in "ghbdtn ".chars() {
+        state.handle(&mut journal, &key(&ch.to_string(), LayoutTag::En));
+    }
+    assert!(journal.request_autoconvert_revert());
+    journal.push_text("!", LayoutTag::Ru, RunOrigin::Physical);
+
+    assert_eq!(state.revert_autoconvert(&mut journal), [replace(1, "", "")]);
+    assert_eq!(journal.text(), "привет ");
+}
+
+#[test]
+fn punctuation_converts_by_the_known_layout() {
+    assert_eq!(convert_typed(",.", LayoutTag::Ru, || None), "?/");
+    assert_eq!(convert_typed(",.", LayoutTag::En, || None), "бю");
+    // A lone punctuation char has no letters to tell the direction, the layout decides.
+    assert_eq!(convert_typed(".", LayoutTag::Ru, || None), "/");
+    assert_eq!(convert_typed(".", LayoutTag::En, || None), "ю");
+}
+
+#[test]
+fn decision_converts_mistyped_words_only() {
+    let detector = detector_ru_en();
+    let rules = ExceptionRules::default();
+
+    assert!(should_autoconvert_word(&detector, &rules, "ghbdtn", "привет").is_ok());
+    assert!(should_autoconvert_word(&detector, &rules, "ghbdtn,", "приветб").is_ok());
+    assert!(should_autoconvert_word(&detector, &rules, "привет", "ghbdtn").is_err());
+}
+
+#[test]
+fn convert_hotkey_converts_trailing_punctuation_with_the_word() {
+    let mut pipeline = manual_pipeline();
+    type_text(&mut pipeline, "ghbdtn,", LayoutTag::En);
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord),
+        [
+            replace(7, "", "приветб"),
+            Effect::SwitchLayout(LayoutTag::Ru)
+        ]
+    );
+}
+
+#[test]
+fn convert_hotkey_spans_words_and_keeps_the_suffix() {
+    let mut pipeline = manual_pipeline();
+    type_text(&mut pipeline, "ghbdtn rjynhjkm  ", LayoutTag::En);
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord),
+        [
+            replace(15, "  ", "привет контроль"),
+            Effect::SwitchLayout(LayoutTag::Ru)
+        ]
+    );
+    assert_eq!(pipeline.journal().text(), "привет контроль  ");
+}
+
+#[test]
+fn convert_hotkey_moves_the_caret_to_the_word_end() {
+    let mut pipeline = manual_pipeline();
+    type_text(&mut pipeline, "hello", LayoutTag::En);
+    pipeline.handle(&InputEvent::Navigate(Navigation::CaretLeft));
+    pipeline.handle(&InputEvent::Navigate(Navigation::CaretLeft));
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord),
+        [
+            Effect::MoveCaretRight(2),
+            replace(5, "", "руддщ"),
+            Effect::SwitchLayout(LayoutTag::Ru)
+        ]
+    );
+}
+
+#[test]
+fn repeated_convert_cycles_back_to_the_original() {
+    let mut pipeline = manual_pipeline();
+    type_text(&mut pipeline, "ghbdtn rjynhjkm", LayoutTag::En);
+    hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord);
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord),
+        [
+            replace(15, "", "ghbdtn rjynhjkm"),
+            Effect::SwitchLayout(LayoutTag::En)
+        ]
+    );
+    assert_eq!(pipeline.journal().text(), "ghbdtn rjynhjkm");
+}
+
+#[test]
+fn convert_after_typing_and_erasing_starts_over() {
+    let mut pipeline = manual_pipeline();
+    type_text(&mut pipeline, "ghbdtn", LayoutTag::En);
+    hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord);
+    type_text(&mut pipeline, "x", LayoutTag::Ru);
+    pipeline.handle(&InputEvent::Backspace);
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord),
+        [
+            replace(6, "", "ghbdtn"),
+            Effect::SwitchLayout(LayoutTag::En)
+        ]
+    );
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord),
+        [
+            replace(6, "", "привет"),
+            Effect::SwitchLayout(LayoutTag::Ru)
+        ]
+    );
+}
+
+#[test]
+fn convert_after_the_repeat_window_starts_over() {
+    let start = Instant::now();
+    let elapsed_ms = Arc::new(AtomicU64::new(0));
+    let clock_ms = Arc::clone(&elapsed_ms);
+    let clock = move || start + Duration::from_millis(clock_ms.load(Ordering::Relaxed));
+    let mut pipeline = TypingPipeline::new(
+        InputJournal::with_sources(100, clock, SingleWindow),
+        AutoconvertSettings::default(),
+    );
+    pipeline.handle(&InputEvent::Hotkey(HotkeyCommand::ToggleAutoconvert));
+    type_text(&mut pipeline, "ghbdtn", LayoutTag::En);
+    hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord);
+    elapsed_ms.store(60_000, Ordering::Relaxed);
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord),
+        [
+            replace(6, "", "ghbdtn"),
+            Effect::SwitchLayout(LayoutTag::En)
+        ]
+    );
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord),
+        [
+            replace(6, "", "привет"),
+            Effect::SwitchLayout(LayoutTag::Ru)
+        ]
+    );
+}
+
+#[test]
+fn repeated_convert_extends_by_the_previous_word() {
+    let mut pipeline = manual_pipeline();
+    pipeline
+        .state_mut()
+        .set_convert_repeat(ConvertRepeatMode::Extend, Duration::from_secs(60));
+    type_text(&mut pipeline, "мир ", LayoutTag::Ru);
+    type_text(&mut pipeline, "ghbdtn", LayoutTag::En);
+    hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord);
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastWord),
+        [replace(10, "", "vbh привет")]
+    );
+    assert_eq!(pipeline.journal().text(), "vbh привет");
+}
+
+#[test]
+fn undo_restores_the_autoconverted_word() {
+    let mut pipeline = pipeline();
+    type_text(&mut pipeline, "ghbdtn ", LayoutTag::En);
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::UndoConversion),
+        [
+            replace(6, " ", "ghbdtn"),
+            Effect::SwitchLayout(LayoutTag::En)
+        ]
+    );
+    assert_eq!(pipeline.journal().text(), "ghbdtn ");
+    assert_eq!(hotkey(&mut pipeline, HotkeyCommand::UndoConversion), []);
+}
+
+#[test]
+fn phrase_hotkey_converts_the_wrong_layout_words() {
+    let mut pipeline = manual_pipeline();
+    type_text(&mut pipeline, "ghbdtn rjynhjkm", LayoutTag::En);
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastPhrase),
+        [
+            replace(15, "", "привет контроль"),
+            Effect::SwitchLayout(LayoutTag::Ru)
+        ]
+    );
+}
+
+/// Static tables that note which runs they were asked to type again, and in which layout.
+#[derive(Debug, Default)]
+struct RecordingLayouts(Arc<Mutex<Vec<(String, LayoutTag)>>>);
+
+impl LayoutConverter for RecordingLayouts {
+    fn redecode(&self, runs: &[InputRun], layout: LayoutTag) -> Option<String> {
+        let mut seen = self.0.lock().expect("recording lock");
+        seen.extend(runs.iter().map(|run| (run.text.clone(), layout)));
+        None
+    }
+}
+
+#[test]
+fn phrase_hotkey_converts_each_word_from_its_own_layout() {
+    let mut pipeline = manual_pipeline();
+    let layouts = RecordingLayouts::default();
+    let seen = Arc::clone(&layouts.0);
+    pipeline.state_mut().set_layouts(layouts);
+    type_text(&mut pipeline, "ghbdtn ", LayoutTag::En);
+    type_text(&mut pipeline, "руддщ", LayoutTag::Ru);
+
+    let effects = hotkey(&mut pipeline, HotkeyCommand::ConvertLastPhrase);
+
+    assert_eq!(effects.first(), Some(&replace(12, "", "привет hello")));
+    assert_eq!(
+        *seen.lock().expect("recording lock"),
+        [
+            ("ghbdtn".to_string(), LayoutTag::En),
+            ("руддщ".to_string(), LayoutTag::Ru)
+        ]
+    );
+}
+
+#[test]
+fn phrase_stops_at_the_focus_change() {
+    let start = Instant::now();
+    let elapsed_ms = Arc::new(AtomicU64::new(0));
+    let clock_ms = Arc::clone(&elapsed_ms);
+    let clock = move || start + Duration::from_millis(clock_ms.load(Ordering::Relaxed));
+    let mut pipeline = TypingPipeline::new(
+        InputJournal::with_sources(100, clock, SingleWindow),
+        AutoconvertSettings::default(),
+    );
+    pipeline.handle(&InputEvent::Hotkey(HotkeyCommand::ToggleAutoconvert));
+    let focus = |window| {
+        InputEvent::FocusChanged(Some(Focus {
+            window: WindowId(window),
+            control: None,
+        }))
+    };
+    pipeline.handle(&focus(1));
+    type_text(&mut pipeline, "ghbdtn", LayoutTag::En);
+    elapsed_ms.store(100, Ordering::Relaxed);
+    pipeline.handle(&focus(2));
+    elapsed_ms.store(200, Ordering::Relaxed);
+    pipeline.handle(&focus(1));
+    type_text(&mut pipeline, " rjynhjkm", LayoutTag::En);
+    assert_eq!(pipeline.journal().text(), "ghbdtn rjynhjkm");
+
+    assert_eq!(
+        hotkey(&mut pipeline, HotkeyCommand::ConvertLastPhrase),
+        [
+            replace(8, "", "контроль"),
+            Effect::SwitchLayout(LayoutTag::Ru)
+        ]
+    );
+}
+
+#[test]
+fn failed_effect_drops_the_journal_and_undo() {
+    let mut journal = InputJournal::new(100);
+    let mut state = TypingState::default();
+    for ch in "ghbdtn ".chars() {
+        state.handle(&mut journal, &key(&ch.to_string(), LayoutTag::En));
+    }
+
+    state.effect_failed(&mut journal);
+
+    assert_eq!(journal.text(), "");
+    assert_eq!(
+        state.run_hotkey(&mut journal, HotkeyCommand::UndoConversion),
+        []
+    );
+}